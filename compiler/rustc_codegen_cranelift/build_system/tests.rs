@@ -1,13 +1,14 @@
+use std::env;
 use std::ffi::OsStr;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::build_sysroot;
 use crate::config;
 use crate::path::{Dirs, RelPath};
 use crate::prepare::{apply_patches, GitRepo};
-use crate::rustc_info::get_default_sysroot;
+use crate::rustc_info::{get_default_sysroot, get_file_name};
 use crate::shared_utils::rustflags_from_env;
 use crate::utils::{spawn_and_wait, CargoProject, Compiler, LogGroup};
 use crate::{CodegenBackend, SysrootKind};
@@ -67,6 +68,43 @@ const fn jit_bin(config: &'static str, source: &'static str, args: &'static str)
         "example/mini_core_hello_world.rs",
         &["abc", "bcd"],
     ),
+    TestCase::custom("aot.big_endian_statics", &|runner| {
+        spawn_and_wait(runner.rustc_command_for_target(
+            "s390x-unknown-linux-gnu",
+            ["example/big-endian-statics.rs", "--emit", "obj"],
+        ));
+
+        let obj =
+            fs::read(BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("big-endian-statics.o"))
+                .unwrap();
+        let contains = |bytes: &[u8]| obj.windows(bytes.len()).any(|window| window == bytes);
+        assert!(contains(&0x01020304u32.to_be_bytes()), "u32 static isn't big-endian");
+        assert!(contains(&0x1122334455667788u64.to_be_bytes()), "u64 static isn't big-endian");
+        assert!(!contains(&0x01020304u32.to_le_bytes()), "u32 static is little-endian");
+    }),
+    TestCase::custom("aot.s390x_debuginfo", &|runner| {
+        // The debuginfo is read using readelf
+        if !cfg!(target_os = "linux") {
+            return;
+        }
+        spawn_and_wait(runner.rustc_command_for_target(
+            "s390x-unknown-linux-gnu",
+            ["example/s390x-debuginfo.rs", "--emit", "obj"],
+        ));
+        let obj_path = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("s390x-debuginfo.o");
+
+        // %r15 is the stack pointer and has DWARF register number 15.
+        let entries = debug_info_entries(&obj_path);
+        let subprogram = entries
+            .iter()
+            .find(|entry| {
+                entry.contains("(DW_TAG_subprogram)") && entry.contains("frame_base_probe")
+            })
+            .unwrap_or_else(|| panic!("no subprogram for frame_base_probe:\n{}", entries.concat()));
+        assert!(subprogram.contains("DW_OP_reg15"), "wrong frame base:\n{subprogram}");
+        let frames = readelf(&["--debug-dump=frames"], &obj_path);
+        assert!(frames.contains("DW_CFA_def_cfa: r15"), "wrong CFA register:\n{frames}");
+    }),
 ];
 
 const BASE_SYSROOT_SUITE: &[TestCase] = &[
@@ -91,6 +129,7 @@ const fn jit_bin(config: &'static str, source: &'static str, args: &'static str)
         &[],
     ),
     TestCase::build_bin_and_run("aot.float-minmax-pass", "example/float-minmax-pass.rs", &[]),
+    TestCase::build_bin_and_run("aot.float-casts", "example/float-casts.rs", &[]),
     TestCase::build_bin_and_run("aot.mod_bench", "example/mod_bench.rs", &[]),
     TestCase::build_bin_and_run("aot.issue-72793", "example/issue-72793.rs", &[]),
     TestCase::build_bin("aot.issue-59326", "example/issue-59326.rs"),
@@ -99,6 +138,922 @@ const fn jit_bin(config: &'static str, source: &'static str, args: &'static str)
         runner.run_out_command("polymorphize_coroutine", &[]);
     }),
     TestCase::build_bin_and_run("aot.neon", "example/neon.rs", &[]),
+    TestCase::custom("aot.emit_asm", &|runner| {
+        runner.run_rustc(["example/emit-asm.rs", "--emit", "asm", "-Ccodegen-units=1"]);
+        let asm_file = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("emit-asm.s");
+        let asm = fs::read_to_string(&asm_file).unwrap();
+        assert!(
+            asm.contains("emit_asm_test_function:"),
+            "function label missing from {}:\n{asm}",
+            asm_file.display(),
+        );
+    }),
+    TestCase::custom("aot.used_static", &|runner| {
+        // FIXME also test Mach-O, COFF doesn't yet retain #[used(linker)] statics
+        if !runner.is_native || !runner.target_compiler.triple.contains("linux") {
+            return;
+        }
+        runner.run_rustc(["example/used-static.rs", "--emit", "obj", "-Cpanic=abort"]);
+        let out_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs);
+        let bin_path = out_dir.join("used-static");
+        let mut cc = Command::new(env::var_os("CC").unwrap_or_else(|| "cc".into()));
+        cc.args(["-nostdlib", "-static", "-Wl,--gc-sections", "-Wl,-e,used_static_entry"])
+            .arg(out_dir.join("used-static.o"))
+            .arg("-o")
+            .arg(&bin_path);
+        spawn_and_wait(cc);
+        let bin = fs::read(bin_path).unwrap();
+        assert!(
+            bin.windows(16).any(|window| window == b"\x7fused-by-linker\x7f"),
+            "#[used(linker)] static was removed by the linker",
+        );
+    }),
+    TestCase::custom("aot.dedup_const", &|runner| {
+        runner.run_rustc(["example/dedup-const.rs", "--emit", "obj", "-Ccodegen-units=1"]);
+        let obj_path = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("dedup-const.o");
+        let obj = fs::read(obj_path).unwrap();
+        let copies = obj.windows(13).filter(|window| *window == b"\x7fdedup-const\x7f").count();
+        assert_eq!(copies, 1, "identical constants were not deduplicated");
+    }),
+    TestCase::custom("aot.function_sections", &|runner| {
+        runner.run_rustc(["example/function-sections.rs", "-Zfunction-sections"]);
+        runner.run_out_command("function-sections", &[]);
+        // Only GNU-style linkers are known to GC unused sections by default
+        if !runner.target_compiler.triple.contains("linux") {
+            return;
+        }
+        let bin_path = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("function-sections");
+        let bin = fs::read(bin_path).unwrap();
+        let contains = |name: &[u8]| bin.windows(name.len()).any(|window| window == name);
+        assert!(contains(b"function_sections_used"), "used function is missing");
+        assert!(!contains(b"function_sections_unused"), "unused function was not removed");
+    }),
+    TestCase::custom("aot.incr_cgu_reuse", &|runner| {
+        let incr_dir = RelPath::BUILD.join("incr_cgu_reuse");
+        incr_dir.ensure_fresh(&runner.dirs);
+        let incremental = format!("-Cincremental={}", incr_dir.to_path(&runner.dirs).display());
+        // rustc_partition_reused and rustc_partition_codegened turn into errors when the actual
+        // reuse of codegen units differs from the expected reuse.
+        for cfg in ["rpass1", "rpass2"] {
+            runner.run_rustc([
+                "example/incr-cgu-reuse.rs",
+                incremental.as_str(),
+                "-Cllvm-args=disable_incr_cache=false",
+                "-Zquery-dep-graph",
+                "--check-cfg=cfg(rpass1,rpass2)",
+                "--cfg",
+                cfg,
+            ]);
+            runner.run_out_command("incr-cgu-reuse", &[]);
+        }
+    }),
+    TestCase::custom("aot.deterministic_codegen", &|runner| {
+        runner.run_rustc([
+            "example/deterministic-codegen.rs",
+            "-Cllvm-args=verify_deterministic_codegen=true",
+        ]);
+        runner.run_out_command("deterministic-codegen", &[]);
+    }),
+    TestCase::custom("aot.asan_checks", &|runner| {
+        // AddressSanitizer is only supported on a couple of targets
+        if !matches!(
+            &*runner.target_compiler.triple,
+            "x86_64-unknown-linux-gnu" | "aarch64-unknown-linux-gnu"
+        ) {
+            return;
+        }
+        runner.run_rustc([
+            "example/asan-checks.rs",
+            "--emit",
+            "obj",
+            "-Ccodegen-units=1",
+            "-Zsanitizer=address",
+        ]);
+        let obj_path = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("asan-checks.o");
+        let obj = fs::read(obj_path).unwrap();
+        let contains = |name: &[u8]| obj.windows(name.len()).any(|window| window == name);
+        assert!(contains(b"__asan_load4"), "load isn't checked");
+        assert!(contains(b"__asan_store8"), "store isn't checked");
+        assert!(!contains(b"__asan_load2"), "#[no_sanitize(address)] is ignored");
+    }),
+    TestCase::custom("aot.stack_protector", &|runner| {
+        // `__stack_chk_guard` and `__stack_chk_fail` are provided by the libc
+        if !runner.target_compiler.triple.contains("linux") {
+            return;
+        }
+        runner.run_rustc(["example/stack-protector.rs", "-Zstack-protector=strong"]);
+        runner.run_out_command("stack-protector", &[]);
+
+        let output = runner.out_command("stack-protector", &["overflow"]).output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "buffer overflow was not detected:\n{stderr}");
+        assert!(
+            !stderr.contains("stack protector didn't detect the buffer overflow"),
+            "buffer overflow was not detected:\n{stderr}",
+        );
+        // glibc prints a message in `__stack_chk_fail` before aborting, musl crashes right away.
+        if runner.target_compiler.triple.ends_with("linux-gnu") {
+            assert!(
+                stderr.contains("stack smashing detected"),
+                "__stack_chk_fail wasn't called:\n{stderr}",
+            );
+        }
+    }),
+    TestCase::custom("aot.black_box", &|runner| {
+        runner.run_rustc(["example/black-box.rs", "--emit", "llvm-ir", "-Copt-level=3"]);
+        let clif_path = BUILD_EXAMPLE_OUT_DIR
+            .to_path(&runner.dirs)
+            .join("black-box.clif")
+            .join("black_box_barrier.opt.clif");
+        let clif = fs::read_to_string(&clif_path).unwrap();
+        assert!(
+            !clif.contains("iconst.i32 43"),
+            "black_box got constant folded in {}:\n{clif}",
+            clif_path.display(),
+        );
+        assert!(!clif.contains("fence"), "black_box emitted a fence:\n{clif}");
+    }),
+    TestCase::custom("aot.volatile", &|runner| {
+        runner.run_rustc(["example/volatile.rs", "--emit", "llvm-ir", "-Copt-level=3"]);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("volatile.clif");
+        let store_clif =
+            fs::read_to_string(clif_dir.join("volatile_store_twice.opt.clif")).unwrap();
+        let stores = store_clif.lines().filter(|line| line.trim().starts_with("store")).count();
+        assert_eq!(stores, 2, "volatile store got removed:\n{store_clif}");
+        let load_clif = fs::read_to_string(clif_dir.join("volatile_load_twice.opt.clif")).unwrap();
+        let loads = load_clif.lines().filter(|line| line.contains(" = atomic_load.i32")).count();
+        assert_eq!(loads, 2, "volatile load got merged:\n{load_clif}");
+        assert!(!load_clif.contains("fence"), "volatile load emitted a fence:\n{load_clif}");
+    }),
+    TestCase::custom("aot.small_copy", &|runner| {
+        runner.run_rustc(["example/small-copy.rs", "--emit", "llvm-ir", "-Copt-level=3"]);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("small-copy.clif");
+        for func in ["small_copy_nonoverlapping", "small_copy"] {
+            let clif = fs::read_to_string(clif_dir.join(format!("{func}.opt.clif"))).unwrap();
+            assert!(!clif.contains(" call "), "copy in {func} wasn't inlined:\n{clif}");
+            assert!(clif.contains(" = load.i64"), "missing load in {func}:\n{clif}");
+            assert!(clif.lines().any(|line| line.trim().starts_with("store")), "{clif}");
+        }
+    }),
+    TestCase::custom("aot.small_memset", &|runner| {
+        runner.run_rustc(["example/small-memset.rs", "--emit", "llvm-ir", "-Copt-level=3"]);
+        let clif = fs::read_to_string(
+            BUILD_EXAMPLE_OUT_DIR
+                .to_path(&runner.dirs)
+                .join("small-memset.clif")
+                .join("small_zero_fill.opt.clif"),
+        )
+        .unwrap();
+        assert!(!clif.contains(" call "), "memset wasn't inlined:\n{clif}");
+        let stores = clif.lines().filter(|line| line.trim().starts_with("store")).count();
+        assert_eq!(stores, 2, "expected two 8 byte stores:\n{clif}");
+    }),
+    TestCase::custom("aot.small_memcmp", &|runner| {
+        runner.run_rustc(["example/small-memcmp.rs", "--emit", "llvm-ir", "-Copt-level=3"]);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("small-memcmp.clif");
+        for func in ["arrays_eq", "arrays_cmp"] {
+            let clif = fs::read_to_string(clif_dir.join(format!("{func}.opt.clif"))).unwrap();
+            assert!(!clif.contains(" call "), "comparison in {func} wasn't inlined:\n{clif}");
+            assert!(!clif.contains("brif"), "comparison in {func} contains a loop:\n{clif}");
+        }
+    }),
+    TestCase::custom("aot.opt_level", &|runner| {
+        for (opt_level, cranelift_opt_level) in [
+            ("0", "none"),
+            ("1", "none"),
+            ("2", "speed"),
+            ("3", "speed"),
+            ("s", "speed_and_size"),
+            ("z", "speed_and_size"),
+        ] {
+            runner.run_rustc([
+                "example/opt-level.rs",
+                "--emit",
+                "llvm-ir",
+                &format!("-Copt-level={opt_level}"),
+            ]);
+            let clif = fs::read_to_string(
+                BUILD_EXAMPLE_OUT_DIR
+                    .to_path(&runner.dirs)
+                    .join("opt-level.clif")
+                    .join("opt_level_probe.unopt.clif"),
+            )
+            .unwrap();
+            let expected = format!("set opt_level={cranelift_opt_level}\n");
+            assert!(clif.contains(&expected), "-Copt-level={opt_level} expected {expected}{clif}");
+        }
+    }),
+    TestCase::custom("aot.cranelift_flags", &|runner| {
+        let flags = "enable_alias_analysis=false,enable_nan_canonicalization=true";
+        runner.run_rustc([
+            "example/opt-level.rs",
+            "--emit",
+            "llvm-ir",
+            &format!("-Cllvm-args=cranelift_flags={flags}"),
+        ]);
+        let clif = fs::read_to_string(
+            BUILD_EXAMPLE_OUT_DIR
+                .to_path(&runner.dirs)
+                .join("opt-level.clif")
+                .join("opt_level_probe.unopt.clif"),
+        )
+        .unwrap();
+        assert!(clif.contains("set enable_alias_analysis=false\n"), "{clif}");
+        assert!(clif.contains("set enable_nan_canonicalization=true\n"), "{clif}");
+
+        let output = runner
+            .rustc_command(["example/opt-level.rs", "-Cllvm-args=cranelift_flags=no_such_flag=1"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "unknown Cranelift flag was accepted");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("unknown Cranelift flag `no_such_flag`"), "{stderr}");
+    }),
+    TestCase::custom("aot.emit_clif", &|runner| {
+        let dump_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("emit_clif_dump");
+        let _ = fs::remove_dir_all(&dump_dir);
+        runner.run_rustc([
+            "example/emit-clif.rs",
+            &format!("-Cllvm-args=emit_clif={}", dump_dir.display()),
+        ]);
+        let clif_file = fs::read_dir(&dump_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| {
+                let file_name = path.file_name().unwrap().to_str().unwrap();
+                file_name.starts_with("_ZN9emit_clif15emit_clif_probe")
+                    && file_name.ends_with(".clif")
+            })
+            .unwrap_or_else(|| panic!("no clif file for emit_clif_probe in {dump_dir:?}"));
+        let clif = fs::read_to_string(&clif_file).unwrap();
+        assert!(clif.contains("; symbol _ZN9emit_clif15emit_clif_probe"), "{clif}");
+        // MIR terminators are recorded as comments
+        assert!(clif.contains("; return"), "{clif}");
+    }),
+    TestCase::custom("aot.timings", &|runner| {
+        let dump_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("timings_clif_dump");
+        let _ = fs::remove_dir_all(&dump_dir);
+        let output = runner
+            .rustc_command([
+                "example/timings.rs",
+                "-Cllvm-args=timings=2",
+                &format!("-Cllvm-args=emit_clif={}", dump_dir.display()),
+            ])
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{stderr}");
+
+        let mut lines = stderr.lines().skip_while(|line| !line.starts_with("cg_clif timings: "));
+        let summary = lines.next().unwrap_or_else(|| panic!("no timings summary in {stderr}"));
+        let function_count = summary
+            .strip_prefix("cg_clif timings: compiled ")
+            .and_then(|rest| rest.split(' ').next())
+            .and_then(|count| count.parse::<usize>().ok())
+            .unwrap_or_else(|| panic!("malformed timings summary `{summary}`"));
+        // Every compiled function gets its own clif file
+        let compiled_functions = fs::read_dir(&dump_dir).unwrap().count();
+        assert_eq!(function_count, compiled_functions, "{stderr}");
+        assert!(function_count >= 3, "{stderr}");
+        // Only the two slowest functions are listed
+        assert_eq!(lines.filter(|line| line.contains("ms  ")).count(), 2, "{stderr}");
+    }),
+    TestCase::custom("aot.relocation_model", &|runner| {
+        runner.run_rustc([
+            "example/relocation-model.rs",
+            "--emit",
+            "obj,link",
+            "-Crelocation-model=static",
+        ]);
+        runner.run_out_command("relocation-model", &[]);
+
+        let triple = &*runner.target_compiler.triple;
+        if triple.starts_with("x86_64-") && triple.contains("linux") {
+            let relocs = text_relocation_types(
+                &BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("relocation-model.o"),
+            );
+            assert!(
+                relocs.iter().any(|r| r == "R_X86_64_64"),
+                "no absolute relocations: {relocs:?}",
+            );
+            assert!(
+                !relocs.iter().any(|r| r.contains("GOTPCREL")),
+                "GOT relocations despite -Crelocation-model=static: {relocs:?}",
+            );
+        }
+    }),
+    TestCase::custom("aot.code_model", &|runner| {
+        let probe_clif = || {
+            fs::read_to_string(
+                BUILD_EXAMPLE_OUT_DIR
+                    .to_path(&runner.dirs)
+                    .join("code-model.clif")
+                    .join("code_model_probe.unopt.clif"),
+            )
+            .unwrap()
+        };
+
+        runner.run_rustc(["example/code-model.rs", "--emit", "llvm-ir,link"]);
+        let clif = probe_clif();
+        assert!(clif.contains(" = colocated "), "local function should be colocated:\n{clif}");
+
+        runner.run_rustc([
+            "example/code-model.rs",
+            "--emit",
+            "llvm-ir,link",
+            "-Ccode-model=large",
+            "-Crelocation-model=static",
+        ]);
+        let clif = probe_clif();
+        assert!(!clif.contains("colocated"), "-Ccode-model=large is ignored:\n{clif}");
+        runner.run_out_command("code-model", &[]);
+
+        let output = runner
+            .rustc_command([
+                "example/code-model.rs",
+                "-Ccode-model=large",
+                "-Crelocation-model=pic",
+            ])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "-Ccode-model=large with pic was accepted");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("`-Ccode-model=large` is only supported by Cranelift"), "{stderr}");
+    }),
+    TestCase::custom("aot.cdylib_exports", &|runner| {
+        runner.run_rustc(["example/cdylib-exports.rs"]);
+        let triple = &*runner.target_compiler.triple;
+        if !(triple.starts_with("x86_64-") || triple.starts_with("aarch64-"))
+            || !triple.contains("linux")
+        {
+            return;
+        }
+
+        let dylib = get_file_name(&runner.target_compiler.rustc, "cdylib_exports", "cdylib");
+        let symbols =
+            defined_symbols("--dyn-syms", &BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join(dylib));
+        for (name, binding) in [
+            ("cdylib_exported_fn", "GLOBAL"),
+            ("cdylib_renamed_fn", "GLOBAL"),
+            ("CDYLIB_EXPORTED_STATIC", "GLOBAL"),
+            ("cdylib_weak_fn", "WEAK"),
+        ] {
+            assert!(
+                symbols.contains(&(name.to_owned(), binding.to_owned(), "DEFAULT".to_owned())),
+                "{name} isn't exported correctly: {symbols:?}",
+            );
+        }
+        assert!(!symbols.iter().any(|(name, _, _)| name == "renamed_fn"), "{symbols:?}");
+    }),
+    TestCase::custom("aot.linkage", &|runner| {
+        runner.run_rustc(["example/linkage-lib.rs", "--emit", "obj,link"]);
+        runner.run_rustc(["example/linkage.rs"]);
+        runner.run_out_command("linkage", &[]);
+
+        if runner.target_compiler.triple.contains("linux") {
+            let symbols = defined_symbols(
+                "--syms",
+                &BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("linkage-lib.o"),
+            );
+            assert!(
+                !symbols.iter().any(|(name, _, _)| name == "linkage_available_externally"),
+                "available_externally function was emitted: {symbols:?}",
+            );
+        }
+    }),
+    TestCase::custom("aot.extern_weak", &|runner| {
+        // FIXME COFF doesn't support weak undefined symbols
+        if runner.target_compiler.triple.contains("windows") {
+            return;
+        }
+        runner.run_rustc(["example/extern-weak.rs"]);
+        runner.run_out_command("extern-weak", &[]);
+    }),
+    TestCase::custom("aot.optimize_attr", &|runner| {
+        runner.run_rustc(["example/optimize-attr.rs", "--emit", "llvm-ir", "-Copt-level=3"]);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("optimize-attr.clif");
+        for (func, opt_level) in
+            [("optimize_for_size", "speed_and_size"), ("optimize_default", "speed")]
+        {
+            let clif = fs::read_to_string(clif_dir.join(format!("{func}.opt.clif"))).unwrap();
+            let expected = format!("set opt_level={opt_level}\n");
+            assert!(clif.contains(&expected), "{func} expected {expected}{clif}");
+        }
+    }),
+    TestCase::custom("aot.i128_c_abi", &|runner| {
+        // The padding needed to match the C ABI is only computed for x86_64 System V
+        if !runner.is_native
+            || !runner.target_compiler.triple.starts_with("x86_64")
+            || runner.target_compiler.triple.contains("windows")
+        {
+            return;
+        }
+        let c_obj = runner.compile_c_object("i128-c-abi");
+        runner.run_rustc(["example/i128-c-abi.rs", &format!("-Clink-arg={}", c_obj.display())]);
+        runner.run_out_command("i128-c-abi", &[]);
+    }),
+    TestCase::custom("aot.sysv_struct_return", &|runner| {
+        if !runner.is_native
+            || !runner.target_compiler.triple.starts_with("x86_64")
+            || runner.target_compiler.triple.contains("windows")
+        {
+            return;
+        }
+        let c_obj = runner.compile_c_object("sysv-struct-return");
+        runner.run_rustc([
+            "example/sysv-struct-return.rs",
+            &format!("-Clink-arg={}", c_obj.display()),
+        ]);
+        runner.run_out_command("sysv-struct-return", &[]);
+    }),
+    TestCase::custom("aot.transparent_abi", &|runner| {
+        runner.run_rustc(["example/transparent-abi.rs", "--emit", "llvm-ir,link"]);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("transparent-abi.clif");
+        for func in ["transparent_len", "transparent_rewrap"] {
+            let clif = fs::read_to_string(clif_dir.join(format!("{func}.unopt.clif"))).unwrap();
+            assert!(!clif.contains("explicit_slot"), "{func} spills to the stack:\n{clif}");
+        }
+        runner.run_out_command("transparent-abi", &[]);
+    }),
+    TestCase::custom("aot.c_variadic_def", &|runner| {
+        if !runner.is_native
+            || !runner.target_compiler.triple.starts_with("x86_64")
+            || runner.target_compiler.triple.contains("windows")
+        {
+            return;
+        }
+        let c_obj = runner.compile_c_object("c-variadic-def");
+        runner.run_rustc(["example/c-variadic-def.rs", &format!("-Clink-arg={}", c_obj.display())]);
+        runner.run_out_command("c-variadic-def", &[]);
+    }),
+    TestCase::custom("aot.vectorcall", &|runner| {
+        if !runner.is_native
+            || !runner.target_compiler.triple.starts_with("x86_64")
+            || !runner.target_compiler.triple.contains("windows")
+        {
+            return;
+        }
+        runner.run_rustc(["example/vectorcall.rs"]);
+        runner.run_out_command("vectorcall", &[]);
+    }),
+    TestCase::custom("aot.unsupported_intrinsic", &|runner| {
+        runner.run_rustc(["example/unsupported-intrinsic.rs"]);
+        runner.run_out_command("unsupported-intrinsic", &[]);
+
+        let output = runner
+            .rustc_command(["example/unsupported-intrinsic.rs"])
+            .env("CG_CLIF_UNSUPPORTED_INTRINSICS", "vtable_size")
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "unsupported intrinsic was accepted");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("error: intrinsic `vtable_size` is not yet supported by Cranelift"),
+            "{stderr}"
+        );
+        assert!(stderr.contains("example/unsupported-intrinsic.rs:"), "{stderr}");
+        assert!(!stderr.contains("internal compiler error"), "{stderr}");
+    }),
+    TestCase::custom("aot.fallback_intrinsic", &|runner| {
+        let mut rustc =
+            runner.rustc_command(["example/fallback-intrinsic.rs", "--emit", "llvm-ir,link"]);
+        rustc.env("CG_CLIF_UNSUPPORTED_INTRINSICS", "likely");
+        spawn_and_wait(rustc);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("fallback-intrinsic.clif");
+        for (func, intrinsic) in [("swap_arrays", "typed_swap"), ("is_likely_positive", "likely")] {
+            let clif = fs::read_to_string(clif_dir.join(format!("{func}.unopt.clif"))).unwrap();
+            // Calls are annotated with the `Instance` of the callee.
+            let fallback_path = format!("intrinsics::{intrinsic})");
+            assert!(
+                clif.lines()
+                    .any(|line| line.contains("Item(DefId(") && line.contains(&fallback_path)),
+                "{func} doesn't call the fallback body of {intrinsic}:\n{clif}"
+            );
+        }
+        runner.run_out_command("fallback-intrinsic", &[]);
+    }),
+    TestCase::custom("aot.unreachable_hint", &|runner| {
+        runner.run_rustc(["example/unreachable-hint.rs", "-O", "--emit", "llvm-ir,link"]);
+        let clif = fs::read_to_string(
+            BUILD_EXAMPLE_OUT_DIR
+                .to_path(&runner.dirs)
+                .join("unreachable-hint.clif")
+                .join("classify.unopt.clif"),
+        )
+        .unwrap();
+        // A single branch is enough to select between the two reachable arms.
+        let branches = clif.lines().filter(|line| line.contains("brif")).count();
+        assert!(
+            branches == 1 && !clif.contains("br_table"),
+            "the unreachable arm of classify wasn't removed:\n{clif}"
+        );
+        runner.run_out_command("unreachable-hint", &[]);
+    }),
+    TestCase::custom("aot.branch_hints", &|runner| {
+        runner.run_rustc(["example/branch-hints.rs", "--emit", "llvm-ir,link"]);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("branch-hints.clif");
+        for func in ["likely_nonzero", "unlikely_zero"] {
+            let clif = fs::read_to_string(clif_dir.join(format!("{func}.unopt.clif"))).unwrap();
+            // Only the call to `unexpected` is in a cold block, which Cranelift moves to the end
+            // of the function, making the expected branch the fallthrough.
+            assert_eq!(calls_in_cold_blocks(&clif, "expected"), [false], "{func}:\n{clif}");
+            assert_eq!(calls_in_cold_blocks(&clif, "unexpected"), [true], "{func}:\n{clif}");
+        }
+        runner.run_out_command("branch-hints", &[]);
+    }),
+    TestCase::custom("aot.prefetch", &|runner| {
+        // Keep the object file with the inline asm wrappers around.
+        runner.run_rustc(["example/prefetch.rs", "-Csave-temps"]);
+        if runner.target_compiler.triple.starts_with("x86_64") {
+            let out_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs);
+            let asm_objects = fs::read_dir(&out_dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    let name = path.file_name().unwrap().to_str().unwrap();
+                    name.starts_with("prefetch.") && name.ends_with(".asm.o")
+                })
+                .map(|path| fs::read(path).unwrap())
+                .collect::<Vec<_>>();
+            // `prefetcht0 [rax]`, `prefetcht1 [rax]` and `prefetchnta [rax]` for locality 3, 2 and
+            // 0. Locality 1 (`prefetcht2 [rax]`) is only used by `_mm_prefetch`.
+            for (instruction, encoding) in [
+                ("prefetcht0", [0x0f, 0x18, 0x08]),
+                ("prefetcht1", [0x0f, 0x18, 0x10]),
+                ("prefetcht2", [0x0f, 0x18, 0x18]),
+                ("prefetchnta", [0x0f, 0x18, 0x00]),
+            ] {
+                assert!(
+                    asm_objects
+                        .iter()
+                        .any(|object| object.windows(3).any(|bytes| bytes == encoding)),
+                    "{instruction} not emitted",
+                );
+            }
+        }
+        runner.run_out_command("prefetch", &[]);
+    }),
+    TestCase::custom("aot.breakpoint", &|runner| {
+        let triple = &*runner.target_compiler.triple;
+        if !triple.starts_with("x86_64") || !triple.contains("linux") {
+            return;
+        }
+        runner.run_rustc(["example/breakpoint.rs"]);
+        runner.run_out_command("breakpoint", &[]);
+    }),
+    TestCase::custom("aot.overflow_checks", &|runner| {
+        runner.run_rustc(["example/overflow-checks.rs", "-Cdebug-assertions"]);
+        runner.run_out_command("overflow-checks", &[]);
+        for (op, msg) in [
+            ("add_i32", "attempt to add with overflow"),
+            ("sub_u8", "attempt to subtract with overflow"),
+            ("mul_i64", "attempt to multiply with overflow"),
+            ("neg_i16", "attempt to negate with overflow"),
+            ("add_i128", "attempt to add with overflow"),
+            ("sub_u128", "attempt to subtract with overflow"),
+            ("mul_u128", "attempt to multiply with overflow"),
+            ("shl_u32", "attempt to shift left with overflow"),
+            ("shr_i64", "attempt to shift right with overflow"),
+            ("shl_u128", "attempt to shift left with overflow"),
+            ("shr_i128", "attempt to shift right with overflow"),
+        ] {
+            runner.expect_panic("overflow-checks", &[op], msg);
+        }
+    }),
+    TestCase::custom("aot.div_checks", &|runner| {
+        // Division checks don't depend on overflow checks being enabled.
+        runner.run_rustc(["example/div-checks.rs", "-Coverflow-checks=off"]);
+        runner.run_out_command("div-checks", &[]);
+        for (op, msg) in [
+            ("div_u32", "attempt to divide by zero"),
+            ("div_i32", "attempt to divide with overflow"),
+            ("rem_u64", "attempt to calculate the remainder with a divisor of zero"),
+            ("rem_i32", "attempt to calculate the remainder with overflow"),
+            ("div_i8", "attempt to divide with overflow"),
+            ("div_u128", "attempt to divide by zero"),
+            ("div_i128", "attempt to divide with overflow"),
+            ("rem_i128", "attempt to calculate the remainder with overflow"),
+        ] {
+            runner.expect_panic("div-checks", &[op], msg);
+        }
+    }),
+    TestCase::custom("aot.exact_div", &|runner| {
+        runner.run_rustc(["example/exact-div.rs", "-Cdebug-assertions"]);
+        runner.run_out_command("exact-div", &[]);
+        runner.expect_panic(
+            "exact-div",
+            &["inexact"],
+            "exact_div requires the division to have no remainder",
+        );
+    }),
+    TestCase::custom("aot.offset_from", &|runner| {
+        runner.run_rustc(["example/offset-from.rs", "-Cdebug-assertions"]);
+        runner.run_out_command("offset-from", &[]);
+        runner.expect_panic(
+            "offset-from",
+            &["misaligned"],
+            "ptr::offset_from requires the distance between the pointers",
+        );
+    }),
+    TestCase::custom("aot.ptr_offset", &|runner| {
+        runner.run_rustc(["example/ptr-offset.rs", "-Cdebug-assertions"]);
+        runner.run_out_command("ptr-offset", &[]);
+        for op in ["offset", "add", "add_bytes"] {
+            runner.expect_panic(
+                "ptr-offset",
+                &[op],
+                "ptr::offset requires the byte offset to fit in an isize",
+            );
+        }
+    }),
+    TestCase::custom("aot.float_cast_mode", &|runner| {
+        let clif_path = BUILD_EXAMPLE_OUT_DIR
+            .to_path(&runner.dirs)
+            .join("float-cast-mode.clif")
+            .join("float_to_int.unopt.clif");
+        runner.run_rustc(["example/float-cast-mode.rs", "--emit", "llvm-ir"]);
+        let clif = fs::read_to_string(&clif_path).unwrap();
+        assert!(clif.contains("fcmp eq"), "saturating cast doesn't check for NaN:\n{clif}");
+
+        runner.run_rustc([
+            "example/float-cast-mode.rs",
+            "--emit",
+            "llvm-ir",
+            "-Zsaturating-float-casts=no",
+        ]);
+        let clif = fs::read_to_string(&clif_path).unwrap();
+        assert!(!clif.contains("fcmp eq"), "non-saturating cast checks for NaN:\n{clif}");
+    }),
+    TestCase::custom("jit.jit_api", &|runner| {
+        for mode in ["jit", "jit-lazy"] {
+            let mut jit_cmd = runner.rustc_command([
+                "-Zunstable-options",
+                &format!("-Cllvm-args=mode={mode}"),
+                "-Cprefer-dynamic",
+                "example/jit-api.rs",
+                "--cfg",
+                "jit",
+            ]);
+            jit_cmd.env("CG_CLIF_JIT_ARGS", mode);
+            spawn_and_wait(jit_cmd);
+        }
+    }),
+    TestCase::custom("jit.unsupported_intrinsic", &|runner| {
+        for mode in ["jit", "jit-lazy"] {
+            let output = runner
+                .rustc_command([
+                    "-Zunstable-options",
+                    &format!("-Cllvm-args=mode={mode}"),
+                    "-Cprefer-dynamic",
+                    "example/unsupported-intrinsic.rs",
+                ])
+                .env("CG_CLIF_UNSUPPORTED_INTRINSICS", "vtable_size")
+                .output()
+                .unwrap();
+            assert!(!output.status.success(), "unsupported intrinsic was accepted in {mode} mode");
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(
+                stderr.contains("error: intrinsic `vtable_size` is not yet supported by Cranelift"),
+                "{stderr}"
+            );
+        }
+    }),
+    TestCase::custom("aot.cgu_order", &|runner| {
+        let out_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs);
+        // Returns and removes the executable and the object files of all codegen units.
+        let take_outputs = || {
+            let mut outputs = fs::read_dir(&out_dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.file_name().unwrap().to_str().unwrap().starts_with("cgu-order"))
+                .map(|path| {
+                    let contents = fs::read(&path).unwrap();
+                    fs::remove_file(&path).unwrap();
+                    (path, contents)
+                })
+                .collect::<Vec<_>>();
+            outputs.sort();
+            outputs
+        };
+
+        let _ = take_outputs();
+        let build = || {
+            runner.run_rustc(["example/cgu-order.rs", "-Ccodegen-units=4", "-Csave-temps"]);
+            runner.run_out_command("cgu-order", &[]);
+            take_outputs()
+        };
+        let (first, second) = (build(), build());
+        let paths = |outputs: &[(PathBuf, Vec<u8>)]| {
+            outputs.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&first), paths(&second), "different files were produced");
+        assert!(first.len() > 2, "expected multiple codegen units: {:?}", paths(&first));
+        for ((path, first), (_, second)) in first.iter().zip(&second) {
+            assert!(first == second, "{} differs between builds", path.display());
+        }
+    }),
+    TestCase::custom("aot.macro_columns", &|runner| {
+        // The line tables are read using readelf
+        if !runner.target_compiler.triple.contains("linux") {
+            return;
+        }
+        runner.run_rustc(["example/macro-columns.rs", "--emit", "obj", "-Ccodegen-units=1"]);
+        let obj_path = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("macro-columns.o");
+        let rows = line_table_rows(&obj_path);
+
+        let source = fs::read_to_string("example/macro-columns.rs").unwrap();
+        let line_of = |marker: &str| {
+            source.lines().position(|line| line.contains(marker)).unwrap() as u64 + 1
+        };
+        let (macro_line, body_line) = (line_of("// macro body"), line_of("// function body"));
+        let macro_columns = rows.iter().filter(|&&(line, _)| line == macro_line);
+        let macro_columns = macro_columns.map(|&(_, column)| column).collect::<Vec<_>>();
+        assert!(
+            !macro_columns.is_empty() && macro_columns.iter().all(|&column| column == 0),
+            "rows for line {macro_line} inside the macro should have column 0: {rows:?}",
+        );
+        assert!(
+            rows.iter().any(|&(line, column)| line == body_line && column != 0),
+            "rows for line {body_line} outside the macro should have a column: {rows:?}",
+        );
+    }),
+    TestCase::custom("aot.variable_locations", &|runner| {
+        // The debuginfo is read using readelf
+        if !runner.target_compiler.triple.contains("linux") {
+            return;
+        }
+        runner.run_rustc(["example/variable-locations.rs", "--emit", "obj", "-Ccodegen-units=1"]);
+        let obj_path = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("variable-locations.o");
+        let entries = debug_info_entries(&obj_path);
+        for (tag, name) in [("DW_TAG_formal_parameter", "x"), ("DW_TAG_variable", "doubled")] {
+            let entry = entries
+                .iter()
+                .find(|entry| {
+                    entry.contains(&format!("({tag})"))
+                        && entry.lines().any(|line| {
+                            line.contains("DW_AT_name") && line.ends_with(&format!(": {name}"))
+                        })
+                })
+                .unwrap_or_else(|| panic!("no {tag} for `{name}`:\n{}", entries.concat()));
+            assert!(entry.contains("(location list)"), "`{name}` has no location list:\n{entry}");
+        }
+    }),
+    TestCase::custom("aot.inlined_subroutines", &|runner| {
+        // The debuginfo is read using readelf
+        if !runner.target_compiler.triple.contains("linux") {
+            return;
+        }
+        runner.run_rustc([
+            "example/inlined-subroutines.rs",
+            "--emit",
+            "obj",
+            "-Ccodegen-units=1",
+            "-O",
+            "-Zinline-mir",
+        ]);
+        let obj_path = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("inlined-subroutines.o");
+        let entries = debug_info_entries(&obj_path);
+        let abstract_subprogram = entries
+            .iter()
+            .find(|entry| {
+                entry.contains("(DW_TAG_subprogram)")
+                    && entry.contains("DW_AT_inline")
+                    && entry.contains(": inlined_helper\n")
+            })
+            .unwrap_or_else(|| panic!("no abstract subprogram:\n{}", entries.concat()));
+        // The entry starts with `<depth><offset>`, which references to it use as `<0xoffset>`.
+        let offset = abstract_subprogram.split(['<', '>']).nth(3).unwrap();
+        let inlined = entries
+            .iter()
+            .filter(|entry| {
+                entry.contains("(DW_TAG_inlined_subroutine)")
+                    && entry.contains(&format!("<0x{offset}>"))
+                    && entry.contains("DW_AT_ranges")
+            })
+            .count();
+        assert_eq!(inlined, 2, "expected two inlined copies:\n{}", entries.concat());
+    }),
+    TestCase::custom("aot.tail_calls", &|runner| {
+        runner.run_rustc(["example/tail-calls.rs", "--emit", "llvm-ir,link"]);
+        let clif = fs::read_to_string(
+            BUILD_EXAMPLE_OUT_DIR
+                .to_path(&runner.dirs)
+                .join("tail-calls.clif")
+                .join("tail_call_is_odd.unopt.clif"),
+        )
+        .unwrap();
+        // The result of the call is returned directly instead of jumping to the next block.
+        let lines = clif.lines().map(str::trim).collect::<Vec<_>>();
+        let returns_call_result = lines
+            .windows(2)
+            .any(|lines| lines[0].contains(" call fn") && lines[1].starts_with("return"));
+        assert!(returns_call_result, "tail call isn't followed by a return:\n{clif}");
+        runner.run_out_command("tail-calls", &[]);
+    }),
+    TestCase::custom("aot.cold_paths", &|runner| {
+        runner.run_rustc(["example/cold-paths.rs", "--emit", "llvm-ir,link"]);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("cold-paths.clif");
+        // Cranelift moves cold blocks to the end of the function, so the hot path falls through.
+        let cold_calls = [("unlikely_panic", "report_zero"), ("calls_cold_fn", "cold_fallback")];
+        for (func, callee) in cold_calls {
+            let clif = fs::read_to_string(clif_dir.join(format!("{func}.unopt.clif"))).unwrap();
+            assert_eq!(
+                calls_in_cold_blocks(&clif, callee),
+                [true],
+                "the call to {callee} in {func} isn't in a cold block:\n{clif}",
+            );
+        }
+        runner.run_out_command("cold-paths", &[]);
+    }),
+    TestCase::custom("aot.simd_locals", &|runner| {
+        runner.run_rustc(["example/simd-locals.rs", "--emit", "llvm-ir,link"]);
+        let clif = fs::read_to_string(
+            BUILD_EXAMPLE_OUT_DIR
+                .to_path(&runner.dirs)
+                .join("simd-locals.clif/simd_local_sum.unopt.clif"),
+        )
+        .unwrap();
+        assert!(!clif.contains("explicit_slot"), "simd_local_sum spills to the stack:\n{clif}");
+        runner.run_out_command("simd-locals", &[]);
+    }),
+    TestCase::custom("aot.target_feature_asm", &|runner| {
+        if !runner.target_compiler.triple.starts_with("x86_64") {
+            return;
+        }
+        runner.run_rustc(["example/target-feature-asm.rs", "--emit", "asm", "-Ccodegen-units=1"]);
+        let asm_file = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("target-feature-asm.s");
+        let asm = fs::read_to_string(&asm_file).unwrap();
+        let function_asm = |name: &str| {
+            asm.split("\n\n")
+                .find(|function| function.starts_with(&format!("{name}:\n")))
+                .unwrap_or_else(|| panic!("{name} missing from {}:\n{asm}", asm_file.display()))
+        };
+        let baseline = function_asm("baseline_add");
+        assert!(
+            baseline.contains("paddd") && !baseline.contains("vpaddd"),
+            "baseline_add should use the sse2 encoding:\n{baseline}",
+        );
+        let avx2 = function_asm("avx2_add");
+        assert!(avx2.contains("vpaddd"), "avx2_add should use the vex encoding:\n{avx2}");
+    }),
+    TestCase::custom("aot.readonly_ref_args", &|runner| {
+        runner.run_rustc(["example/readonly-ref-args.rs", "--emit", "llvm-ir,link"]);
+        let clif_dir = BUILD_EXAMPLE_OUT_DIR.to_path(&runner.dirs).join("readonly-ref-args.clif");
+        let read_clif =
+            |func: &str| fs::read_to_string(clif_dir.join(format!("{func}.unopt.clif"))).unwrap();
+        let clif = read_clif("readonly_read_twice");
+        assert!(
+            clif.lines().any(|line| line.contains("= load.i64 notrap aligned readonly ")),
+            "loads of x.1 aren't readonly:\n{clif}",
+        );
+        let clif = read_clif("readonly_read_cell");
+        assert!(!clif.contains("readonly"), "loads through &Cell are readonly:\n{clif}");
+        runner.run_out_command("readonly-ref-args", &[]);
+    }),
+    TestCase::custom("aot.asan_report", &|runner| {
+        // AddressSanitizer is only supported on a couple of targets
+        if !runner.is_native
+            || !matches!(
+                &*runner.target_compiler.triple,
+                "x86_64-unknown-linux-gnu" | "aarch64-unknown-linux-gnu"
+            )
+        {
+            return;
+        }
+        runner.run_rustc(["example/asan-report.rs", "-Zsanitizer=address"]);
+        let output = runner.out_command("asan-report", &[]).output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "out of bounds read wasn't detected");
+        assert!(
+            stderr.contains("ERROR: AddressSanitizer: heap-buffer-overflow"),
+            "unexpected AddressSanitizer output:\n{stderr}",
+        );
+    }),
+    TestCase::custom("aot.assume", &|runner| {
+        let opt_clif = || {
+            fs::read_to_string(
+                BUILD_EXAMPLE_OUT_DIR
+                    .to_path(&runner.dirs)
+                    .join("assume.clif")
+                    .join("assume_in_bounds.opt.clif"),
+            )
+            .unwrap()
+        };
+
+        // Without UB checks `assume` must not add any code.
+        runner.run_rustc(["example/assume.rs", "-O", "--emit", "llvm-ir,link"]);
+        let clif = opt_clif();
+        assert!(!clif.contains(" trapz "), "assume added a trap:\n{clif}");
+        runner.run_out_command("assume", &[]);
+
+        runner.run_rustc(["example/assume.rs", "-O", "-Zub-checks", "--emit", "llvm-ir,link"]);
+        let clif = opt_clif();
+        assert!(
+            clif.lines().any(|line| line.contains(" trapz ") && line.contains(", unreachable")),
+            "assume isn't checked with UB checks enabled:\n{clif}"
+        );
+        runner.run_out_command("assume", &[]);
+    }),
     TestCase::custom("aot.gen_block_iterate", &|runner| {
         runner.run_rustc([
             "example/gen_block_iterate.rs",
@@ -284,6 +1239,17 @@ pub(crate) fn run_tests(
         eprintln!("[SKIP] no_sysroot tests");
     }
 
+    if config::get_bool("testsuite.embedding") && !skip_tests.contains(&"testsuite.embedding") {
+        match cg_clif_dylib {
+            CodegenBackend::Local(cg_clif_dylib) => {
+                run_embedding_test(dirs, cg_clif_dylib, bootstrap_host_compiler);
+            }
+            CodegenBackend::Builtin(_) => eprintln!("[SKIP] embedding test (builtin backend)"),
+        }
+    } else {
+        eprintln!("[SKIP] embedding test");
+    }
+
     let run_base_sysroot = config::get_bool("testsuite.base_sysroot")
         && !skip_tests.contains(&"testsuite.base_sysroot");
     let run_extended_sysroot = config::get_bool("testsuite.extended_sysroot")
@@ -326,6 +1292,177 @@ pub(crate) fn run_tests(
     }
 }
 
+/// Build a custom rustc driver embedding the backend using the bootstrap compiler and use it to
+/// compile and run a trivial program.
+fn run_embedding_test(dirs: &Dirs, cg_clif_dylib: &Path, bootstrap_host_compiler: &Compiler) {
+    let _guard = LogGroup::guard("[TEST] embedding");
+    eprintln!("[TEST] embedding");
+
+    let out_dir = BUILD_EXAMPLE_OUT_DIR.to_path(dirs);
+    fs::create_dir_all(&out_dir).unwrap();
+    let cg_clif_dir = cg_clif_dylib.parent().unwrap();
+
+    let mut build_cmd = Command::new(&bootstrap_host_compiler.rustc);
+    build_cmd
+        .arg("example/embed-backend.rs")
+        .arg("--crate-name=embed_backend")
+        .arg("--edition=2021")
+        .arg("-Zallow-features=rustc_private")
+        .arg("--extern")
+        .arg(format!("rustc_codegen_cranelift={}", cg_clif_dylib.display()))
+        .arg("-L")
+        .arg(format!("dependency={}", cg_clif_dir.join("deps").display()))
+        .arg("--out-dir")
+        .arg(&out_dir);
+    spawn_and_wait(build_cmd);
+
+    // The driver and backend dynamically link against the bootstrap compiler's rustc_driver.
+    let host_sysroot = get_default_sysroot(&bootstrap_host_compiler.rustc);
+    let host_lib_dir = host_sysroot.join("lib");
+    let library_path_var = if cfg!(windows) {
+        "PATH"
+    } else if cfg!(target_os = "macos") {
+        "DYLD_LIBRARY_PATH"
+    } else {
+        "LD_LIBRARY_PATH"
+    };
+    let library_path = std::env::join_paths(
+        [host_lib_dir, cg_clif_dir.to_owned()]
+            .into_iter()
+            .chain(std::env::var_os(library_path_var).iter().flat_map(std::env::split_paths)),
+    )
+    .unwrap();
+
+    let mut compile_cmd = Command::new(out_dir.join(get_file_name(
+        &bootstrap_host_compiler.rustc,
+        "embed_backend",
+        "bin",
+    )));
+    compile_cmd
+        .env(library_path_var, library_path)
+        .arg("example/mod_bench.rs")
+        .arg("--sysroot")
+        .arg(&host_sysroot)
+        .arg("-Cpanic=abort")
+        .arg("--out-dir")
+        .arg(&out_dir);
+    spawn_and_wait(compile_cmd);
+
+    spawn_and_wait(Command::new(out_dir.join(get_file_name(
+        &bootstrap_host_compiler.rustc,
+        "mod_bench",
+        "bin",
+    ))));
+}
+
+/// Returns for every call to `callee` in `clif` whether the call is in a cold block.
+fn calls_in_cold_blocks(clif: &str, callee: &str) -> Vec<bool> {
+    // Calls refer to `fnN`, which the preamble annotates with the instance it was declared for.
+    let func_refs = clif
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| line.starts_with("fn") && line.contains(&format!("::{callee})")))
+        .map(|line| line.split_once(" = ").unwrap().0)
+        .collect::<Vec<_>>();
+
+    let mut calls = vec![];
+    let mut in_cold_block = false;
+    for line in clif.lines() {
+        if line.starts_with("block") {
+            in_cold_block = line.contains(" cold:");
+        } else if let Some((_, call)) = line.split_once(" call ") {
+            if func_refs.contains(&call.split('(').next().unwrap()) {
+                calls.push(in_cold_block);
+            }
+        }
+    }
+    calls
+}
+
+/// Runs `readelf` with the given arguments on `file` and returns its output.
+fn readelf(args: &[&str], file: &Path) -> String {
+    let output = Command::new("readelf").arg("-W").args(args).arg(file).output().unwrap();
+    assert!(
+        output.status.success(),
+        "readelf failed on {}:\n{}",
+        file.display(),
+        String::from_utf8_lossy(&output.stderr),
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Returns the line and column of every row in the line tables of `object` by following the line
+/// number statements printed by `readelf`.
+fn line_table_rows(object: &Path) -> Vec<(u64, u64)> {
+    let mut rows = vec![];
+    let (mut line, mut column) = (1, 1);
+    for statement in readelf(&["--debug-dump=rawline"], object).lines() {
+        let last_number = || statement.rsplit_once(" to ").unwrap().1.trim().parse().unwrap();
+        if statement.contains("Set column to ") {
+            column = last_number();
+        } else if statement.contains("Advance Line by ") {
+            line = last_number();
+        } else if statement.contains("Special opcode ") {
+            line = last_number();
+            rows.push((line, column));
+        } else if statement.trim_end().ends_with("Copy") {
+            rows.push((line, column));
+        } else if statement.contains("End of Sequence") {
+            (line, column) = (1, 1);
+        }
+    }
+    rows
+}
+
+/// Returns the text `readelf` prints for each debuginfo entry in `object`, including all attributes.
+fn debug_info_entries(object: &Path) -> Vec<String> {
+    let mut entries: Vec<String> = vec![];
+    for line in readelf(&["--debug-dump=info"], object).lines() {
+        if line.contains("Abbrev Number: ") {
+            entries.push(String::new());
+        }
+        if let Some(entry) = entries.last_mut() {
+            entry.push_str(line);
+            entry.push('\n');
+        }
+    }
+    entries
+}
+
+/// Returns the type of every relocation `readelf` prints for the executable sections of `object`.
+fn text_relocation_types(object: &Path) -> Vec<String> {
+    let mut relocs = vec![];
+    let mut in_text_section = false;
+    for line in readelf(&["--relocs"], object).lines() {
+        if let Some(section) = line.strip_prefix("Relocation section '") {
+            in_text_section = section.starts_with(".rela.text");
+        } else if let Some(reloc_type) = line.split_whitespace().nth(2) {
+            if in_text_section && reloc_type.starts_with("R_") {
+                relocs.push(reloc_type.to_owned());
+            }
+        }
+    }
+    relocs
+}
+
+/// Returns the name, binding and visibility `readelf` prints for every defined symbol in the
+/// symbol table of `file` selected by `table`, which is either `--syms` or `--dyn-syms`.
+fn defined_symbols(table: &str, file: &Path) -> Vec<(String, String, String)> {
+    let mut symbols = vec![];
+    for line in readelf(&[table], file).lines() {
+        // Num: Value Size Type Bind Vis Ndx Name
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let is_symbol = fields.len() >= 8
+            && fields[0].strip_suffix(':').is_some_and(|num| num.parse::<usize>().is_ok());
+        if !is_symbol || fields[6] == "UND" {
+            continue;
+        }
+        let name = fields[7].split('@').next().unwrap();
+        symbols.push((name.to_owned(), fields[4].to_owned(), fields[5].to_owned()));
+    }
+    symbols
+}
+
 struct TestRunner<'a> {
     is_native: bool,
     jit_supported: bool,
@@ -430,6 +1567,18 @@ fn run_testsuite(&self, tests: &[TestCase]) {
 
     #[must_use]
     fn rustc_command<I, S>(&self, args: I) -> Command
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.rustc_command_for_target(&self.target_compiler.triple, args)
+    }
+
+    /// Like [`rustc_command`](Self::rustc_command), but compiles for `target` rather than for the
+    /// target the tests run on. Only usable for `no_core` crates as the sysroot is only built for
+    /// the latter.
+    #[must_use]
+    fn rustc_command_for_target<I, S>(&self, target: &str, args: I) -> Command
     where
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr>,
@@ -442,7 +1591,7 @@ fn rustc_command<I, S>(&self, args: I) -> Command
         cmd.arg(format!("{}", BUILD_EXAMPLE_OUT_DIR.to_path(&self.dirs).display()));
         cmd.arg("-Cdebuginfo=2");
         cmd.arg("--target");
-        cmd.arg(&self.target_compiler.triple);
+        cmd.arg(target);
         cmd.arg("-Cpanic=abort");
         cmd.arg("-Zunstable-options");
         cmd.arg("--check-cfg=cfg(jit)");
@@ -458,7 +1607,31 @@ fn run_rustc<I, S>(&self, args: I)
         spawn_and_wait(self.rustc_command(args));
     }
 
+    /// Compile `example/{name}.c` with the system C compiler to an object file that can be linked
+    /// into an example.
+    fn compile_c_object(&self, name: &str) -> PathBuf {
+        let obj = BUILD_EXAMPLE_OUT_DIR.to_path(&self.dirs).join(format!("{name}-c.o"));
+        let mut cc = Command::new(env::var_os("CC").unwrap_or_else(|| "cc".into()));
+        cc.arg("-c").arg(format!("example/{name}.c")).arg("-o").arg(&obj);
+        spawn_and_wait(cc);
+        obj
+    }
+
     fn run_out_command(&self, name: &str, args: &[&str]) {
+        spawn_and_wait(self.out_command(name, args));
+    }
+
+    /// Run the example `name` with `args` and check that it fails with `msg` in its stderr.
+    fn expect_panic(&self, name: &str, args: &[&str], msg: &str) {
+        let output = self.out_command(name, args).output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            !output.status.success() && stderr.contains(msg),
+            "{name} {args:?} didn't panic with `{msg}`:\n{stderr}",
+        );
+    }
+
+    fn out_command(&self, name: &str, args: &[&str]) -> Command {
         let mut full_cmd = vec![];
 
         // Prepend the RUN_WRAPPER's
@@ -479,7 +1652,6 @@ fn run_out_command(&self, name: &str, args: &[&str]) {
 
         let mut cmd = Command::new(first);
         cmd.args(cmd_iter);
-
-        spawn_and_wait(cmd);
+        cmd
     }
 }
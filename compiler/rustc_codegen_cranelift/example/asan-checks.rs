@@ -0,0 +1,22 @@
+// Compiled with -Zsanitizer=address. The test harness checks that the object file references the
+// AddressSanitizer check functions for the loads and stores below, except for the function with
+// `#[no_sanitize(address)]`.
+
+#![feature(no_sanitize)]
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub fn asan_checked_load(ptr: &u32) -> u32 {
+    *ptr
+}
+
+#[no_mangle]
+pub fn asan_checked_store(ptr: &mut u64, val: u64) {
+    *ptr = val;
+}
+
+#[no_mangle]
+#[no_sanitize(address)]
+pub fn asan_unchecked_load(ptr: &u16) -> u16 {
+    *ptr
+}
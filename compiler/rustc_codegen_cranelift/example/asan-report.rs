@@ -0,0 +1,13 @@
+// Compiled with -Zsanitizer=address. Reads one element past the end of a heap allocation, which
+// the test harness expects AddressSanitizer to report.
+
+#[inline(never)]
+fn read_past_end(v: &[u32]) -> u32 {
+    let ptr = std::hint::black_box(v.as_ptr());
+    unsafe { *ptr.add(v.len()) }
+}
+
+fn main() {
+    let v = vec![1u32; 4];
+    println!("{}", read_past_end(&v));
+}
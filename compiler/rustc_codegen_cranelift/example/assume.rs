@@ -0,0 +1,17 @@
+// `assume` is ignored unless UB checks are enabled, in which case a violated assumption traps.
+// See the aot.assume test.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+
+use std::hint::black_box;
+
+#[no_mangle]
+pub fn assume_in_bounds(values: &[u32; 4], index: usize) -> u32 {
+    unsafe { std::intrinsics::assume(index < 4) };
+    values[index]
+}
+
+fn main() {
+    assert_eq!(black_box(assume_in_bounds)(&[1, 2, 3, 4], 2), 3);
+}
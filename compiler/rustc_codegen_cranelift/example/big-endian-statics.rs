@@ -0,0 +1,26 @@
+// Compiled for s390x-unknown-linux-gnu, which is big-endian. The test harness checks that the
+// statics below are emitted in target byte order. Doesn't depend on the sysroot, which is only
+// available for the host.
+
+#![feature(no_core, lang_items)]
+#![no_core]
+#![crate_type = "lib"]
+
+#[lang = "sized"]
+pub trait Sized {}
+
+#[lang = "sync"]
+pub unsafe trait Sync {}
+
+unsafe impl Sync for u32 {}
+unsafe impl Sync for u64 {}
+unsafe impl<T: Sync + ?Sized> Sync for &T {}
+
+#[no_mangle]
+pub static BIG_ENDIAN_U32: u32 = 0x01020304;
+
+#[no_mangle]
+pub static BIG_ENDIAN_U64: u64 = 0x1122334455667788;
+
+#[no_mangle]
+pub static BIG_ENDIAN_REF: &u32 = &BIG_ENDIAN_U32;
@@ -0,0 +1,9 @@
+// Compiled with optimizations and --emit llvm-ir. The test harness checks in the optimized clif
+// ir that Cranelift didn't constant fold through `black_box`.
+
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub fn black_box_barrier() -> u32 {
+    std::hint::black_box(42u32) + 1
+}
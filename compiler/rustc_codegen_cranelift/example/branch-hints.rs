@@ -0,0 +1,34 @@
+// The branch not expected to be taken is moved out of the hot path, see the aot.branch_hints test.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+
+use std::hint::black_box;
+use std::intrinsics::{likely, unlikely};
+
+#[inline(never)]
+fn expected(x: u32) -> u32 {
+    x + 1
+}
+
+#[inline(never)]
+fn unexpected(x: u32) -> u32 {
+    x + 2
+}
+
+#[no_mangle]
+fn likely_nonzero(x: u32) -> u32 {
+    if likely(x != 0) { expected(x) } else { unexpected(x) }
+}
+
+#[no_mangle]
+fn unlikely_zero(x: u32) -> u32 {
+    if unlikely(x == 0) { unexpected(x) } else { expected(x) }
+}
+
+fn main() {
+    assert_eq!(likely_nonzero(black_box(1)), 2);
+    assert_eq!(likely_nonzero(black_box(0)), 2);
+    assert_eq!(unlikely_zero(black_box(1)), 2);
+    assert_eq!(unlikely_zero(black_box(0)), 2);
+}
@@ -0,0 +1,28 @@
+// Only compiled and run on x86_64 Linux by the aot.breakpoint test. `int3` raises SIGTRAP and
+// execution continues after it once the signal handler returns. Any other trap instruction would
+// either raise a different signal or trap again when the handler returns.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const SIGTRAP: i32 = 5;
+
+extern "C" {
+    fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+}
+
+static TRAPS: AtomicU32 = AtomicU32::new(0);
+
+extern "C" fn on_sigtrap(_signum: i32) {
+    TRAPS.fetch_add(1, Ordering::Relaxed);
+}
+
+fn main() {
+    unsafe {
+        signal(SIGTRAP, on_sigtrap);
+        std::intrinsics::breakpoint();
+    }
+    assert_eq!(TRAPS.load(Ordering::Relaxed), 1);
+}
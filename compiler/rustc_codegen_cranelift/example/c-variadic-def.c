@@ -0,0 +1,9 @@
+// C side of example/c-variadic-def.rs. Passes more variadic integer and float arguments than fit
+// in the remaining argument registers, so some of both are read from the stack.
+
+extern double rust_variadic_sum(double scale, const char *format, ...);
+
+double call_rust_variadic_sum(void) {
+    return rust_variadic_sum(2.0, "idindidddnddiddi",
+        1L, 0.5, 2L, 3, 1.5, 4L, 2.5, 3.5, 4.5, 5, 5.5, 6.5, 6L, 7.5, 8.5, 7L);
+}
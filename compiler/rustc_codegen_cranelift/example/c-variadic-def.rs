@@ -0,0 +1,41 @@
+// Defines a C-variadic function which is called from C, see example/c-variadic-def.c.
+
+#![feature(c_variadic)]
+
+use std::ffi::{c_char, c_double, c_int, c_long, CStr, VaList};
+
+extern "C" {
+    fn call_rust_variadic_sum() -> c_double;
+}
+
+/// Sums the variadic arguments described by `format` ('i' for `long`, 'n' for `int` and 'd' for
+/// `double`) and multiplies the result by `scale`.
+#[no_mangle]
+pub unsafe extern "C" fn rust_variadic_sum(
+    scale: c_double,
+    format: *const c_char,
+    mut args: ...
+) -> c_double {
+    // Reading from a copy must not advance the original `va_list`.
+    let mut copy = args.clone();
+    let sum = sum_va_list(format, copy.as_va_list());
+    assert_eq!(sum_va_list(format, args.as_va_list()), sum);
+    sum * scale
+}
+
+unsafe fn sum_va_list(format: *const c_char, mut args: VaList<'_, '_>) -> c_double {
+    let mut sum = 0.0;
+    for &c in CStr::from_ptr(format).to_bytes() {
+        sum += match c {
+            b'i' => args.arg::<c_long>() as c_double,
+            b'n' => args.arg::<c_int>() as c_double,
+            b'd' => args.arg::<c_double>(),
+            _ => unreachable!(),
+        };
+    }
+    sum
+}
+
+fn main() {
+    assert_eq!(unsafe { call_rust_variadic_sum() }, 137.0);
+}
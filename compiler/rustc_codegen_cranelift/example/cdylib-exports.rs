@@ -0,0 +1,24 @@
+// Compiled as a cdylib. The test harness checks that the symbols below are exported from the
+// dynamic symbol table with default visibility and the expected binding.
+
+#![feature(linkage)]
+#![crate_type = "cdylib"]
+
+#[no_mangle]
+pub extern "C" fn cdylib_exported_fn(a: u32) -> u32 {
+    a + 1
+}
+
+#[export_name = "cdylib_renamed_fn"]
+pub extern "C" fn renamed_fn(a: u32) -> u32 {
+    a * 2
+}
+
+#[no_mangle]
+pub static CDYLIB_EXPORTED_STATIC: u32 = 42;
+
+#[no_mangle]
+#[linkage = "weak"]
+pub extern "C" fn cdylib_weak_fn() -> u32 {
+    7
+}
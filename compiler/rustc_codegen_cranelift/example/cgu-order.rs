@@ -0,0 +1,35 @@
+// Compiled twice with several codegen units of different sizes to check that the output doesn't
+// depend on the order in which the codegen units are scheduled.
+
+mod small {
+    #[inline(never)]
+    pub fn value() -> u64 {
+        1
+    }
+}
+
+mod medium {
+    #[inline(never)]
+    pub fn value(n: u64) -> u64 {
+        (0..n).map(|i| i * i).sum()
+    }
+}
+
+mod large {
+    use std::collections::BTreeMap;
+
+    #[inline(never)]
+    pub fn value(words: &[&str]) -> String {
+        let mut counts = BTreeMap::new();
+        for word in words {
+            *counts.entry(word.to_lowercase()).or_insert(0u64) += 1;
+        }
+        counts.iter().map(|(word, count)| format!("{word}={count}")).collect::<Vec<_>>().join(",")
+    }
+}
+
+fn main() {
+    assert_eq!(small::value(), 1);
+    assert_eq!(medium::value(4), 14);
+    assert_eq!(large::value(&["a", "B", "b"]), "a=1,b=2");
+}
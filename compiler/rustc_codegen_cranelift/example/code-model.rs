@@ -0,0 +1,22 @@
+// Compiled and run with -Ccode-model=large -Crelocation-model=static. The test harness checks in
+// the clif ir that the function below doesn't assume the function and static it references are
+// nearby.
+
+use std::hint::black_box;
+
+static VALUES: [u32; 4] = [1, 2, 3, 4];
+
+#[inline(never)]
+fn code_model_callee(index: usize) -> &'static u32 {
+    &VALUES[index]
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn code_model_probe(index: usize) -> u32 {
+    *code_model_callee(index) + 1
+}
+
+fn main() {
+    assert_eq!(black_box(code_model_probe)(2), 4);
+}
@@ -0,0 +1,37 @@
+// Blocks which aren't expected to be executed are marked cold, see the aot.cold_paths test.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+
+use std::hint::black_box;
+use std::intrinsics::unlikely;
+
+#[inline(never)]
+fn report_zero() -> ! {
+    panic!("x must not be zero");
+}
+
+#[cold]
+#[inline(never)]
+fn cold_fallback(x: u32) -> u32 {
+    x / 2
+}
+
+#[no_mangle]
+fn unlikely_panic(x: u32) -> u32 {
+    if unlikely(x == 0) {
+        report_zero();
+    }
+    x - 1
+}
+
+#[no_mangle]
+fn calls_cold_fn(x: u32) -> u32 {
+    if x > 100 { cold_fallback(x + 1) } else { x }
+}
+
+fn main() {
+    assert_eq!(unlikely_panic(black_box(3)), 2);
+    assert_eq!(calls_cold_fn(black_box(3)), 3);
+    assert_eq!(calls_cold_fn(black_box(201)), 101);
+}
@@ -0,0 +1,14 @@
+// Both functions reference an identical string constant. The test harness checks that only a
+// single copy of it ends up in the object file.
+
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub fn dedup_const_a() -> &'static str {
+    "\x7fdedup-const\x7f"
+}
+
+#[no_mangle]
+pub fn dedup_const_b() -> &'static str {
+    "\x7fdedup-const\x7f"
+}
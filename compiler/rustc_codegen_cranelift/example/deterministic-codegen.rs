@@ -0,0 +1,26 @@
+// Compiled with `-Cllvm-args=verify_deterministic_codegen=true`, which makes cg_clif compile
+// every codegen unit twice and error out if the resulting object files differ. Iterating over
+// a hash map gives a different order every run, so the entries are sorted before use.
+
+use std::collections::HashMap;
+
+static GREETINGS: &[(&str, &str)] = &[("en", "hello"), ("nl", "hallo"), ("de", "hallo")];
+
+trait Greet {
+    fn greet(&self) -> String;
+}
+
+impl Greet for &str {
+    fn greet(&self) -> String {
+        format!("{self}!")
+    }
+}
+
+fn main() {
+    let greetings = GREETINGS.iter().copied().collect::<HashMap<_, _>>();
+    let mut sorted = greetings.into_iter().collect::<Vec<_>>();
+    sorted.sort();
+    let greetings = sorted.iter().map(|(_, greeting)| greeting as &dyn Greet).collect::<Vec<_>>();
+    let greeted = greetings.iter().map(|greeting| greeting.greet()).collect::<Vec<_>>();
+    assert_eq!(greeted, ["hallo!", "hello!", "hallo!"]);
+}
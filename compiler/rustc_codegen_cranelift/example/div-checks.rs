@@ -0,0 +1,30 @@
+// Division by zero and `MIN / -1` panic independently of overflow checks. When passed the name of
+// one of the operations below as argument, this performs it with such operands, which should
+// panic with the same message as with the LLVM backend.
+
+use std::hint::black_box;
+
+fn main() {
+    let res = match std::env::args().nth(1).as_deref() {
+        None => {
+            assert_eq!(black_box(7i32) / black_box(-2), -3);
+            assert_eq!(black_box(7i32) % black_box(-2), 1);
+            assert_eq!(black_box(i32::MIN).checked_div(black_box(-1)), None);
+            assert_eq!(black_box(i32::MIN).wrapping_div(black_box(-1)), i32::MIN);
+            assert_eq!(black_box(i32::MIN).wrapping_rem(black_box(-1)), 0);
+            assert_eq!(black_box(1u64).checked_rem(black_box(0)), None);
+            assert_eq!(black_box(i128::MIN) / black_box(2), i128::MIN >> 1);
+            return;
+        }
+        Some("div_u32") => (black_box(1u32) / black_box(0)).to_string(),
+        Some("div_i32") => (black_box(i32::MIN) / black_box(-1)).to_string(),
+        Some("rem_u64") => (black_box(1u64) % black_box(0)).to_string(),
+        Some("rem_i32") => (black_box(i32::MIN) % black_box(-1)).to_string(),
+        Some("div_i8") => (black_box(i8::MIN) / black_box(-1)).to_string(),
+        Some("div_u128") => (black_box(1u128) / black_box(0)).to_string(),
+        Some("div_i128") => (black_box(i128::MIN) / black_box(-1)).to_string(),
+        Some("rem_i128") => (black_box(i128::MIN) % black_box(-1)).to_string(),
+        Some(op) => panic!("unknown operation {op}"),
+    };
+    unreachable!("invalid division not detected, result: {res}");
+}
@@ -0,0 +1,56 @@
+// A custom rustc driver which embeds the Cranelift backend instead of loading it with
+// `-Zcodegen-backend`. It is built by the test harness against the freshly built backend and then
+// used to compile and run a trivial program.
+
+#![feature(rustc_private)]
+
+extern crate rustc_codegen_cranelift;
+extern crate rustc_driver;
+extern crate rustc_interface;
+
+use rustc_codegen_cranelift::{compile_codegen_unit, BackendConfig, CraneliftCodegenBackend};
+use rustc_driver::Compilation;
+use rustc_interface::interface::{Compiler, Config};
+use rustc_interface::Queries;
+
+struct EmbedCallbacks;
+
+impl rustc_driver::Callbacks for EmbedCallbacks {
+    fn config(&mut self, config: &mut Config) {
+        config.make_codegen_backend =
+            Some(Box::new(|_opts| Box::new(CraneliftCodegenBackend::default())));
+    }
+
+    fn after_analysis<'tcx>(
+        &mut self,
+        _compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        queries.global_ctxt().unwrap().enter(|tcx| {
+            let backend_config = BackendConfig::from_opts(&tcx.sess.opts.cg.llvm_args)
+                .unwrap_or_else(|err| tcx.dcx().fatal(err));
+            // Keep the object files apart from the ones written by the regular codegen below.
+            let out_dir = tcx.output_filenames(()).with_extension("embed");
+            std::fs::create_dir_all(&out_dir).unwrap();
+            let (_, cgus) = tcx.collect_and_partition_mono_items(());
+            assert!(!cgus.is_empty());
+            for cgu in cgus {
+                let (module, _global_asm_module) =
+                    compile_codegen_unit(tcx, &backend_config, cgu.name(), &out_dir).unwrap();
+                let object = module.object.unwrap();
+                assert!(object.starts_with(&out_dir));
+                assert!(object.exists());
+            }
+        });
+
+        // Continue with the regular codegen using the embedded backend.
+        Compilation::Continue
+    }
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    std::process::exit(rustc_driver::catch_with_exit_code(|| {
+        rustc_driver::RunCompiler::new(&args, &mut EmbedCallbacks).run()
+    }));
+}
@@ -0,0 +1,8 @@
+// Compiled with `--emit asm` to check that the disassembly contains the function labels.
+
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub fn emit_asm_test_function(a: u32, b: u32) -> u32 {
+    a.wrapping_mul(b).rotate_left(3)
+}
@@ -0,0 +1,8 @@
+// Compiled with -Cllvm-args=emit_clif=<dir>. The test harness checks that the clif ir of the
+// function below is written to `<dir>/<mangled symbol name>.clif`.
+
+#![crate_type = "lib"]
+
+pub fn emit_clif_probe(a: u32, b: u32) -> u32 {
+    a.wrapping_mul(b)
+}
@@ -0,0 +1,20 @@
+// Compiled with -Cdebug-assertions, which enables UB checks. When passed `inexact` as argument,
+// this calls `exact_div` on operands that don't divide exactly, which should panic.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+
+use std::hint::black_box;
+use std::intrinsics::exact_div;
+
+fn main() {
+    unsafe {
+        assert_eq!(exact_div(black_box(6u32), black_box(2)), 3);
+        assert_eq!(exact_div(black_box(-6i64), black_box(3)), -2);
+        assert_eq!(exact_div(black_box(i128::MIN), black_box(-2)), 1 << 126);
+        if std::env::args().nth(1).as_deref() == Some("inexact") {
+            let res = exact_div(black_box(7i32), black_box(2));
+            unreachable!("inexact division not detected, result: {res}");
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// Weak references to a symbol that is never defined and to one that is. Reading an extern static
+// with `#[linkage = "extern_weak"]` gives the address of the symbol, which has to be null rather
+// than a link error when the symbol is absent.
+
+#![feature(linkage)]
+
+use std::ptr;
+
+extern "C" {
+    #[linkage = "extern_weak"]
+    static cg_clif_undefined_weak_symbol: *const u8;
+
+    #[linkage = "extern_weak"]
+    static EXTERN_WEAK_DEFINED: *const u8;
+}
+
+mod defined {
+    #[no_mangle]
+    pub static EXTERN_WEAK_DEFINED: u8 = 42;
+}
+
+fn main() {
+    unsafe {
+        assert!(cg_clif_undefined_weak_symbol.is_null());
+        assert!(ptr::eq(EXTERN_WEAK_DEFINED, &defined::EXTERN_WEAK_DEFINED));
+        assert_eq!(*EXTERN_WEAK_DEFINED, 42);
+    }
+}
@@ -0,0 +1,28 @@
+// Calls intrinsics through their fallback body, see the aot.fallback_intrinsic test.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+
+use std::intrinsics::{likely, typed_swap};
+
+// cg_clif doesn't implement `typed_swap`, so this always uses its fallback body.
+#[no_mangle]
+fn swap_arrays(a: &mut [u64; 3], b: &mut [u64; 3]) {
+    unsafe { typed_swap(a, b) }
+}
+
+// cg_clif implements `likely`, but the test forces its fallback body to be used.
+#[no_mangle]
+fn is_likely_positive(x: i32) -> bool {
+    likely(x > 0)
+}
+
+fn main() {
+    let mut a = [1, 2, 3];
+    let mut b = [4, 5, 6];
+    swap_arrays(&mut a, &mut b);
+    assert_eq!((a, b), ([4, 5, 6], [1, 2, 3]));
+
+    assert!(is_likely_positive(std::hint::black_box(1)));
+    assert!(!is_likely_positive(std::hint::black_box(-1)));
+}
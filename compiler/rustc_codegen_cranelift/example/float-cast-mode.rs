@@ -0,0 +1,9 @@
+// Compiled with and without -Zsaturating-float-casts=no to check that float to int casts only
+// check for NaN when they are saturating.
+
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub fn float_to_int(f: f32) -> i32 {
+    f as i32
+}
@@ -0,0 +1,184 @@
+// Test that casts between integers and floats of all sizes produce the correct results.
+//
+// Arithmetic and comparisons on f16 and f128 are not supported by Cranelift, so the results of
+// those casts are compared by their bit patterns.
+
+#![feature(f16, f128, repr_simd, core_intrinsics)]
+#![allow(internal_features, non_camel_case_types)]
+
+use std::hint::black_box;
+use std::intrinsics::simd::simd_cast;
+
+fn main() {
+    test_small_ints();
+    test_f16();
+    test_f128();
+    test_simd();
+}
+
+fn f16_is_nan(f: f16) -> bool {
+    f.to_bits() & 0x7fff > 0x7c00
+}
+
+fn f128_is_nan(f: f128) -> bool {
+    f.to_bits() & (u128::MAX >> 1) > 0x7fff_u128 << 112
+}
+
+fn test_small_ints() {
+    assert_eq!(black_box(-200.0f32) as i8, i8::MIN);
+    assert_eq!(black_box(200.0f32) as i8, i8::MAX);
+    assert_eq!(black_box(-128.9f32) as i8, -128);
+    assert_eq!(black_box(300.0f32) as u8, u8::MAX);
+    assert_eq!(black_box(-300.0f32) as u8, 0);
+    assert_eq!(black_box(255.9f32) as u8, 255);
+    assert_eq!(black_box(f32::NAN) as i8, 0);
+    assert_eq!(black_box(f32::NAN) as u8, 0);
+    assert_eq!(black_box(-40000.0f64) as i16, i16::MIN);
+    assert_eq!(black_box(70000.0f64) as u16, u16::MAX);
+    assert_eq!(black_box(f64::NAN) as i16, 0);
+    assert_eq!(black_box(f64::NEG_INFINITY) as u16, 0);
+}
+
+fn test_f16() {
+    assert_eq!(black_box(1.5f16) as i32, 1);
+    assert_eq!(black_box(-1.5f16) as i32, -1);
+    assert_eq!(black_box(65504.0f16) as i32, 65504);
+    assert_eq!(black_box(-1.0f16) as u8, 0);
+    assert_eq!(black_box(300.0f16) as u8, 255);
+    assert_eq!(black_box(f16::INFINITY) as i32, i32::MAX);
+    assert_eq!(black_box(f16::NEG_INFINITY) as i32, i32::MIN);
+    assert_eq!(black_box(f16::NAN) as i32, 0);
+    assert_eq!(black_box(f16::NAN) as u64, 0);
+    assert_eq!(black_box(f16::NAN) as i128, 0);
+
+    assert_eq!((black_box(100i64) as f16).to_bits(), 100.0f16.to_bits());
+    assert_eq!((black_box(-2048i64) as f16).to_bits(), (-2048.0f16).to_bits());
+    assert_eq!((black_box(2049i64) as f16).to_bits(), 2048.0f16.to_bits());
+    assert_eq!((black_box(65519i64) as f16).to_bits(), 65504.0f16.to_bits());
+    assert_eq!((black_box(65520i64) as f16).to_bits(), f16::INFINITY.to_bits());
+    assert_eq!((black_box(i64::MAX) as f16).to_bits(), f16::INFINITY.to_bits());
+    assert_eq!((black_box(i64::MIN) as f16).to_bits(), f16::NEG_INFINITY.to_bits());
+    assert_eq!((black_box(u128::MAX) as f16).to_bits(), f16::INFINITY.to_bits());
+
+    assert_eq!(black_box(1.5f16) as f32, 1.5);
+    assert_eq!(black_box(1.5f16) as f64, 1.5);
+    assert_eq!((black_box(1.5f32) as f16).to_bits(), 1.5f16.to_bits());
+    assert_eq!((black_box(1.5f64) as f16).to_bits(), 1.5f16.to_bits());
+    assert_eq!((black_box(1e10f32) as f16).to_bits(), f16::INFINITY.to_bits());
+    assert!(f16_is_nan(black_box(f32::NAN) as f16));
+    assert!((black_box(f16::NAN) as f64).is_nan());
+    // Rounds up when converted directly, but rounds to even (1.0) when rounding through f32.
+    let just_above_halfway = 1.0f64 + 2.0f64.powi(-11) + 2.0f64.powi(-40);
+    assert_eq!((black_box(just_above_halfway) as f16).to_bits(), 0x3c01);
+}
+
+fn test_f128() {
+    assert_eq!((black_box(1.5f32) as f128).to_bits(), 1.5f128.to_bits());
+    assert_eq!((black_box(-2.25f64) as f128).to_bits(), (-2.25f128).to_bits());
+    assert_eq!(black_box(1.5f128) as f32, 1.5);
+    assert_eq!(black_box(-2.25f128) as f64, -2.25);
+    assert_eq!((black_box(f32::INFINITY) as f128).to_bits(), f128::INFINITY.to_bits());
+    assert_eq!(black_box(1e400f128) as f64, f64::INFINITY);
+    assert!(f128_is_nan(black_box(f32::NAN) as f128));
+    assert!((black_box(f128::NAN) as f32).is_nan());
+    assert_eq!((black_box(1.5f16) as f128).to_bits(), 1.5f128.to_bits());
+    assert_eq!((black_box(1.5f128) as f16).to_bits(), 1.5f16.to_bits());
+
+    assert_eq!(black_box(u128::MAX) as f128 as u128, u128::MAX);
+    assert_eq!(black_box(i128::MIN) as f128 as i128, i128::MIN);
+    assert_eq!(black_box(12345678901234567890u128) as f128 as u128, 12345678901234567890);
+    assert_eq!((black_box(-7i8) as f128).to_bits(), (-7.0f128).to_bits());
+    assert_eq!(black_box(u64::MAX) as f128 as u64, u64::MAX);
+    assert_eq!(black_box(i32::MIN) as f128 as i32, i32::MIN);
+
+    assert_eq!(black_box(-1.0f128) as u128, 0);
+    assert_eq!(black_box(1e40f128) as u128, u128::MAX);
+    assert_eq!(black_box(-1e40f128) as i128, i128::MIN);
+    assert_eq!(black_box(f128::NAN) as u128, 0);
+    assert_eq!(black_box(f128::NAN) as i64, 0);
+    assert_eq!(black_box(1e10f128) as i32, i32::MAX);
+    assert_eq!(black_box(-1e10f128) as i32, i32::MIN);
+    assert_eq!(black_box(300.5f128) as u8, 255);
+    assert_eq!(black_box(-300.5f128) as i16, -300);
+    assert_eq!(black_box(-40000.0f128) as i16, i16::MIN);
+    assert_eq!(black_box(-1.5f128) as u16, 0);
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct f32x4([f32; 4]);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct i32x4([i32; 4]);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct u32x4([u32; 4]);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct f64x2([f64; 2]);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct i64x2([i64; 2]);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct i64x4([i64; 4]);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct i8x16([i8; 16]);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct u8x16([u8; 16]);
+
+#[repr(simd)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct i16x16([i16; 16]);
+
+fn test_simd() {
+    let floats = [1.5, -2.5, i32::MAX as f32 + 1.0, f32::NAN];
+    let ints: i32x4 = unsafe { simd_cast(black_box(f32x4(floats))) };
+    assert_eq!(ints, i32x4(floats.map(|f| f as i32)));
+    let uints: u32x4 = unsafe { simd_cast(black_box(f32x4(floats))) };
+    assert_eq!(uints, u32x4(floats.map(|f| f as u32)));
+
+    let ints = [1, -2, i32::MAX, i32::MIN];
+    let floats: f32x4 = unsafe { simd_cast(black_box(i32x4(ints))) };
+    assert_eq!(floats, f32x4(ints.map(|i| i as f32)));
+    let uints = [1, 2, u32::MAX, 1 << 31];
+    let floats: f32x4 = unsafe { simd_cast(black_box(u32x4(uints))) };
+    assert_eq!(floats, f32x4(uints.map(|i| i as f32)));
+
+    let floats = [-1e300, f64::NAN];
+    let ints: i64x2 = unsafe { simd_cast(black_box(f64x2(floats))) };
+    assert_eq!(ints, i64x2(floats.map(|f| f as i64)));
+    let ints = [i64::MAX, -3];
+    let floats: f64x2 = unsafe { simd_cast(black_box(i64x2(ints))) };
+    assert_eq!(floats, f64x2(ints.map(|i| i as f64)));
+
+    let ints: u32x4 = unsafe { simd_cast(black_box(i32x4([-1, 0, 1, i32::MIN]))) };
+    assert_eq!(ints, u32x4([u32::MAX, 0, 1, 1 << 31]));
+
+    // Casts between lanes of different sizes
+    let ints = [-1, 2, i32::MIN, i32::MAX];
+    let wide: i64x4 = unsafe { simd_cast(black_box(i32x4(ints))) };
+    assert_eq!(wide, i64x4(ints.map(|i| i as i64)));
+    let uints = [u32::MAX, 2, 1 << 31, 0];
+    let wide: i64x4 = unsafe { simd_cast(black_box(u32x4(uints))) };
+    assert_eq!(wide, i64x4(uints.map(|i| i as i64)));
+    let wide = [-1, (1 << 32) | 5, i64::MIN, 0x1_2345_6789];
+    let narrow: i32x4 = unsafe { simd_cast(black_box(i64x4(wide))) };
+    assert_eq!(narrow, i32x4(wide.map(|i| i as i32)));
+
+    let bytes = std::array::from_fn(|i| (i as i8).wrapping_mul(37));
+    let wide: i16x16 = unsafe { simd_cast(black_box(i8x16(bytes))) };
+    assert_eq!(wide, i16x16(bytes.map(|i| i as i16)));
+    let wide = std::array::from_fn(|i| (i as i16 - 8) * 1000);
+    let narrow: u8x16 = unsafe { simd_cast(black_box(i16x16(wide))) };
+    assert_eq!(narrow, u8x16(wide.map(|i| i as u8)));
+}
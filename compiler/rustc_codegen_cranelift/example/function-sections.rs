@@ -0,0 +1,18 @@
+// Compiled with -Zfunction-sections. The test harness checks that the unused function got
+// garbage collected by the linker while the used function was kept.
+
+#[no_mangle]
+#[inline(never)]
+pub fn function_sections_used() -> u32 {
+    42
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn function_sections_unused() -> u32 {
+    43
+}
+
+fn main() {
+    assert_eq!(function_sections_used(), 42);
+}
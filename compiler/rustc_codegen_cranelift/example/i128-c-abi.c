@@ -0,0 +1,22 @@
+// C side of example/i128-c-abi.rs. Compiled with the system C compiler to check that 128-bit
+// integers are passed and returned the same way as by cg_clif.
+
+#include <stdint.h>
+
+__int128 i128_c_make(uint64_t hi, uint64_t lo) {
+    return (__int128)(((unsigned __int128)hi << 64) | lo);
+}
+
+// `x` doesn't fit in the remaining integer register, which is used by `f` instead. `y` is passed
+// on the stack after `g` and needs to be aligned to 16 bytes.
+__int128 i128_c_sum(int64_t a, int64_t b, int64_t c, int64_t d, int64_t e, __int128 x,
+                    int64_t f, int64_t g, __int128 y) {
+    return a + b + c + d + e + x + f + g + y;
+}
+
+extern __int128 i128_rust_sum(int64_t a, int64_t b, int64_t c, int64_t d, int64_t e, __int128 x,
+                              int64_t f, int64_t g, __int128 y);
+
+__int128 i128_c_call_rust(__int128 x, __int128 y) {
+    return i128_rust_sum(1, 2, 3, 4, 5, x, 6, 7, y);
+}
@@ -0,0 +1,37 @@
+// Passes 128-bit integers to and from C functions compiled by the system C compiler, see
+// example/i128-c-abi.c.
+
+#![allow(improper_ctypes, improper_ctypes_definitions)]
+
+extern "C" {
+    fn i128_c_make(hi: u64, lo: u64) -> i128;
+    fn i128_c_sum(a: i64, b: i64, c: i64, d: i64, e: i64, x: i128, f: i64, g: i64, y: i128)
+    -> i128;
+    fn i128_c_call_rust(x: i128, y: i128) -> i128;
+}
+
+#[no_mangle]
+extern "C" fn i128_rust_sum(
+    a: i64,
+    b: i64,
+    c: i64,
+    d: i64,
+    e: i64,
+    x: i128,
+    f: i64,
+    g: i64,
+    y: i128,
+) -> i128 {
+    assert_eq!((a, b, c, d, e, f, g), (1, 2, 3, 4, 5, 6, 7));
+    (a + b + c + d + e + f + g) as i128 + x + y
+}
+
+fn main() {
+    let big = unsafe { i128_c_make(0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210) };
+    assert_eq!(big, 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210);
+
+    let x = 1 << 100;
+    let y = -(1 << 90);
+    assert_eq!(unsafe { i128_c_sum(1, 2, 3, 4, 5, x, 6, 7, y) }, 28 + x + y);
+    assert_eq!(unsafe { i128_c_call_rust(x, y) }, 28 + x + y);
+}
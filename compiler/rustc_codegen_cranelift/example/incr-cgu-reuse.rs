@@ -0,0 +1,46 @@
+// Compiled twice with the same incremental cache directory, first with `--cfg rpass1` and then
+// with `--cfg rpass2`. Only the `x` module changes between both builds, so the codegen units of
+// the crate root and of `y` must be reused from the incremental cache.
+
+#![feature(rustc_attrs)]
+#![rustc_partition_reused(module = "incr_cgu_reuse", cfg = "rpass2")]
+#![rustc_partition_codegened(module = "incr_cgu_reuse-x", cfg = "rpass2")]
+#![rustc_partition_reused(module = "incr_cgu_reuse-y", cfg = "rpass2")]
+
+mod x {
+    pub struct X {
+        x: u32,
+        y: u32,
+    }
+
+    #[cfg(rpass1)]
+    fn make() -> X {
+        X { x: 22, y: 0 }
+    }
+
+    #[cfg(rpass2)]
+    fn make() -> X {
+        X { x: 11, y: 11 }
+    }
+
+    pub fn new() -> X {
+        make()
+    }
+
+    pub fn sum(x: &X) -> u32 {
+        x.x + x.y
+    }
+}
+
+mod y {
+    use crate::x;
+
+    pub fn assert_sum() -> bool {
+        let x = x::new();
+        x::sum(&x) == 22
+    }
+}
+
+fn main() {
+    assert!(y::assert_sum());
+}
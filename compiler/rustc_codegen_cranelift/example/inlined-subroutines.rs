@@ -0,0 +1,14 @@
+// Compiled with MIR inlining and debuginfo to check that inlined function bodies are described by
+// DW_TAG_inlined_subroutine entries.
+
+#![crate_type = "lib"]
+
+#[inline]
+fn inlined_helper(x: u64) -> u64 {
+    x.wrapping_mul(3).rotate_left(5)
+}
+
+#[no_mangle]
+pub fn inlined_subroutine_probe(x: u64) -> u64 {
+    inlined_helper(x) ^ inlined_helper(x.wrapping_add(1))
+}
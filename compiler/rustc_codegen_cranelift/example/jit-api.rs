@@ -0,0 +1,83 @@
+// Uses the functions the JIT driver exposes to the jitted program. Only runs in JIT mode and takes
+// the JIT mode as argument.
+
+use std::ffi::c_char;
+
+extern "C" {
+    fn __clif_jit_lookup_symbol(name: *const c_char) -> *const u8;
+    fn __clif_jit_recompile(name: *const c_char) -> *const u8;
+    fn __clif_jit_redefine(name: *const c_char, body: *const c_char) -> *const u8;
+}
+
+#[no_mangle]
+static JIT_API_STATIC: u32 = 7;
+
+#[no_mangle]
+#[inline(never)]
+extern "C" fn jit_api_answer() -> i32 {
+    42
+}
+
+// Edited version of `jit_api_answer` to swap in.
+#[no_mangle]
+#[inline(never)]
+extern "C" fn jit_api_new_answer() -> i32 {
+    43
+}
+
+// Only differs from `jit_api_answer` in its signature.
+#[no_mangle]
+#[inline(never)]
+extern "C" fn jit_api_wrong_signature() -> i64 {
+    44
+}
+
+// Function pointers are loaded from the GOT every time this is called.
+#[inline(never)]
+fn answer_addr() -> usize {
+    jit_api_answer as usize
+}
+
+fn main() {
+    let mode = std::env::args().nth(1).unwrap();
+
+    let answer = unsafe { __clif_jit_lookup_symbol(c"jit_api_answer".as_ptr()) };
+    assert!(!answer.is_null());
+    let answer: extern "C" fn() -> i32 = unsafe { std::mem::transmute(answer) };
+    assert_eq!(answer(), 42);
+    let static_addr = unsafe { __clif_jit_lookup_symbol(c"JIT_API_STATIC".as_ptr()) };
+    assert_eq!(static_addr, &JIT_API_STATIC as *const u32 as *const u8);
+    assert!(unsafe { __clif_jit_lookup_symbol(c"no_such_symbol".as_ptr()) }.is_null());
+
+    assert_eq!(jit_api_answer(), 42);
+    let old_body = answer_addr();
+    let new_body = unsafe { __clif_jit_recompile(c"jit_api_answer".as_ptr()) };
+    if mode != "jit-lazy" {
+        assert!(new_body.is_null(), "functions can only be recompiled in lazy JIT mode");
+        return;
+    }
+
+    assert!(!new_body.is_null());
+    assert_ne!(new_body as usize, old_body, "no new body was compiled");
+    assert_eq!(answer_addr(), new_body as usize, "function pointers don't use the new body");
+    let new_fn: extern "C" fn() -> i32 = unsafe { std::mem::transmute(new_body) };
+    assert_eq!(new_fn(), 42);
+    assert_eq!(jit_api_answer(), 42);
+
+    assert!(unsafe { __clif_jit_recompile(c"no_such_function".as_ptr()) }.is_null());
+
+    // Swap in a body that returns a different value.
+    let swapped_body =
+        unsafe { __clif_jit_redefine(c"jit_api_answer".as_ptr(), c"jit_api_new_answer".as_ptr()) };
+    assert!(!swapped_body.is_null());
+    assert_eq!(jit_api_answer(), 43, "calls don't use the swapped in body");
+    assert_eq!(answer_addr(), swapped_body as usize, "function pointers don't use the new body");
+    assert_eq!(jit_api_new_answer(), 43);
+
+    let wrong_signature = unsafe {
+        __clif_jit_redefine(c"jit_api_answer".as_ptr(), c"jit_api_wrong_signature".as_ptr())
+    };
+    assert!(wrong_signature.is_null(), "body with a different signature was swapped in");
+    assert_eq!(jit_api_answer(), 43);
+    assert_eq!(jit_api_wrong_signature(), 44);
+}
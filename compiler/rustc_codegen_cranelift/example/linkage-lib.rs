@@ -0,0 +1,66 @@
+// Library for example/linkage.rs. Defines functions with every `#[linkage]` kind supported by
+// cg_clif. `linkage_overridable` is also defined by the binary, whose strong definition must win.
+// `linkage_available_externally` must not be defined by the library at all.
+
+#![feature(linkage)]
+#![crate_type = "lib"]
+
+#[no_mangle]
+#[linkage = "weak"]
+pub extern "C" fn linkage_overridable() -> u32 {
+    1
+}
+
+#[no_mangle]
+#[linkage = "weak"]
+pub extern "C" fn linkage_weak() -> u32 {
+    2
+}
+
+#[no_mangle]
+#[linkage = "weak_odr"]
+pub extern "C" fn linkage_weak_odr() -> u32 {
+    3
+}
+
+#[no_mangle]
+#[linkage = "linkonce_odr"]
+pub extern "C" fn linkage_linkonce_odr() -> u32 {
+    4
+}
+
+#[no_mangle]
+#[linkage = "external"]
+pub extern "C" fn linkage_external() -> u32 {
+    5
+}
+
+#[linkage = "internal"]
+extern "C" fn linkage_internal() -> u32 {
+    6
+}
+
+// Only provided for inlining. The definition used at runtime is the one in the binary.
+#[no_mangle]
+#[linkage = "available_externally"]
+pub extern "C" fn linkage_available_externally() -> u32 {
+    8
+}
+
+#[no_mangle]
+#[linkage = "weak"]
+pub static LINKAGE_WEAK_STATIC: u32 = 7;
+
+#[inline(never)]
+pub fn call_all() -> [u32; 8] {
+    [
+        linkage_overridable(),
+        linkage_weak(),
+        linkage_weak_odr(),
+        linkage_linkonce_odr(),
+        linkage_external(),
+        linkage_internal(),
+        LINKAGE_WEAK_STATIC,
+        linkage_available_externally(),
+    ]
+}
@@ -0,0 +1,19 @@
+// The strong definition of `linkage_overridable` below has to take precedence over the weak
+// definition in example/linkage-lib.rs at link time. `linkage_available_externally` is only
+// defined here.
+
+extern crate linkage_lib;
+
+#[no_mangle]
+pub extern "C" fn linkage_overridable() -> u32 {
+    100
+}
+
+#[no_mangle]
+pub extern "C" fn linkage_available_externally() -> u32 {
+    108
+}
+
+fn main() {
+    assert_eq!(linkage_lib::call_all(), [100, 2, 3, 4, 5, 6, 7, 108]);
+}
@@ -0,0 +1,16 @@
+// Compiled with debuginfo to check that line table rows for code inside a macro definition have
+// no column, while rows for code outside of macros do.
+
+#![crate_type = "lib"]
+
+macro_rules! add_one {
+    ($e:expr) => {
+        $e.wrapping_add(1) // macro body
+    };
+}
+
+#[no_mangle]
+pub fn macro_column_probe(x: u32) -> u32 {
+    let y = x.wrapping_mul(3); // function body
+    add_one!(y)
+}
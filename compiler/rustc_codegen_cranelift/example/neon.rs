@@ -202,6 +202,51 @@ unsafe fn test_vqadd_u8() {
     assert_eq!(r, e);
 }
 
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vaddq_u8() {
+    let a = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0xff];
+    let b = [30, 1, 1, 1, 34, 0xff, 36, 37, 38, 39, 40, 41, 42, 43, 44, 2];
+    let r: [u8; 16] = transmute(vaddq_u8(vld1q_u8(a.as_ptr()), vld1q_u8(b.as_ptr())));
+    let e: [u8; 16] = std::array::from_fn(|i| a[i].wrapping_add(b[i]));
+    assert_eq!(r, e);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vmull_s8() {
+    let a = i8x8::from([1, -2, 3, -4, 5, 6, -128, 127]);
+    let b = i8x8::from([30, 31, -32, -33, 34, 35, -128, 127]);
+    let r: i16x8 = transmute(vmull_s8(transmute(a), transmute(b)));
+    let e = i16x8::from([30, -62, -96, 132, 170, 210, 16384, 16129]);
+    assert_eq!(r, e);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vmull_u16() {
+    let a = u16x4::from([1, 2, 0xffff, 4]);
+    let b = u16x4::from([30, 31, 0xffff, 33]);
+    let r: u32x4 = transmute(vmull_u16(transmute(a), transmute(b)));
+    let e = u32x4::from([30, 62, 0xfffe0001, 132]);
+    assert_eq!(r, e);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vmull_p8() {
+    let a = u8x8::from([1, 2, 3, 0xff, 5, 6, 7, 0x80]);
+    let b = u8x8::from([1, 3, 3, 0xff, 5, 0, 9, 0x80]);
+    let r: u16x8 = transmute(vmull_p8(transmute(a), transmute(b)));
+    let e = u16x8::from([1, 6, 5, 0x5555, 17, 0, 63, 0x4000]);
+    assert_eq!(r, e);
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn test_vqtbl1q_u8() {
+    let t = u8x16::from([10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25]);
+    let idx = u8x16::from([15, 0, 1, 16, 2, 0xff, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+    let r: u8x16 = transmute(vqtbl1q_u8(transmute(t), transmute(idx)));
+    let e = u8x16::from([25, 10, 11, 0, 12, 0, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22]);
+    assert_eq!(r, e);
+}
+
 #[cfg(target_arch = "aarch64")]
 fn main() {
     unsafe {
@@ -229,6 +274,12 @@ fn main() {
 
         test_vqsub_u8();
         test_vqadd_u8();
+
+        test_vaddq_u8();
+        test_vmull_s8();
+        test_vmull_u16();
+        test_vmull_p8();
+        test_vqtbl1q_u8();
     }
 }
 
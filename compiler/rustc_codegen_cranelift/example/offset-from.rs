@@ -0,0 +1,22 @@
+// Compiled with -Cdebug-assertions, which enables UB checks. When passed `misaligned` as argument,
+// this calls `offset_from` on pointers whose distance isn't a multiple of the pointee size, which
+// should panic.
+
+#![feature(ptr_sub_ptr)]
+
+use std::hint::black_box;
+
+fn main() {
+    let array = [0u32; 8];
+    let start = black_box(array.as_ptr());
+    unsafe {
+        assert_eq!(start.add(5).offset_from(start), 5);
+        assert_eq!(start.offset_from(start.add(3)), -3);
+        assert_eq!(start.add(7).sub_ptr(start), 7);
+        if std::env::args().nth(1).as_deref() == Some("misaligned") {
+            let misaligned = start.byte_add(black_box(6));
+            let res = misaligned.offset_from(start);
+            unreachable!("misaligned offset_from not detected, result: {res}");
+        }
+    }
+}
@@ -0,0 +1,9 @@
+// Compiled with every `-Copt-level` and --emit llvm-ir. The test harness checks the Cranelift
+// `opt_level` setting recorded in the clif ir of the function below.
+
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub fn opt_level_probe(a: u32) -> u32 {
+    a + 1
+}
@@ -0,0 +1,16 @@
+// Compiled with -Copt-level=3 and --emit llvm-ir. The test harness checks that the function with
+// `#[optimize(size)]` is compiled with a different Cranelift `opt_level` than the other function.
+
+#![feature(optimize_attribute)]
+#![crate_type = "lib"]
+
+#[no_mangle]
+#[optimize(size)]
+pub fn optimize_for_size(a: u32) -> u32 {
+    a.wrapping_mul(3)
+}
+
+#[no_mangle]
+pub fn optimize_default(a: u32) -> u32 {
+    a.wrapping_mul(5)
+}
@@ -0,0 +1,30 @@
+// Compiled with -Cdebug-assertions, which enables overflow checks. When passed the name of one of
+// the operations below as argument, this performs it with overflowing operands, which should
+// panic with the same message as with the LLVM backend.
+
+use std::hint::black_box;
+
+fn main() {
+    let res = match std::env::args().nth(1).as_deref() {
+        None => {
+            assert_eq!(black_box(i32::MAX - 1) + black_box(1), i32::MAX);
+            assert_eq!(black_box(i128::MAX - 1) + black_box(1), i128::MAX);
+            assert_eq!(black_box(1u32) << black_box(31), 1 << 31);
+            assert_eq!(black_box(-1i128) >> black_box(127), -1);
+            return;
+        }
+        Some("add_i32") => (black_box(i32::MAX) + black_box(1)).to_string(),
+        Some("sub_u8") => (black_box(0u8) - black_box(1)).to_string(),
+        Some("mul_i64") => (black_box(i64::MAX) * black_box(2)).to_string(),
+        Some("neg_i16") => (-black_box(i16::MIN)).to_string(),
+        Some("add_i128") => (black_box(i128::MAX) + black_box(1)).to_string(),
+        Some("sub_u128") => (black_box(0u128) - black_box(1)).to_string(),
+        Some("mul_u128") => (black_box(u128::MAX) * black_box(2)).to_string(),
+        Some("shl_u32") => (black_box(1u32) << black_box(32)).to_string(),
+        Some("shr_i64") => (black_box(1i64) >> black_box(64)).to_string(),
+        Some("shl_u128") => (black_box(1u128) << black_box(128)).to_string(),
+        Some("shr_i128") => (black_box(1i128) >> black_box(200)).to_string(),
+        Some(op) => panic!("unknown operation {op}"),
+    };
+    unreachable!("overflow not detected, result: {res}");
+}
@@ -0,0 +1,42 @@
+// Prefetches are emitted using inline asm, see the aot.prefetch test.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+
+use std::hint::black_box;
+use std::intrinsics::{
+    prefetch_read_data, prefetch_read_instruction, prefetch_write_data, prefetch_write_instruction,
+};
+
+#[no_mangle]
+fn prefetch_all(data: &mut [u64; 64]) -> u64 {
+    unsafe {
+        prefetch_read_data(data.as_ptr(), 3);
+        prefetch_read_data(data.as_ptr().add(8), 0);
+        prefetch_write_data(data.as_mut_ptr().add(16), 2);
+        prefetch_write_data(data.as_mut_ptr().add(24), 3);
+        prefetch_read_instruction(prefetch_all as *const u8, 3);
+        prefetch_write_instruction(prefetch_all as *const u8, 3);
+    }
+    data.iter().sum()
+}
+
+// Goes through `llvm.prefetch` rather than the `prefetch_*` intrinsics.
+#[cfg(target_arch = "x86_64")]
+#[no_mangle]
+fn mm_prefetch(data: &[u64; 64]) -> u64 {
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T2};
+
+    unsafe { _mm_prefetch::<_MM_HINT_T2>(data.as_ptr().add(32).cast()) };
+    data[32]
+}
+
+fn main() {
+    let mut data = [0; 64];
+    for (i, elem) in data.iter_mut().enumerate() {
+        *elem = i as u64;
+    }
+    assert_eq!(prefetch_all(black_box(&mut data)), 2016);
+    #[cfg(target_arch = "x86_64")]
+    assert_eq!(mm_prefetch(black_box(&data)), 32);
+}
@@ -0,0 +1,30 @@
+// Compiled with -Cdebug-assertions, which enables UB checks. When passed `offset`, `add` or
+// `add_bytes` as argument, this offsets a pointer by a byte offset that overflows `isize`, which
+// should panic.
+// `wrapping_offset` is allowed to overflow.
+
+use std::hint::black_box;
+
+fn main() {
+    let array = [0u32; 8];
+    let start = black_box(array.as_ptr());
+    let huge = black_box(isize::MAX / 2 + 1);
+    unsafe {
+        assert_eq!(*start.offset(black_box(3)), 0);
+        assert_eq!(start.add(black_box(5)).offset(black_box(-5)), start);
+        let wrapped = start.wrapping_offset(huge).wrapping_offset(huge);
+        assert_eq!(
+            wrapped as usize,
+            (start as usize).wrapping_add((huge as usize).wrapping_mul(8))
+        );
+
+        let res = match std::env::args().nth(1).as_deref() {
+            None => return,
+            Some("offset") => start.offset(huge),
+            Some("add") => start.add(huge as usize * 2),
+            Some("add_bytes") => start.cast::<u8>().add(huge as usize * 2).cast(),
+            Some(op) => panic!("unknown operation {op}"),
+        };
+        unreachable!("byte offset overflow not detected, result: {res:p}");
+    }
+}
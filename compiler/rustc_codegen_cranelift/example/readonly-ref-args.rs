@@ -0,0 +1,28 @@
+// Loads through `&T` arguments with `T: Freeze` are marked readonly. The test harness checks the
+// flag in the clif ir of the functions below.
+
+use std::cell::Cell;
+
+#[no_mangle]
+#[inline(never)]
+pub fn readonly_read_twice(x: &(u32, u64), out: &mut u64) -> u64 {
+    let a = x.1;
+    *out = 42;
+    a + x.1 + x.0 as u64
+}
+
+// `Cell` isn't `Freeze`, so loads through `c` must not be marked readonly.
+#[no_mangle]
+#[inline(never)]
+pub fn readonly_read_cell(c: &Cell<u32>) -> u32 {
+    let a = c.get();
+    c.set(a + 1);
+    a + c.get()
+}
+
+fn main() {
+    let mut out = 0;
+    assert_eq!(readonly_read_twice(&(1, 2), &mut out), 5);
+    assert_eq!(out, 42);
+    assert_eq!(readonly_read_cell(&Cell::new(10)), 21);
+}
@@ -0,0 +1,23 @@
+// Compiled and run with -Crelocation-model=static. On x86_64 Linux the test harness also checks
+// that the object file references the address of `std::process::id` using an absolute relocation.
+
+use std::hint::black_box;
+
+static mut COUNTER: u32 = 0;
+
+#[inline(never)]
+fn bump() {
+    unsafe {
+        COUNTER += 1;
+    }
+}
+
+fn main() {
+    let bump_fn: fn() = black_box(bump);
+    bump_fn();
+    bump_fn();
+    assert_eq!(unsafe { COUNTER }, 2);
+
+    let id_fn: fn() -> u32 = black_box(std::process::id);
+    assert_eq!(id_fn(), std::process::id());
+}
@@ -0,0 +1,15 @@
+// Compiled for s390x-unknown-linux-gnu with debuginfo to check that the frame base and the call
+// frame information use the s390x stack pointer register. Doesn't depend on the sysroot, which is
+// only available for the host.
+
+#![feature(no_core, lang_items)]
+#![no_core]
+#![crate_type = "lib"]
+
+#[lang = "sized"]
+pub trait Sized {}
+
+#[no_mangle]
+pub fn frame_base_probe(x: u64) -> u64 {
+    x
+}
@@ -0,0 +1,27 @@
+// `#[repr(simd)]` locals that fit a 128bit vector register are kept in SSA variables. The test
+// harness checks in the clif ir that the function below doesn't spill the vectors to a stack slot.
+
+#![feature(core_intrinsics, repr_simd)]
+
+use std::intrinsics::simd::{simd_add, simd_extract};
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct F32x4(f32, f32, f32, f32);
+
+#[no_mangle]
+#[inline(never)]
+pub fn simd_local_sum(a: f32, b: f32, c: f32, d: f32) -> f32 {
+    let x = F32x4(a, b, c, d);
+    let y = F32x4(d, c, b, a);
+    unsafe {
+        let sum = simd_add(x, y);
+        let first: f32 = simd_extract(sum, 0);
+        let last: f32 = simd_extract(sum, 3);
+        first + last
+    }
+}
+
+fn main() {
+    assert_eq!(simd_local_sum(1.0, 2.0, 3.0, 4.0), 10.0);
+}
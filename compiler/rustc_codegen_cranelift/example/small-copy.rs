@@ -0,0 +1,14 @@
+// Compiled with optimizations and --emit llvm-ir. The test harness checks in the clif ir that the
+// small copies below are inlined as loads and stores rather than calls to memcpy or memmove.
+
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub unsafe fn small_copy_nonoverlapping(src: *const u64, dst: *mut u64) {
+    std::ptr::copy_nonoverlapping::<u64>(src, dst, 1);
+}
+
+#[no_mangle]
+pub unsafe fn small_copy(src: *const u64, dst: *mut u64) {
+    std::ptr::copy::<u64>(src, dst, 2);
+}
@@ -0,0 +1,18 @@
+// Compiled with optimizations and --emit llvm-ir. The test harness checks in the clif ir that the
+// comparisons below are inlined without a call to memcmp or a per-byte loop.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+#![crate_type = "lib"]
+
+use std::intrinsics::{compare_bytes, raw_eq};
+
+#[no_mangle]
+pub fn arrays_eq(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    unsafe { raw_eq(a, b) }
+}
+
+#[no_mangle]
+pub fn arrays_cmp(a: &[u8; 32], b: &[u8; 32]) -> i32 {
+    unsafe { compare_bytes(a.as_ptr(), b.as_ptr(), 32) }
+}
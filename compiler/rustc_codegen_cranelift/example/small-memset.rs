@@ -0,0 +1,9 @@
+// Compiled with optimizations and --emit llvm-ir. The test harness checks in the clif ir that the
+// small fill below is inlined as stores rather than a call to memset.
+
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub unsafe fn small_zero_fill(dst: *mut [u8; 16]) {
+    std::ptr::write_bytes(dst, 0, 1);
+}
@@ -0,0 +1,20 @@
+// Compiled with -Zstack-protector=strong. When passed `overflow` as argument, this overflows a
+// stack buffer, which should make the stack protector abort the process before `fill_buffer`
+// returns.
+
+#[no_mangle]
+#[inline(never)]
+fn fill_buffer(len: usize) {
+    let mut buf = [0u8; 16];
+    // Deliberately writes past the end of `buf` when `len` is bigger than 16.
+    unsafe { std::ptr::write_bytes(buf.as_mut_ptr(), 0xaa, len) };
+    std::hint::black_box(&buf);
+}
+
+fn main() {
+    fill_buffer(16);
+    if std::env::args().nth(1).as_deref() == Some("overflow") {
+        fill_buffer(64);
+        unreachable!("stack protector didn't detect the buffer overflow");
+    }
+}
@@ -1,10 +1,13 @@
 #![feature(
+    adt_const_params,
     core_intrinsics,
     coroutines,
     stmt_expr_attributes,
     coroutine_trait,
     is_sorted,
+    naked_functions,
     repr_simd,
+    thread_local,
     tuple_trait,
     unboxed_closures
 )]
@@ -111,6 +114,64 @@ fn main() {
     }
 
     test_checked_mul();
+    test_i128_div();
+    test_simd_aggregate();
+    test_simd_shuffle_generic();
+    test_simd_gather_scatter();
+    test_simd_select_bitmask();
+    test_simd_reduce();
+    test_fma();
+    test_float_rounding();
+    test_atomic_min_max();
+    test_compare_exchange_weak();
+    test_readonly_ref_args();
+    test_unaligned_volatile();
+    test_u128_bit_counting();
+    test_reverse_bits();
+    test_rotate();
+    test_saturating();
+    test_float_sign();
+    test_float_min_max();
+    #[cfg(not(jit))]
+    test_thread_local();
+    #[cfg(all(not(jit), target_os = "linux"))]
+    test_link_section();
+
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        unsafe { test_target_feature_simd() };
+    }
+
+    #[cfg(all(not(jit), target_os = "linux", target_env = "gnu"))]
+    {
+        test_atomic_min_max_u128();
+        test_atomic_u128_fetch_add();
+
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("cmpxchg16b") {
+            unsafe { test_atomic_u128_fetch_add_cmpxchg16b() };
+        }
+    }
+
+    #[cfg(all(not(jit), target_arch = "x86_64"))]
+    unsafe {
+        test_asm_sym();
+        test_asm_const();
+        test_feature_detection();
+    }
+
+    #[cfg(all(not(jit), target_arch = "x86_64", not(windows)))]
+    unsafe {
+        test_asm_clobber_abi();
+    }
+
+    #[cfg(all(not(jit), target_arch = "x86_64", target_os = "linux"))]
+    unsafe {
+        assert_eq!(global_asm_sym_test(), 42);
+    }
+
+    #[cfg(all(not(jit), target_arch = "x86_64", any(target_os = "linux", target_os = "macos")))]
+    assert_eq!(naked_add(black_box(40), black_box(2)), 42);
 
     let _a = 1u32 << 2u8;
 
@@ -263,6 +324,13 @@ unsafe fn test_simd() {
     test_mm256_shuffle_epi8();
     test_mm256_permute2x128_si256();
     test_mm256_permutevar8x32_epi32();
+    test_mm256_permutevar8x32_ps();
+    test_mm256_permutevar_ps();
+    test_mm256_add_epi32();
+    test_mm256_srlv_epi32();
+    test_mm256_srav_epi32();
+    test_mm_hadd_epi16();
+    test_mm256_hsub_epi32();
 
     #[rustfmt::skip]
     let mask1 = _mm_movemask_epi8(dbg!(_mm_setr_epi8(255u8 as i8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0)));
@@ -479,6 +547,78 @@ unsafe fn test_mm256_permutevar8x32_epi32() {
     assert_eq_m256i(r, e);
 }
 
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn test_mm256_permutevar8x32_ps() {
+    let a = _mm256_setr_ps(1., 2., 3., 4., 5., 6., 7., 8.);
+    // Only the lowest 3 bits of each index are used.
+    let idx = _mm256_setr_epi32(7, 6, 5, 4, 3, 2, 1, 8);
+    let r = _mm256_permutevar8x32_ps(a, idx);
+    let e = [8., 7., 6., 5., 4., 3., 2., 1.];
+    assert_eq!(std::mem::transmute::<_, [f32; 8]>(r), e);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx")]
+unsafe fn test_mm256_permutevar_ps() {
+    let a = _mm256_setr_ps(1., 2., 3., 4., 5., 6., 7., 8.);
+    // Lanes are only permuted within each 128bit half.
+    let idx = _mm256_setr_epi32(3, 2, 1, 4, 0, 1, 2, 3);
+    let r = _mm256_permutevar_ps(a, idx);
+    let e = [4., 3., 2., 1., 5., 6., 7., 8.];
+    assert_eq!(std::mem::transmute::<_, [f32; 8]>(r), e);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn test_mm256_add_epi32() {
+    let a = _mm256_setr_epi32(0, 1, 2, 3, 4, 5, 6, i32::MAX);
+    let b = _mm256_setr_epi32(10, 20, 30, 40, 50, 60, 70, 1);
+    let r = _mm256_add_epi32(a, b);
+    let e = _mm256_setr_epi32(10, 21, 32, 43, 54, 65, 76, i32::MIN);
+    assert_eq_m256i(r, e);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn test_mm256_srlv_epi32() {
+    let a = _mm256_set1_epi32(-1);
+    let count = _mm256_setr_epi32(0, 1, 4, 31, 32, 33, -1, 16);
+    let r = _mm256_srlv_epi32(a, count);
+    let e = _mm256_setr_epi32(-1, i32::MAX, 0x0fff_ffff, 1, 0, 0, 0, 0xffff);
+    assert_eq_m256i(r, e);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn test_mm256_srav_epi32() {
+    let a = _mm256_setr_epi32(-16, 16, -16, 16, -16, 16, i32::MIN, i32::MAX);
+    let count = _mm256_setr_epi32(0, 2, 2, 4, 31, 32, 100, -1);
+    let r = _mm256_srav_epi32(a, count);
+    let e = _mm256_setr_epi32(-16, 4, -4, 1, -1, 0, -1, 0);
+    assert_eq_m256i(r, e);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn test_mm_hadd_epi16() {
+    let a = _mm_setr_epi16(1, 2, 3, 4, 5, 6, 7, i16::MAX);
+    let b = _mm_setr_epi16(10, 20, 30, 40, 50, 60, 70, 80);
+    let r = _mm_hadd_epi16(a, b);
+    let e = _mm_setr_epi16(3, 7, 11, i16::MIN + 6, 30, 70, 110, 150);
+    assert_eq_m128i(r, e);
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn test_mm256_hsub_epi32() {
+    let a = _mm256_setr_epi32(1, 2, 3, 5, 100, 10, 200, 20);
+    let b = _mm256_setr_epi32(7, 11, 13, 17, 1000, 1, 2000, 2);
+    let r = _mm256_hsub_epi32(a, b);
+    let e = _mm256_setr_epi32(-1, -2, -4, -4, 90, 180, 999, 1998);
+    assert_eq_m256i(r, e);
+}
+
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2")]
 #[cfg(not(jit))]
@@ -533,6 +673,30 @@ fn test_checked_mul() {
     assert_eq!((-1i64).checked_mul(i64::MIN + 1), Some(i64::MAX));
     assert_eq!(1i64.checked_mul(i64::MIN), Some(i64::MIN));
     assert_eq!(i64::MIN.checked_mul(i64::MIN), None);
+
+    assert_eq!(black_box(u128::MAX).overflowing_mul(2), (u128::MAX - 1, true));
+    assert_eq!(black_box(u128::MAX).overflowing_mul(u128::MAX), (1, true));
+    assert_eq!(black_box(1u128 << 127).overflowing_mul(1 << 127), (0, true));
+    assert_eq!(black_box(u128::MAX).checked_mul(1), Some(u128::MAX));
+    assert_eq!(black_box(u128::MAX).checked_mul(0), Some(0));
+    assert_eq!(black_box(1u128 << 64).checked_mul(1 << 63), Some(1 << 127));
+    assert_eq!(black_box(1u128 << 64).checked_mul(1 << 64), None);
+    assert_eq!(black_box(u64::MAX as u128).checked_mul(u64::MAX as u128 + 2), Some(u128::MAX));
+    assert_eq!(black_box(u64::MAX as u128).checked_mul(u64::MAX as u128 + 3), None);
+    assert_eq!(
+        black_box(u64::MAX as u128).checked_mul(u64::MAX as u128),
+        Some(0xFFFF_FFFF_FFFF_FFFE_0000_0000_0000_0001)
+    );
+    assert_eq!(black_box(i128::MIN).overflowing_mul(-1), (i128::MIN, true));
+    assert_eq!(black_box(i128::MIN).checked_mul(1), Some(i128::MIN));
+    assert_eq!(black_box(i128::MIN).checked_mul(0), Some(0));
+    assert_eq!(black_box(i128::MIN + 1).checked_mul(-1), Some(i128::MAX));
+    assert_eq!(black_box(-(1i128 << 63)).checked_mul(1 << 64), Some(i128::MIN));
+    assert_eq!(black_box(1i128 << 63).checked_mul(1 << 64), None);
+    assert_eq!(black_box(i128::MAX).checked_mul(-1), Some(-i128::MAX));
+    assert_eq!(black_box(-3i128).checked_mul(-5), Some(15));
+    assert_eq!(black_box(-3i128).checked_mul(5), Some(-15));
+    assert_eq!(black_box(i128::MAX).overflowing_mul(i128::MAX), (1, true));
 }
 
 #[derive(PartialEq)]
@@ -546,6 +710,771 @@ pub enum Instruction {
     Loop,
 }
 
+fn test_i128_div() {
+    let max = u64::MAX as u128;
+    assert_eq!(black_box(max) / black_box(3), max / 3);
+    assert_eq!(black_box(max) % black_box(7), max % 7);
+    assert_eq!(black_box(max + 1) / black_box(2), 1 << 63);
+    assert_eq!(black_box(max + 1) % black_box(max), 1);
+    assert_eq!(black_box(max * 5) / black_box(max), 5);
+    assert_eq!(black_box(max) / black_box(max + 1), 0);
+    assert_eq!(black_box(max) % black_box(max + 1), max);
+
+    let min = i64::MIN as i128;
+    assert_eq!(black_box(min) / black_box(-1), 1 << 63);
+    assert_eq!(black_box(min) % black_box(-1), 0);
+    assert_eq!(black_box(min - 1) / black_box(2), min / 2);
+    assert_eq!(black_box(-7i128) / black_box(2), -3);
+    assert_eq!(black_box(-7i128) % black_box(2), -1);
+    assert_eq!(black_box(i128::MIN) / black_box(min), 1 << 64);
+    assert_eq!(black_box(i64::MAX as i128 + 1) / black_box(-1), min);
+
+    let mut sum = 0u128;
+    for i in 1..1000u128 {
+        sum += black_box(max - i) / black_box(i) + black_box(max - i) % black_box(i);
+    }
+    assert_eq!(sum, (1..1000u128).map(|i| (max - i) / i + (max - i) % i).sum());
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct F32x4(f32, f32, f32, f32);
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct U8x16([u8; 16]);
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct F32x1([f32; 1]);
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct I64x4(i64, i64, i64, i64);
+
+fn test_simd_aggregate() {
+    use std::intrinsics::simd::simd_add;
+
+    let (a, b, c, d) = black_box((1.0f32, 2.0, 3.0, 4.0));
+    let x = F32x4(a, b, c, d);
+    let y = F32x4(d, c, b, a);
+    let sum: [f32; 4] = unsafe { std::mem::transmute(simd_add(x, y)) };
+    assert_eq!(sum, [5.0; 4]);
+
+    let bytes = U8x16(black_box([7; 16]));
+    let sum: [u8; 16] = unsafe { std::mem::transmute(simd_add(bytes, bytes)) };
+    assert_eq!(sum, [14; 16]);
+
+    let single = F32x1([black_box(1.5)]);
+    let sum: [f32; 1] = unsafe { std::mem::transmute(simd_add(single, single)) };
+    assert_eq!(sum, [3.0]);
+
+    let (a, b) = black_box((i64::MAX - 1, -5i64));
+    let wide = I64x4(a, b, a, b);
+    let sum: [i64; 4] = unsafe { std::mem::transmute(simd_add(wide, I64x4(1, 5, 1, 5))) };
+    assert_eq!(sum, [i64::MAX, 0, i64::MAX, 0]);
+}
+
+const SHUFFLE_REVERSE: &[u32] = &[3, 2, 1, 0];
+const SHUFFLE_INTERLEAVE_LOW: &[u32] = &[0, 4, 1, 5];
+const SHUFFLE_INTERLEAVE_BYTES: &[u32] = &[0, 16, 1, 17, 2, 18, 3, 19, 4, 20, 5, 21, 6, 22, 7, 23];
+const SHUFFLE_ODD_LANES: &[u32] = &[1, 3];
+
+fn test_simd_shuffle_generic() {
+    use std::intrinsics::simd::simd_shuffle_generic;
+
+    let (a, b, c, d) = black_box((1.0f32, 2.0, 3.0, 4.0));
+    let x = F32x4(a, b, c, d);
+    let y = F32x4(-a, -b, -c, -d);
+
+    let reversed: [f32; 4] =
+        unsafe { std::mem::transmute(simd_shuffle_generic::<F32x4, F32x4, SHUFFLE_REVERSE>(x, y)) };
+    assert_eq!(reversed, [4.0, 3.0, 2.0, 1.0]);
+
+    let interleaved: [f32; 4] = unsafe {
+        std::mem::transmute(simd_shuffle_generic::<F32x4, F32x4, SHUFFLE_INTERLEAVE_LOW>(x, y))
+    };
+    assert_eq!(interleaved, [1.0, -1.0, 2.0, -2.0]);
+
+    let bytes: [u8; 16] = black_box([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+    let x = U8x16(bytes);
+    let y = U8x16(bytes.map(|byte| byte + 100));
+    let interleaved: [u8; 16] = unsafe {
+        std::mem::transmute(simd_shuffle_generic::<U8x16, U8x16, SHUFFLE_INTERLEAVE_BYTES>(x, y))
+    };
+    assert_eq!(interleaved, [0, 100, 1, 101, 2, 102, 3, 103, 4, 104, 5, 105, 6, 106, 7, 107]);
+
+    let x = I64x4(1, 2, 3, 4);
+    let odd: [i64; 2] = unsafe {
+        std::mem::transmute(simd_shuffle_generic::<I64x4, I64X2, SHUFFLE_ODD_LANES>(x, x))
+    };
+    assert_eq!(odd, [2, 4]);
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct I32x4(i32, i32, i32, i32);
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct ConstPtrX4(*const i32, *const i32, *const i32, *const i32);
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct MutPtrX4(*mut i32, *mut i32, *mut i32, *mut i32);
+
+fn test_simd_gather_scatter() {
+    use std::intrinsics::simd::{simd_gather, simd_scatter};
+
+    let data = black_box([10, 11, 12, 13, 14, 15, 16, 17, 18, 19]);
+    let base = data.as_ptr();
+    let ptrs = unsafe { ConstPtrX4(base.add(7), base.add(1), base.add(9), base.add(4)) };
+    let passthrough = I32x4(-1, -2, -3, -4);
+
+    let all = I32x4(!0, !0, !0, !0);
+    let gathered: [i32; 4] = unsafe { std::mem::transmute(simd_gather(passthrough, ptrs, all)) };
+    assert_eq!(gathered, [17, 11, 19, 14]);
+
+    let some = I32x4(!0, 0, 0, !0);
+    let gathered: [i32; 4] = unsafe { std::mem::transmute(simd_gather(passthrough, ptrs, some)) };
+    assert_eq!(gathered, [17, -2, -3, 14]);
+
+    let mut data = [0; 8];
+    let base = data.as_mut_ptr();
+    let ptrs = unsafe { MutPtrX4(base.add(6), base.add(0), base.add(3), base.add(5)) };
+    let values = I32x4(1, 2, 3, 4);
+    let mask = I32x4(0, !0, !0, 0);
+    unsafe { simd_scatter(values, ptrs, black_box(mask)) };
+    assert_eq!(data, [2, 0, 0, 3, 0, 0, 0, 0]);
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct I32x8([i32; 8]);
+
+fn test_atomic_min_max() {
+    use std::sync::atomic::{AtomicI64, AtomicU32, Ordering};
+
+    let a = AtomicI64::new(-5);
+    assert_eq!(a.fetch_min(black_box(-10), Ordering::SeqCst), -5);
+    assert_eq!(a.fetch_min(black_box(3), Ordering::SeqCst), -10);
+    assert_eq!(a.fetch_max(black_box(-20), Ordering::SeqCst), -10);
+    assert_eq!(a.fetch_max(black_box(-1), Ordering::SeqCst), -10);
+    assert_eq!(a.load(Ordering::SeqCst), -1);
+
+    let b = AtomicU32::new(0x8000_0000);
+    assert_eq!(b.fetch_min(black_box(7), Ordering::Relaxed), 0x8000_0000);
+    assert_eq!(b.fetch_max(black_box(u32::MAX), Ordering::Relaxed), 7);
+    assert_eq!(b.load(Ordering::Relaxed), u32::MAX);
+}
+
+fn test_readonly_ref_args() {
+    use std::cell::Cell;
+
+    // Loads through `x` are marked readonly, so the second load of `x.1` may reuse the first.
+    #[inline(never)]
+    fn read_twice(x: &(u32, u64), out: &mut u64) -> u64 {
+        let a = x.1;
+        *out = 42;
+        a + x.1 + x.0 as u64
+    }
+
+    #[inline(never)]
+    fn read_variant(x: &Option<u64>, out: &mut u64) -> u64 {
+        *out = 7;
+        if let Some(val) = *x { val } else { 0 }
+    }
+
+    // `Cell` isn't `Freeze`, so loads through `c` must observe the store.
+    #[inline(never)]
+    fn read_cell(c: &Cell<u32>) -> u32 {
+        let a = c.get();
+        c.set(a + 1);
+        a + c.get()
+    }
+
+    let mut out = 0;
+    assert_eq!(read_twice(black_box(&(1, 2)), &mut out), 5);
+    assert_eq!(out, 42);
+    assert_eq!(read_variant(black_box(&Some(3)), &mut out), 3);
+    assert_eq!(out, 7);
+    assert_eq!(read_cell(black_box(&Cell::new(10))), 21);
+}
+
+fn test_unaligned_volatile() {
+    use std::intrinsics::{unaligned_volatile_load, unaligned_volatile_store};
+
+    #[repr(C, packed)]
+    struct Packed {
+        tag: u8,
+        val: u32,
+    }
+
+    #[repr(C, align(4))]
+    struct Aligned(Packed);
+
+    // `Packed` is placed at a 4 byte aligned address, so `val` is always misaligned.
+    let mut aligned = Aligned(Packed { tag: 1, val: 0x12345678 });
+    let val = std::ptr::addr_of_mut!(aligned.0.val);
+    unsafe {
+        assert_eq!(unaligned_volatile_load(black_box(val)), 0x12345678);
+        unaligned_volatile_store(black_box(val), 0x9abcdef0);
+        assert_eq!(unaligned_volatile_load(black_box(val)), 0x9abcdef0);
+    }
+    assert_eq!({ aligned.0.tag }, 1);
+    assert_eq!({ aligned.0.val }, 0x9abcdef0);
+}
+
+fn test_u128_bit_counting() {
+    assert_eq!(black_box(1u128).leading_zeros(), 127);
+    assert_eq!(black_box(u128::MAX).leading_zeros(), 0);
+    assert_eq!(black_box(0u128).leading_zeros(), 128);
+    assert_eq!(black_box(1u128 << 64).leading_zeros(), 63);
+
+    assert_eq!(black_box(1u128).trailing_zeros(), 0);
+    assert_eq!(black_box(1u128 << 64).trailing_zeros(), 64);
+    assert_eq!(black_box(0u128).trailing_zeros(), 128);
+
+    assert_eq!(black_box(u128::MAX).count_ones(), 128);
+    assert_eq!(black_box(0x1_0000_0000_0000_0001u128).count_ones(), 2);
+
+    assert_eq!(
+        black_box(0x0102030405060708_090a0b0c0d0e0f10u128).swap_bytes(),
+        0x100f0e0d0c0b0a09_0807060504030201u128,
+    );
+}
+
+fn test_reverse_bits() {
+    assert_eq!(black_box(0b1000_0110u8).reverse_bits(), 0b0110_0001u8);
+    assert_eq!(black_box(0x1234_5678u32).reverse_bits(), 0x1e6a_2c48u32);
+    assert_eq!(black_box(1u128).reverse_bits(), 1u128 << 127);
+    assert_eq!(
+        black_box(0x0000_0000_0000_0001_8000_0000_0000_0003u128).reverse_bits(),
+        0xc000_0000_0000_0001_8000_0000_0000_0000u128,
+    );
+}
+
+fn test_rotate() {
+    let x = black_box(0x8000_0000_0000_0001u64);
+    assert_eq!(x.rotate_left(black_box(65)), (x << 1) | (x >> 63));
+    assert_eq!(x.rotate_right(black_box(65)), (x >> 1) | (x << 63));
+    assert_eq!(x.rotate_left(black_box(64)), x);
+    assert_eq!(black_box(0x81u8).rotate_left(black_box(9)), 0x03);
+
+    let y = black_box(0x8000_0000_0000_0000_0000_0000_0000_0001u128);
+    assert_eq!(y.rotate_left(black_box(129)), 0x3);
+    assert_eq!(y.rotate_right(black_box(1)), 0xc000_0000_0000_0000_0000_0000_0000_0000);
+    assert_eq!(y.rotate_left(black_box(0)), y);
+    assert_eq!(y.rotate_right(black_box(64)), 0x0000_0000_0000_0001_8000_0000_0000_0000);
+}
+
+fn test_saturating() {
+    use std::intrinsics::simd::{simd_saturating_add, simd_saturating_sub};
+
+    assert_eq!(black_box(u8::MAX).saturating_add(10), u8::MAX);
+    assert_eq!(black_box(i8::MIN).saturating_sub(1), i8::MIN);
+    assert_eq!(black_box(i128::MAX).saturating_add(1), i128::MAX);
+
+    let a = U8x16(black_box([0, 1, 2, 3, 4, 5, 6, 7, 250, 251, 252, 253, 254, 255, 128, 127]));
+    let b = U8x16(black_box([10; 16]));
+    let U8x16(sum) = unsafe { simd_saturating_add(a, b) };
+    assert_eq!(sum, [10, 11, 12, 13, 14, 15, 16, 17, 255, 255, 255, 255, 255, 255, 138, 137]);
+    let U8x16(diff) = unsafe { simd_saturating_sub(a, b) };
+    assert_eq!(diff, [0, 0, 0, 0, 0, 0, 0, 0, 240, 241, 242, 243, 244, 245, 118, 117]);
+}
+
+fn test_float_sign() {
+    use std::intrinsics::simd::simd_fabs;
+
+    let pos_zero = black_box(-0.0f64).copysign(1.0);
+    assert_eq!(pos_zero.to_bits(), 0.0f64.to_bits());
+    assert_eq!(black_box(1.5f32).copysign(-0.0), -1.5);
+    assert_eq!(black_box(f64::NAN).copysign(-1.0).to_bits() >> 63, 1);
+
+    let nan_abs = black_box(-f32::NAN).abs();
+    assert!(nan_abs.is_nan());
+    assert!(nan_abs.is_sign_positive());
+    assert_eq!(black_box(f32::NAN).abs().to_bits() >> 31, 0);
+    assert_eq!(black_box(-0.0f64).abs().to_bits(), 0);
+
+    assert_eq!(black_box(-3.0f64).signum(), -1.0);
+    assert_eq!(black_box(0.0f32).signum(), 1.0);
+    assert_eq!(black_box(-0.0f32).signum(), -1.0);
+    assert!(black_box(f64::NAN).signum().is_nan());
+
+    let F32x4(a, b, c, d) =
+        unsafe { simd_fabs(F32x4(black_box(-1.0), 2.0, -0.0, black_box(-f32::INFINITY))) };
+    assert_eq!([a, b, d], [1.0, 2.0, f32::INFINITY]);
+    assert_eq!(c.to_bits(), 0);
+}
+
+fn test_float_min_max() {
+    use std::intrinsics::simd::{simd_fmax, simd_fmin};
+
+    let nan = black_box(f64::NAN);
+    assert_eq!(nan.min(1.0), 1.0);
+    assert_eq!(black_box(1.0f64).min(nan), 1.0);
+    assert_eq!(nan.max(-1.0), -1.0);
+    assert_eq!(black_box(-1.0f64).max(nan), -1.0);
+    assert!(nan.min(nan).is_nan());
+    assert_eq!(black_box(f32::NAN).min(2.0), 2.0);
+    assert_eq!(black_box(2.0f32).max(f32::NAN), 2.0);
+
+    let (pos_zero, neg_zero) = (black_box(0.0f64), black_box(-0.0f64));
+    assert!(pos_zero.min(neg_zero).is_sign_negative());
+    assert!(neg_zero.min(pos_zero).is_sign_negative());
+    assert!(pos_zero.max(neg_zero).is_sign_positive());
+    assert!(neg_zero.max(pos_zero).is_sign_positive());
+    assert_eq!(black_box(3.0f32).min(-4.0), -4.0);
+    assert_eq!(black_box(3.0f32).max(-4.0), 3.0);
+
+    let a = F32x4(black_box(f32::NAN), 1.0, -0.0, 5.0);
+    let b = F32x4(2.0, black_box(f32::NAN), 0.0, -5.0);
+    let F32x4(min0, min1, min2, min3) = unsafe { simd_fmin(a, b) };
+    assert_eq!([min0, min1, min3], [2.0, 1.0, -5.0]);
+    assert!(min2.is_sign_negative());
+    let F32x4(max0, max1, max2, max3) = unsafe { simd_fmax(a, b) };
+    assert_eq!([max0, max1, max3], [2.0, 1.0, 5.0]);
+    assert!(max2.is_sign_positive());
+}
+
+fn test_compare_exchange_weak() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let counter = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    let mut current = counter.load(Ordering::Relaxed);
+                    // Weak compare-exchange may fail spuriously, so retry until it succeeds.
+                    while let Err(actual) = counter.compare_exchange_weak(
+                        current,
+                        current + 1,
+                        Ordering::AcqRel,
+                        Ordering::Relaxed,
+                    ) {
+                        current = actual;
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(counter.load(Ordering::SeqCst), 4000);
+}
+
+#[cfg(not(jit))]
+#[thread_local]
+static mut TLS_COUNTER: u32 = 0;
+
+#[cfg(not(jit))]
+fn test_thread_local() {
+    fn bump(n: u32) -> u32 {
+        for _ in 0..n {
+            unsafe { TLS_COUNTER += 1 };
+        }
+        unsafe { TLS_COUNTER }
+    }
+
+    std::thread::scope(|s| {
+        let a = s.spawn(|| bump(black_box(10)));
+        let b = s.spawn(|| bump(black_box(20)));
+        assert_eq!(a.join().unwrap(), 10);
+        assert_eq!(b.join().unwrap(), 20);
+    });
+    assert_eq!(bump(black_box(5)), 5);
+}
+
+#[cfg(all(not(jit), target_os = "linux"))]
+#[link_section = "cg_clif_custom_ro"]
+static CUSTOM_SECTION_RO: [u32; 2] = [0x1234_5678, 0x9abc_def0];
+
+#[cfg(all(not(jit), target_os = "linux"))]
+#[link_section = "cg_clif_custom_rw"]
+static mut CUSTOM_SECTION_RW: u32 = 1;
+
+#[cfg(all(not(jit), target_os = "linux"))]
+fn test_link_section() {
+    // The linker defines these for every section whose name is a valid C identifier.
+    extern "C" {
+        static __start_cg_clif_custom_ro: u8;
+        static __stop_cg_clif_custom_ro: u8;
+    }
+
+    unsafe {
+        let start = std::ptr::addr_of!(__start_cg_clif_custom_ro) as usize;
+        let stop = std::ptr::addr_of!(__stop_cg_clif_custom_ro) as usize;
+        let addr = black_box(std::ptr::addr_of!(CUSTOM_SECTION_RO)) as usize;
+        assert!(start <= addr && addr + 8 <= stop);
+        assert_eq!(CUSTOM_SECTION_RO, [0x1234_5678, 0x9abc_def0]);
+
+        // Writing would crash if the section was read-only.
+        *black_box(std::ptr::addr_of_mut!(CUSTOM_SECTION_RW)) += 1;
+        assert_eq!(CUSTOM_SECTION_RW, 2);
+    }
+}
+
+// Without cmpxchg16b, 128bit atomics are lowered to `__atomic_*_16` libcalls.
+#[cfg(all(not(jit), target_os = "linux", target_env = "gnu"))]
+#[link(name = "atomic")]
+extern "C" {}
+
+#[cfg(all(not(jit), target_os = "linux", target_env = "gnu"))]
+fn test_atomic_min_max_u128() {
+    use std::intrinsics::{
+        atomic_max_seqcst, atomic_min_seqcst, atomic_umax_seqcst, atomic_umin_seqcst,
+    };
+
+    #[repr(align(16))]
+    struct Aligned<T>(T);
+
+    let mut u = Aligned(1u128 << 100);
+    unsafe {
+        assert_eq!(atomic_umax_seqcst(&mut u.0, black_box(u128::MAX - 1)), 1 << 100);
+        assert_eq!(atomic_umin_seqcst(&mut u.0, black_box(1 << 64)), u128::MAX - 1);
+    }
+    assert_eq!(u.0, 1 << 64);
+
+    let mut i = Aligned(-1i128);
+    unsafe {
+        assert_eq!(atomic_min_seqcst(&mut i.0, black_box(i128::MIN + 1)), -1);
+        assert_eq!(atomic_max_seqcst(&mut i.0, black_box(-(1 << 90))), i128::MIN + 1);
+    }
+    assert_eq!(i.0, -(1 << 90));
+}
+
+#[cfg(all(not(jit), target_os = "linux", target_env = "gnu"))]
+fn test_atomic_u128_fetch_add() {
+    use std::intrinsics::{atomic_load_seqcst, atomic_store_seqcst, atomic_xadd_seqcst};
+
+    #[repr(align(16))]
+    struct Aligned(u128);
+
+    let mut counter = Aligned(0);
+    let counter_ptr = &mut counter.0 as *mut u128 as usize;
+    unsafe { atomic_store_seqcst(counter_ptr as *mut u128, black_box(u64::MAX as u128)) };
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    unsafe { atomic_xadd_seqcst(counter_ptr as *mut u128, (1 << 64) + 1) };
+                }
+            });
+        }
+    });
+
+    let total = unsafe { atomic_load_seqcst(counter_ptr as *const u128) };
+    assert_eq!(total, u64::MAX as u128 + 4000 * ((1 << 64) + 1));
+}
+
+#[cfg(all(not(jit), target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+#[target_feature(enable = "cmpxchg16b")]
+unsafe fn test_atomic_u128_fetch_add_cmpxchg16b() {
+    use std::intrinsics::{atomic_cxchg_seqcst_seqcst, atomic_load_seqcst, atomic_xadd_seqcst};
+
+    #[repr(align(16))]
+    struct Aligned(u128);
+
+    let mut counter = Aligned(u64::MAX as u128);
+    let counter_ptr = &mut counter.0 as *mut u128 as usize;
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    unsafe { atomic_xadd_seqcst(counter_ptr as *mut u128, (1 << 64) + 1) };
+                }
+            });
+        }
+    });
+
+    let ptr = counter_ptr as *mut u128;
+    let total = u64::MAX as u128 + 4000 * ((1 << 64) + 1);
+    assert_eq!(atomic_load_seqcst(ptr), total);
+    assert_eq!(atomic_cxchg_seqcst_seqcst(ptr, black_box(0), 1), (total, false));
+    assert_eq!(atomic_cxchg_seqcst_seqcst(ptr, black_box(total), 1), (total, true));
+    assert_eq!(atomic_load_seqcst(ptr), 1);
+}
+
+// Compiled with a `TargetIsa` that has AVX2 enabled, so 256bit vectors don't need to be
+// split into 128bit halves by the target.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn test_target_feature_simd() {
+    use std::intrinsics::simd::{simd_add, simd_mul};
+
+    let a = I32x8(black_box([0, 1, 2, 3, 4, 5, 6, 7]));
+    let b = I32x8(black_box([7, 6, 5, 4, 3, 2, 1, 0]));
+
+    let sum: [i32; 8] = transmute(simd_add(a, b));
+    assert_eq!(sum, [7; 8]);
+    let product: [i32; 8] = transmute(simd_mul(a, b));
+    assert_eq!(product, [0, 6, 10, 12, 12, 10, 6, 0]);
+}
+
+fn test_simd_select_bitmask() {
+    use std::intrinsics::simd::simd_select_bitmask;
+
+    let a = I32x8([0, 1, 2, 3, 4, 5, 6, 7]);
+    let b = I32x8([-0, -1, -2, -3, -4, -5, -6, -7]);
+
+    // Bit `n` of the mask selects lane `n` on little endian targets.
+    let selected: [i32; 8] =
+        unsafe { std::mem::transmute(simd_select_bitmask(black_box(0b1010_0011u8), a, b)) };
+    if cfg!(target_endian = "little") {
+        assert_eq!(selected, [0, 1, -2, -3, -4, 5, -6, 7]);
+    } else {
+        assert_eq!(selected, [0, -1, 2, -3, -4, -5, 6, 7]);
+    }
+
+    let selected: [i32; 8] = unsafe { std::mem::transmute(simd_select_bitmask(0u8, a, b)) };
+    assert_eq!(selected, [0, -1, -2, -3, -4, -5, -6, -7]);
+    let selected: [i32; 8] = unsafe { std::mem::transmute(simd_select_bitmask(!0u8, a, b)) };
+    assert_eq!(selected, [0, 1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct F32x8([f32; 8]);
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct I32x3(i32, i32, i32);
+
+fn test_simd_reduce() {
+    use std::intrinsics::simd::*;
+
+    // Summing these in a different order gives a different result.
+    let lanes = black_box([1.0e8f32, 1.0, -1.0e8, 1.0, 3.0e-8, 1.0e8, 0.5, -1.0e8]);
+    let sequential = lanes.iter().fold(-0.0f32, |acc, &lane| acc + lane);
+    let ordered: f32 = unsafe { simd_reduce_add_ordered(F32x8(lanes), -0.0) };
+    assert_eq!(ordered.to_bits(), sequential.to_bits());
+
+    let sequential = lanes.iter().fold(2.0f32, |acc, &lane| acc * lane);
+    let ordered: f32 = unsafe { simd_reduce_mul_ordered(F32x8(lanes), 2.0) };
+    assert_eq!(ordered.to_bits(), sequential.to_bits());
+
+    // Exactly representable partial sums give the same result in any order.
+    let lanes = black_box([1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+    let unordered: f32 = unsafe { simd_reduce_add_unordered(F32x8(lanes)) };
+    assert_eq!(unordered, 36.0);
+    let unordered: f32 = unsafe { simd_reduce_mul_unordered(F32x8(lanes)) };
+    assert_eq!(unordered, 40320.0);
+
+    let v = I32x8(black_box([3, -7, 12, 0, 5, -1, 9, 2]));
+    assert_eq!(unsafe { simd_reduce_add_unordered::<_, i32>(v) }, 23);
+    assert_eq!(unsafe { simd_reduce_min::<_, i32>(v) }, -7);
+    assert_eq!(unsafe { simd_reduce_max::<_, i32>(v) }, 12);
+    assert_eq!(unsafe { simd_reduce_and::<_, i32>(v) }, 0);
+    assert_eq!(unsafe { simd_reduce_or::<_, i32>(v) }, -1);
+    assert_eq!(unsafe { simd_reduce_xor::<_, i32>(v) }, 3 ^ -7 ^ 12 ^ 0 ^ 5 ^ -1 ^ 9 ^ 2);
+
+    // Non power of two lane count
+    let v = I32x3(black_box(2), 3, 4);
+    assert_eq!(unsafe { simd_reduce_mul_unordered::<_, i32>(v) }, 24);
+}
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+struct F64x2(f64, f64);
+
+fn test_fma() {
+    use std::intrinsics::simd::simd_fma;
+
+    // `a * b` is not exactly representable, so only a fused multiply-add gives `2^-60` here
+    // rather than `0`.
+    let a = black_box(1.0 + 2.0f64.powi(-30));
+    let c = black_box(-(1.0 + 2.0f64.powi(-29)));
+    let expected = 2.0f64.powi(-60);
+    assert_eq!(a.mul_add(a, c), expected);
+    assert_eq!((a as f32).mul_add(2.0, 1.0), 3.0);
+
+    let res: [f64; 2] =
+        unsafe { std::mem::transmute(simd_fma(F64x2(a, 2.0), F64x2(a, 3.0), F64x2(c, 1.0))) };
+    assert_eq!(res, [expected, 7.0]);
+}
+
+fn test_float_rounding() {
+    use std::intrinsics::{
+        ceilf32, ceilf64, floorf32, floorf64, nearbyintf32, nearbyintf64, rintf32, rintf64,
+        roundevenf32, roundevenf64, roundf32, roundf64, truncf32, truncf64,
+    };
+
+    macro_rules! check {
+        ($($f32_fn:ident, $f64_fn:ident: $($input:literal => $output:literal),*;)*) => {$($(
+            assert_eq!(unsafe { $f32_fn(black_box($input)) }, $output);
+            assert_eq!(unsafe { $f64_fn(black_box($input)) }, $output);
+        )*)*};
+    }
+
+    check! {
+        ceilf32, ceilf64: 2.5 => 3.0, -2.5 => -2.0, 3.5 => 4.0, -3.5 => -3.0;
+        floorf32, floorf64: 2.5 => 2.0, -2.5 => -3.0, 3.5 => 3.0, -3.5 => -4.0;
+        truncf32, truncf64: 2.5 => 2.0, -2.5 => -2.0, 3.5 => 3.0, -3.5 => -3.0;
+        roundf32, roundf64: 2.5 => 3.0, -2.5 => -3.0, 3.5 => 4.0, -3.5 => -4.0;
+        roundevenf32, roundevenf64: 2.5 => 2.0, -2.5 => -2.0, 3.5 => 4.0, -3.5 => -4.0;
+        rintf32, rintf64: 2.5 => 2.0, -2.5 => -2.0, 3.5 => 4.0, -3.5 => -4.0;
+        nearbyintf32, nearbyintf64: 2.5 => 2.0, -2.5 => -2.0, 3.5 => 4.0, -3.5 => -4.0;
+    }
+
+    // The sign of zero is preserved.
+    assert!(unsafe { ceilf64(black_box(-0.5)) }.is_sign_negative());
+    assert!(unsafe { roundevenf32(black_box(-0.5)) }.is_sign_negative());
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64"))]
+static ASM_SYM_STATIC: u32 = 0xdeadbeef;
+
+#[cfg(all(not(jit), target_arch = "x86_64"))]
+extern "C" fn asm_sym_fn() -> u32 {
+    42
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64"))]
+unsafe fn test_asm_sym() {
+    let ptr: *const u32;
+    std::arch::asm!("lea {}, [rip + {}]", out(reg) ptr, sym ASM_SYM_STATIC);
+    assert_eq!(ptr, &ASM_SYM_STATIC as *const u32);
+    assert_eq!(*ptr, 0xdeadbeef);
+
+    let ptr: *const u8;
+    std::arch::asm!("lea {}, [rip + {}]", out(reg) ptr, sym asm_sym_fn);
+    let f = transmute::<*const u8, extern "C" fn() -> u32>(ptr);
+    assert_eq!(f(), 42);
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64"))]
+trait AsmConst {
+    const VALUE: i32;
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64"))]
+struct AsmConstSeven;
+
+#[cfg(all(not(jit), target_arch = "x86_64"))]
+impl AsmConst for AsmConstSeven {
+    const VALUE: i32 = 7;
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64"))]
+#[target_feature(enable = "xsave")]
+unsafe fn test_feature_detection() {
+    // Query the CPU directly and compare with the result of runtime feature detection.
+    let leaf1 = __cpuid(1);
+    let leaf7 = __cpuid_count(7, 0);
+    let osxsave = leaf1.ecx & (1 << 27) != 0;
+    let os_avx_support = osxsave && _xgetbv(0) & 0b110 == 0b110;
+    let avx2 = leaf7.ebx & (1 << 5) != 0 && os_avx_support;
+    assert_eq!(is_x86_feature_detected!("avx2"), avx2);
+    assert_eq!(is_x86_feature_detected!("sse4.1"), leaf1.ecx & (1 << 19) != 0);
+    assert!(is_x86_feature_detected!("sse2"));
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64"))]
+unsafe fn test_asm_const() {
+    let a: u32;
+    std::arch::asm!("mov {:e}, {}", out(reg) a, const 42);
+    assert_eq!(a, 42);
+
+    let b: i64;
+    std::arch::asm!("mov {}, {}", out(reg) b, const -42i64);
+    assert_eq!(b, -42);
+
+    let c: i8;
+    std::arch::asm!("mov {}, {}", out(reg_byte) c, const i8::MIN);
+    assert_eq!(c, i8::MIN);
+
+    let d: i32;
+    std::arch::asm!("mov {:e}, {}", out(reg) d, const AsmConstSeven::VALUE * -2);
+    assert_eq!(d, -14);
+
+    let e: i64;
+    std::arch::asm!("movq ${}, {}", const -3, out(reg) e, options(att_syntax));
+    assert_eq!(e, -3);
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64", not(windows)))]
+unsafe fn test_asm_clobber_abi() {
+    let ints = [black_box(1u64), black_box(2), black_box(3), black_box(4), black_box(5)];
+    let floats = [black_box(1.5f64), black_box(2.5), black_box(3.5)];
+
+    // Overwrite all registers that are caller-saved in the SysV ABI.
+    std::arch::asm!(
+        "xor eax, eax",
+        "xor ecx, ecx",
+        "xor edx, edx",
+        "xor esi, esi",
+        "xor edi, edi",
+        "xor r8d, r8d",
+        "xor r9d, r9d",
+        "xor r10d, r10d",
+        "xor r11d, r11d",
+        "xorps xmm0, xmm0",
+        "xorps xmm1, xmm1",
+        "xorps xmm2, xmm2",
+        "xorps xmm3, xmm3",
+        "xorps xmm4, xmm4",
+        "xorps xmm5, xmm5",
+        "xorps xmm6, xmm6",
+        "xorps xmm7, xmm7",
+        "xorps xmm8, xmm8",
+        "xorps xmm9, xmm9",
+        "xorps xmm10, xmm10",
+        "xorps xmm11, xmm11",
+        "xorps xmm12, xmm12",
+        "xorps xmm13, xmm13",
+        "xorps xmm14, xmm14",
+        "xorps xmm15, xmm15",
+        clobber_abi("C"),
+    );
+
+    assert_eq!(ints, [1, 2, 3, 4, 5]);
+    assert_eq!(floats, [1.5, 2.5, 3.5]);
+
+    // Callee-saved registers must be preserved when explicitly clobbered.
+    let a = black_box(42u64);
+    let b: u64;
+    std::arch::asm!("mov r12, 7", "mov {}, r12", out(reg) b, out("r12") _, clobber_abi("C"));
+    assert_eq!(a, 42);
+    assert_eq!(b, 7);
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64", target_os = "linux"))]
+std::arch::global_asm! {
+    ".text",
+    ".global global_asm_sym_test",
+    "global_asm_sym_test:",
+    "jmp {}",
+    sym global_asm_sym_callee,
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64", target_os = "linux"))]
+extern "C" {
+    fn global_asm_sym_test() -> u32;
+}
+
+// Not exported from the crate, so only reachable from the global asm through a wrapper.
+#[cfg(all(not(jit), target_arch = "x86_64", target_os = "linux"))]
+extern "C" fn global_asm_sym_callee() -> u32 {
+    42
+}
+
+#[cfg(all(not(jit), target_arch = "x86_64", any(target_os = "linux", target_os = "macos")))]
+#[naked]
+extern "sysv64" fn naked_add(_a: u64, _b: u64) -> u64 {
+    unsafe {
+        std::arch::asm!(
+            "push rbp",
+            "mov rbp, rsp",
+            "lea rax, [rdi + rsi]",
+            "pop rbp",
+            "ret",
+            options(noreturn)
+        );
+    }
+}
+
 fn map(a: Option<(u8, Box<Instruction>)>) -> Option<Box<Instruction>> {
     match a {
         None => None,
@@ -0,0 +1,33 @@
+// C side of example/sysv-struct-return.rs. Small structs are returned in RAX, RDX, XMM0 and XMM1
+// depending on the class of each eightbyte, so getting a field back from the wrong register shows
+// up as a wrong value.
+
+#include <stdint.h>
+
+struct FloatInt { double a; int64_t b; };
+struct IntFloat { int64_t a; double b; };
+struct Floats { float a; float b; float c; };
+struct IntByte { int64_t a; int8_t b; };
+
+struct FloatInt float_int_from_c(double a, int64_t b) { return (struct FloatInt){ a, b }; }
+struct IntFloat int_float_from_c(int64_t a, double b) { return (struct IntFloat){ a, b }; }
+struct Floats floats_from_c(float a, float b, float c) { return (struct Floats){ a, b, c }; }
+struct IntByte int_byte_from_c(int64_t a, int8_t b) { return (struct IntByte){ a, b }; }
+
+extern struct FloatInt float_int_from_rust(void);
+extern struct IntFloat int_float_from_rust(void);
+extern struct Floats floats_from_rust(void);
+extern struct IntByte int_byte_from_rust(void);
+
+// Returns the number of the first struct returned by Rust that has the wrong value or 0.
+int check_rust_struct_returns(void) {
+    struct FloatInt float_int = float_int_from_rust();
+    if (float_int.a != 1.5 || float_int.b != -2) return 1;
+    struct IntFloat int_float = int_float_from_rust();
+    if (int_float.a != 3 || int_float.b != -4.5) return 2;
+    struct Floats floats = floats_from_rust();
+    if (floats.a != 5.5f || floats.b != 6.5f || floats.c != 7.5f) return 3;
+    struct IntByte int_byte = int_byte_from_rust();
+    if (int_byte.a != 8 || int_byte.b != -9) return 4;
+    return 0;
+}
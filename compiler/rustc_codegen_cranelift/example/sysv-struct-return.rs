@@ -0,0 +1,69 @@
+// Returns small structs with mixed integer and float fields to and from C functions compiled by
+// the system C compiler, see example/sysv-struct-return.c.
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+struct FloatInt {
+    a: f64,
+    b: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+struct IntFloat {
+    a: i64,
+    b: f64,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+struct Floats {
+    a: f32,
+    b: f32,
+    c: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+struct IntByte {
+    a: i64,
+    b: i8,
+}
+
+extern "C" {
+    fn float_int_from_c(a: f64, b: i64) -> FloatInt;
+    fn int_float_from_c(a: i64, b: f64) -> IntFloat;
+    fn floats_from_c(a: f32, b: f32, c: f32) -> Floats;
+    fn int_byte_from_c(a: i64, b: i8) -> IntByte;
+    fn check_rust_struct_returns() -> i32;
+}
+
+#[no_mangle]
+extern "C" fn float_int_from_rust() -> FloatInt {
+    FloatInt { a: 1.5, b: -2 }
+}
+
+#[no_mangle]
+extern "C" fn int_float_from_rust() -> IntFloat {
+    IntFloat { a: 3, b: -4.5 }
+}
+
+#[no_mangle]
+extern "C" fn floats_from_rust() -> Floats {
+    Floats { a: 5.5, b: 6.5, c: 7.5 }
+}
+
+#[no_mangle]
+extern "C" fn int_byte_from_rust() -> IntByte {
+    IntByte { a: 8, b: -9 }
+}
+
+fn main() {
+    unsafe {
+        assert_eq!(float_int_from_c(1.5, -2), FloatInt { a: 1.5, b: -2 });
+        assert_eq!(int_float_from_c(3, -4.5), IntFloat { a: 3, b: -4.5 });
+        assert_eq!(floats_from_c(5.5, 6.5, 7.5), Floats { a: 5.5, b: 6.5, c: 7.5 });
+        assert_eq!(int_byte_from_c(8, -9), IntByte { a: 8, b: -9 });
+        assert_eq!(check_rust_struct_returns(), 0);
+    }
+}
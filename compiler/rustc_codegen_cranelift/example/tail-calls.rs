@@ -0,0 +1,24 @@
+// Tail calls are lowered to a regular call followed by a return, as Cranelift only supports
+// return_call for the tail calling convention. This checks that they behave like a call. They
+// don't reuse the stack frame of the caller, so the recursion depth is kept moderate.
+
+#![feature(explicit_tail_calls)]
+#![allow(incomplete_features)]
+
+use std::hint::black_box;
+
+#[no_mangle]
+fn tail_call_is_even(n: u32) -> bool {
+    if n == 0 { true } else { become tail_call_is_odd(n - 1) }
+}
+
+#[no_mangle]
+fn tail_call_is_odd(n: u32) -> bool {
+    if n == 0 { false } else { become tail_call_is_even(n - 1) }
+}
+
+fn main() {
+    assert!(tail_call_is_even(black_box(1000)));
+    assert!(tail_call_is_odd(black_box(1001)));
+    assert!(!tail_call_is_even(black_box(7)));
+}
@@ -0,0 +1,22 @@
+// Compiled with `--emit asm` to check that `#[target_feature(enable = "avx2")]` makes Cranelift
+// use the VEX encoded instructions that come with the implied avx feature.
+
+#![feature(core_intrinsics, repr_simd)]
+#![crate_type = "lib"]
+
+use std::intrinsics::simd::simd_add;
+
+#[repr(simd)]
+#[derive(Copy, Clone)]
+pub struct I32x4(i32, i32, i32, i32);
+
+#[no_mangle]
+pub fn baseline_add(a: &I32x4, b: &I32x4, ret: &mut I32x4) {
+    *ret = unsafe { simd_add(*a, *b) };
+}
+
+#[no_mangle]
+#[target_feature(enable = "avx2")]
+pub unsafe fn avx2_add(a: &I32x4, b: &I32x4, ret: &mut I32x4) {
+    *ret = simd_add(*a, *b);
+}
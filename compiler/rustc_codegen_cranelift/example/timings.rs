@@ -0,0 +1,18 @@
+// Compiled with -Cllvm-args=timings=<n>. The test harness checks that the printed summary covers
+// every function that was compiled.
+
+#![crate_type = "lib"]
+
+pub fn timings_sum(values: &[u64]) -> u64 {
+    values.iter().copied().fold(0, u64::wrapping_add)
+}
+
+pub fn timings_max(values: &[u64]) -> Option<u64> {
+    values.iter().copied().max()
+}
+
+pub fn timings_scale(values: &mut [u64], factor: u64) {
+    for value in values {
+        *value = value.wrapping_mul(factor);
+    }
+}
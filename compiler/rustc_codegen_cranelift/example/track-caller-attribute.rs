@@ -37,4 +37,35 @@ fn main() {
     assert_eq!(contained.file(), file!());
     assert_eq!(contained.line(), 17);
     assert_eq!(contained.column(), 5);
+
+    // The location is propagated through nested `#[track_caller]` functions.
+    let (nested_tracked, line) = (nested_track_caller(), line!());
+    assert_eq!(nested_tracked.file(), file!());
+    assert_eq!(nested_tracked.line(), line);
+
+    // Calls through function pointers and trait objects go through a shim providing the location.
+    let tracked_fn_ptr: fn() -> &'static Location<'static> = crate::tracked;
+    assert_eq!(tracked_fn_ptr().file(), file!());
+    assert_eq!((&() as &dyn Tracked).tracked_method().file(), file!());
+
+    // Every call from the same call site gets the same location.
+    let locations = [0; 2].map(|_| crate::tracked());
+    assert_eq!(locations[0], locations[1]);
+}
+
+#[track_caller]
+fn nested_track_caller() -> &'static Location<'static> {
+    tracked()
+}
+
+trait Tracked {
+    #[track_caller]
+    fn tracked_method(&self) -> &'static Location<'static>;
+}
+
+impl Tracked for () {
+    #[track_caller]
+    fn tracked_method(&self) -> &'static Location<'static> {
+        Location::caller()
+    }
 }
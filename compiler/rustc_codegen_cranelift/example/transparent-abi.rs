@@ -0,0 +1,38 @@
+// `#[repr(transparent)]` wrappers around a fat pointer are passed in two registers like the fat
+// pointer itself. The test harness checks in the clif ir that the functions below keep the wrapper
+// in registers instead of spilling it to a stack slot.
+
+use std::marker::PhantomData;
+
+#[repr(transparent)]
+pub struct Bytes<'a>(&'a [u8]);
+
+#[repr(transparent)]
+pub struct Tagged<'a>(Bytes<'a>, PhantomData<u32>);
+
+#[no_mangle]
+#[inline(never)]
+pub fn transparent_len(bytes: Tagged<'_>) -> usize {
+    bytes.0.0.len()
+}
+
+#[no_mangle]
+#[inline(never)]
+pub fn transparent_rewrap(bytes: &[u8]) -> Tagged<'_> {
+    Tagged(Bytes(bytes), PhantomData)
+}
+
+extern "Rust" {
+    #[link_name = "transparent_len"]
+    fn extern_transparent_len(bytes: Tagged<'_>) -> usize;
+
+    #[link_name = "transparent_rewrap"]
+    fn extern_transparent_rewrap(bytes: &[u8]) -> Tagged<'_>;
+}
+
+fn main() {
+    let data = [1, 2, 3];
+    let tagged = unsafe { extern_transparent_rewrap(&data) };
+    assert_eq!(tagged.0.0.as_ptr(), data.as_ptr());
+    assert_eq!(unsafe { extern_transparent_len(tagged) }, 3);
+}
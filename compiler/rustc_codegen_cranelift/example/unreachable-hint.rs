@@ -0,0 +1,27 @@
+// Branches to `unreachable_unchecked` are removed, see the aot.unreachable_hint test.
+
+use std::hint::{black_box, unreachable_unchecked};
+
+#[inline(never)]
+fn zero() -> u32 {
+    10
+}
+
+#[inline(never)]
+fn one() -> u32 {
+    20
+}
+
+#[no_mangle]
+fn classify(x: u8) -> u32 {
+    match x {
+        0 => zero(),
+        1 => one(),
+        _ => unsafe { unreachable_unchecked() },
+    }
+}
+
+fn main() {
+    assert_eq!(classify(black_box(0)), 10);
+    assert_eq!(classify(black_box(1)), 20);
+}
@@ -0,0 +1,15 @@
+// Calls an intrinsic without a fallback body. Compiled once normally and with
+// `CG_CLIF_UNSUPPORTED_INTRINSICS=vtable_size` in both AOT and JIT mode to check the error for
+// intrinsics cg_clif doesn't implement.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+
+use std::fmt::Debug;
+
+fn main() {
+    let value: &dyn Debug = &0u32;
+    let (_data, vtable) =
+        unsafe { std::mem::transmute::<&dyn Debug, (*const (), *const ())>(value) };
+    assert_eq!(unsafe { std::intrinsics::vtable_size(vtable) }, 4);
+}
@@ -0,0 +1,21 @@
+// Nothing references this static. When rustc links an executable it references all `#[used]`
+// statics from an extra object file, so instead the test harness links the object file of this
+// crate itself using `--gc-sections`. Without `#[used(linker)]` being respected, the linker would
+// garbage collect the static. The test harness checks that its contents end up in the executable.
+
+#![feature(used_with_arg)]
+#![no_std]
+#![no_main]
+
+#[used(linker)]
+static USED_LINKER_STATIC: [u8; 16] = *b"\x7fused-by-linker\x7f";
+
+#[no_mangle]
+extern "C" fn used_static_entry() -> ! {
+    loop {}
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo<'_>) -> ! {
+    loop {}
+}
@@ -0,0 +1,10 @@
+// Compiled with debuginfo to check that arguments and variables which are kept in registers are
+// given a location.
+
+#![crate_type = "lib"]
+
+#[no_mangle]
+pub fn variable_location_probe(x: u64) -> u64 {
+    let doubled = x.wrapping_mul(2);
+    doubled.wrapping_add(x)
+}
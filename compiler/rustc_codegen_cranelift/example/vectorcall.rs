@@ -0,0 +1,57 @@
+// Calls `extern "vectorcall"` functions written in assembly and the other way around to check that
+// the arguments end up in the registers the vectorcall calling convention assigns to them.
+
+#![feature(abi_vectorcall)]
+
+std::arch::global_asm! {
+    ".text",
+    // a in rcx, b in xmm1, c in r8, d in xmm3. Returns a * 10 + c.
+    ".global vectorcall_ints",
+    "vectorcall_ints:",
+    "imul rax, rcx, 10",
+    "add rax, r8",
+    "ret",
+    // a in rcx, b in xmm1, c in r8, d in xmm3. Returns b - d.
+    ".global vectorcall_floats",
+    "vectorcall_floats:",
+    "subsd xmm1, xmm3",
+    "movapd xmm0, xmm1",
+    "ret",
+    // Calls the vectorcall function in rcx with a = 3, b = 2.0, c = 5, d = 4.0.
+    ".global call_vectorcall_fn",
+    "call_vectorcall_fn:",
+    "sub rsp, 40",
+    "mov rax, rcx",
+    "mov rcx, 3",
+    "mov rdx, 0x4000000000000000",
+    "movq xmm1, rdx",
+    "mov r8, 5",
+    "mov rdx, 0x4010000000000000",
+    "movq xmm3, rdx",
+    "call rax",
+    "add rsp, 40",
+    "ret",
+}
+
+type VectorcallFn = extern "vectorcall" fn(i64, f64, i64, f64) -> f64;
+
+extern "vectorcall" {
+    fn vectorcall_ints(a: i64, b: f64, c: i64, d: f64) -> i64;
+    fn vectorcall_floats(a: i64, b: f64, c: i64, d: f64) -> f64;
+}
+
+extern "C" {
+    fn call_vectorcall_fn(f: VectorcallFn) -> f64;
+}
+
+extern "vectorcall" fn rust_vectorcall(a: i64, b: f64, c: i64, d: f64) -> f64 {
+    a as f64 * 1000.0 + b * 100.0 + c as f64 * 10.0 + d
+}
+
+fn main() {
+    unsafe {
+        assert_eq!(vectorcall_ints(1, 2.0, 3, 4.0), 13);
+        assert_eq!(vectorcall_floats(1, 2.5, 3, 4.0), -1.5);
+        assert_eq!(call_vectorcall_fn(rust_vectorcall), 3254.0);
+    }
+}
@@ -0,0 +1,19 @@
+// Compiled with optimizations and --emit llvm-ir. The test harness checks in the optimized clif
+// ir that none of the volatile accesses below got merged or removed.
+
+#![feature(core_intrinsics)]
+#![allow(internal_features)]
+#![crate_type = "lib"]
+
+use std::intrinsics::{volatile_load, volatile_store};
+
+#[no_mangle]
+pub unsafe fn volatile_store_twice(ptr: *mut u32) {
+    volatile_store(ptr, 1);
+    volatile_store(ptr, 2);
+}
+
+#[no_mangle]
+pub unsafe fn volatile_load_twice(ptr: *const u32) -> u32 {
+    volatile_load(ptr) + volatile_load(ptr)
+}
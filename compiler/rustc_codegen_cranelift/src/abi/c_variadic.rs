@@ -0,0 +1,210 @@
+//! Support for defining C-variadic functions (`unsafe extern "C" fn f(x: i32, mut args: ...)`)
+//!
+//! Cranelift has no notion of variadic parameters. Instead the signature of a variadic function
+//! gets an extra parameter for every argument register not used by the fixed parameters. These are
+//! spilled to a register save area in the prologue, which together with the location of the
+//! arguments passed on the stack initializes the `VaListImpl` local. `va_arg` then walks the
+//! register save area and the stack arguments the same way the C ABI of the target does.
+//!
+//! Only the x86_64 System V ABI is currently supported.
+
+use cranelift_codegen::ir::ArgumentPurpose;
+use rustc_target::abi::call::FnAbi;
+use rustc_target::abi::Primitive;
+
+use super::pass_mode::ArgAbiExt;
+use crate::prelude::*;
+
+const GP_REGS: u32 = 6;
+const FP_REGS: u32 = 8;
+/// Offset of the first SSE register in the register save area.
+const FP_SAVE_AREA_OFFSET: u32 = GP_REGS * 8;
+const REG_SAVE_AREA_SIZE: u32 = FP_SAVE_AREA_OFFSET + FP_REGS * 16;
+
+// Field offsets of the x86_64 `VaListImpl`, which is `#[repr(C)]`.
+const GP_OFFSET: i64 = 0;
+const FP_OFFSET: i64 = 4;
+const OVERFLOW_ARG_AREA: i64 = 8;
+const REG_SAVE_AREA: i64 = 16;
+
+/// How the fixed parameters of a variadic function are passed.
+struct FixedArgs {
+    gp_regs: u32,
+    fp_regs: u32,
+    stack_bytes: u32,
+}
+
+fn supports_c_variadic_definitions(tcx: TyCtxt<'_>) -> bool {
+    tcx.sess.target.arch == "x86_64" && !tcx.sess.target.is_like_windows
+}
+
+/// Returns `None` if defining variadic functions isn't supported for this target or the fixed
+/// parameters of `fn_abi` contain a value not passed in a single register.
+fn fixed_args<'tcx>(tcx: TyCtxt<'tcx>, fn_abi: &FnAbi<'tcx, Ty<'tcx>>) -> Option<FixedArgs> {
+    if !fn_abi.c_variadic || !supports_c_variadic_definitions(tcx) {
+        return None;
+    }
+
+    let (return_ptr, _returns) = fn_abi.ret.get_abi_return(tcx);
+    let fixed_params = fn_abi.args[..fn_abi.fixed_count as usize]
+        .iter()
+        .flat_map(|arg_abi| arg_abi.get_abi_param(tcx).into_iter());
+
+    let mut fixed_args = FixedArgs { gp_regs: 0, fp_regs: 0, stack_bytes: 0 };
+    for param in return_ptr.into_iter().chain(fixed_params) {
+        if param.purpose != ArgumentPurpose::Normal
+            && param.purpose != ArgumentPurpose::StructReturn
+        {
+            return None;
+        }
+        if param.value_type.is_int() && param.value_type.bits() <= 64 {
+            if fixed_args.gp_regs < GP_REGS {
+                fixed_args.gp_regs += 1;
+            } else {
+                fixed_args.stack_bytes += 8;
+            }
+        } else if param.value_type.is_float() && param.value_type.bits() <= 64 {
+            if fixed_args.fp_regs < FP_REGS {
+                fixed_args.fp_regs += 1;
+            } else {
+                fixed_args.stack_bytes += 8;
+            }
+        } else {
+            return None;
+        }
+    }
+    Some(fixed_args)
+}
+
+/// The extra parameters of a variadic function capturing the argument registers that may contain
+/// variadic arguments. Empty for functions that aren't variadic.
+pub(super) fn register_save_params<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    fn_abi: &FnAbi<'tcx, Ty<'tcx>>,
+) -> Vec<AbiParam> {
+    let Some(fixed_args) = fixed_args(tcx, fn_abi) else {
+        return vec![];
+    };
+    (fixed_args.gp_regs..GP_REGS)
+        .map(|_| AbiParam::new(types::I64))
+        .chain((fixed_args.fp_regs..FP_REGS).map(|_| AbiParam::new(types::F64)))
+        .collect()
+}
+
+/// Error out if the variadic function currently being defined isn't supported.
+pub(super) fn check_c_variadic_definition(fx: &FunctionCx<'_, '_, '_>) {
+    if !supports_c_variadic_definitions(fx.tcx) {
+        fx.tcx.dcx().span_fatal(
+            fx.mir.span,
+            "Defining variadic functions is only supported by Cranelift on x86_64 System V targets",
+        );
+    }
+    if fixed_args(fx.tcx, fx.fn_abi).is_none() {
+        fx.tcx.dcx().span_fatal(
+            fx.mir.span,
+            "Defining variadic functions with fixed parameters that aren't passed in a single \
+             register is not yet supported by Cranelift",
+        );
+    }
+}
+
+/// Initialize the `VaListImpl` of the variadic function currently being defined from the values of
+/// the parameters returned by [`register_save_params`].
+pub(super) fn codegen_va_start<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    va_list_layout: TyAndLayout<'tcx>,
+    register_params: &[Value],
+) -> CValue<'tcx> {
+    let fixed_args = fixed_args(fx.tcx, fx.fn_abi).unwrap();
+
+    let reg_save_area = fx.create_stack_slot(REG_SAVE_AREA_SIZE, 16);
+    for (i, &param) in register_params.iter().enumerate() {
+        let i = u32::try_from(i).unwrap();
+        let unused_gp_regs = GP_REGS - fixed_args.gp_regs;
+        let offset = if i < unused_gp_regs {
+            (fixed_args.gp_regs + i) * 8
+        } else {
+            FP_SAVE_AREA_OFFSET + (fixed_args.fp_regs + i - unused_gp_regs) * 16
+        };
+        reg_save_area.offset_i64(fx, i64::from(offset)).store(fx, param, MemFlags::trusted());
+    }
+
+    // The stack arguments are right above the return address and the frame pointer saved by the
+    // prologue.
+    // FIXME use a dedicated Cranelift instruction once one exists for accessing stack arguments
+    let frame_pointer = fx.bcx.ins().get_frame_pointer(fx.pointer_type);
+    let overflow_arg_area =
+        fx.bcx.ins().iadd_imm(frame_pointer, 16 + i64::from(fixed_args.stack_bytes));
+
+    let gp_offset = fx.bcx.ins().iconst(types::I32, i64::from(fixed_args.gp_regs * 8));
+    let fp_offset =
+        fx.bcx.ins().iconst(types::I32, i64::from(FP_SAVE_AREA_OFFSET + fixed_args.fp_regs * 16));
+    let reg_save_area = reg_save_area.get_addr(fx);
+
+    assert_eq!(va_list_layout.size.bytes(), 24);
+    let va_list = CPlace::new_stack_slot(fx, va_list_layout);
+    let ptr = va_list.to_ptr();
+    ptr.offset_i64(fx, GP_OFFSET).store(fx, gp_offset, MemFlags::trusted());
+    ptr.offset_i64(fx, FP_OFFSET).store(fx, fp_offset, MemFlags::trusted());
+    ptr.offset_i64(fx, OVERFLOW_ARG_AREA).store(fx, overflow_arg_area, MemFlags::trusted());
+    ptr.offset_i64(fx, REG_SAVE_AREA).store(fx, reg_save_area, MemFlags::trusted());
+    va_list.to_cvalue(fx)
+}
+
+/// Read the next variadic argument of the type of `ret` from the `VaListImpl` `va_list` points to.
+pub(crate) fn codegen_va_arg<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    va_list: Value,
+    ret: CPlace<'tcx>,
+    span: Span,
+) {
+    if !supports_c_variadic_definitions(fx.tcx) {
+        fx.tcx
+            .dcx()
+            .span_fatal(span, "va_arg is only supported by Cranelift on x86_64 System V targets");
+    }
+
+    // All `VaArgSafe` types are integers, pointers or `f64` and take a single eightbyte.
+    let layout = ret.layout();
+    assert!(layout.size.bytes() <= 8, "{:?}", layout.ty);
+    let is_float = match layout.abi {
+        Abi::Scalar(scalar) => matches!(scalar.primitive(), Primitive::Float(_)),
+        _ => unreachable!("va_arg for non-scalar {:?}", layout.ty),
+    };
+    let (offset_field, save_area_end, reg_size) = if is_float {
+        (FP_OFFSET, REG_SAVE_AREA_SIZE, 16)
+    } else {
+        (GP_OFFSET, FP_SAVE_AREA_OFFSET, 8)
+    };
+
+    let va_list = Pointer::new(va_list);
+    let offset_ptr = va_list.offset_i64(fx, offset_field);
+    let overflow_arg_area_ptr = va_list.offset_i64(fx, OVERFLOW_ARG_AREA);
+    let reg_save_area_ptr = va_list.offset_i64(fx, REG_SAVE_AREA);
+
+    let reg_block = fx.bcx.create_block();
+    let stack_block = fx.bcx.create_block();
+    let done_block = fx.bcx.create_block();
+    let arg_addr = fx.bcx.append_block_param(done_block, fx.pointer_type);
+
+    let offset = offset_ptr.load(fx, types::I32, MemFlags::trusted());
+    let in_regs = fx.bcx.ins().icmp_imm(IntCC::UnsignedLessThan, offset, i64::from(save_area_end));
+    fx.bcx.ins().brif(in_regs, reg_block, &[], stack_block, &[]);
+
+    fx.bcx.switch_to_block(reg_block);
+    let reg_save_area = reg_save_area_ptr.load(fx, fx.pointer_type, MemFlags::trusted());
+    let extended_offset = fx.bcx.ins().uextend(fx.pointer_type, offset);
+    let reg_addr = fx.bcx.ins().iadd(reg_save_area, extended_offset);
+    let next_offset = fx.bcx.ins().iadd_imm(offset, reg_size);
+    offset_ptr.store(fx, next_offset, MemFlags::trusted());
+    fx.bcx.ins().jump(done_block, &[reg_addr]);
+
+    fx.bcx.switch_to_block(stack_block);
+    let stack_addr = overflow_arg_area_ptr.load(fx, fx.pointer_type, MemFlags::trusted());
+    let next_stack_addr = fx.bcx.ins().iadd_imm(stack_addr, 8);
+    overflow_arg_area_ptr.store(fx, next_stack_addr, MemFlags::trusted());
+    fx.bcx.ins().jump(done_block, &[stack_addr]);
+
+    fx.bcx.switch_to_block(done_block);
+    ret.write_cvalue(fx, CValue::by_ref(Pointer::new(arg_addr), layout));
+}
@@ -1,8 +1,10 @@
 //! Handling of everything related to the calling convention. Also fills `fx.local_map`.
 
+mod c_variadic;
 mod comments;
 mod pass_mode;
 mod returning;
+mod stack_protector;
 
 use std::borrow::Cow;
 use std::mem;
@@ -18,11 +20,13 @@
 use rustc_middle::ty::TypeVisitableExt;
 use rustc_session::Session;
 use rustc_span::source_map::Spanned;
-use rustc_target::abi::call::{Conv, FnAbi, PassMode};
+use rustc_target::abi::call::{Conv, FnAbi, HomogeneousAggregate, PassMode, Reg, RegKind};
 use rustc_target::spec::abi::Abi;
 
+pub(crate) use self::c_variadic::codegen_va_arg;
 use self::pass_mode::*;
 pub(crate) use self::returning::codegen_return;
+pub(crate) use self::stack_protector::move_canary_to_last_stack_slot;
 use crate::prelude::*;
 
 fn clif_sig_from_fn_abi<'tcx>(
@@ -32,13 +36,121 @@ fn clif_sig_from_fn_abi<'tcx>(
 ) -> Signature {
     let call_conv = conv_to_call_conv(tcx.sess, fn_abi.conv, default_call_conv);
 
+    let (mut params, returns) = unpadded_params_and_returns(tcx, fn_abi);
+
+    if fn_abi.conv == Conv::X86VectorCall && call_conv == CallConv::WindowsFastcall {
+        check_vectorcall_params(tcx, fn_abi, &params);
+    }
+
+    if let Some(arrangement) = i128_param_arrangement(tcx, call_conv, &params) {
+        params = arrangement
+            .iter()
+            .map(|&param| param.map_or(AbiParam::new(types::I64), |index| params[index]))
+            .collect();
+    }
+
+    params.extend(c_variadic::register_save_params(tcx, fn_abi));
+
+    Signature { params, returns, call_conv }
+}
+
+fn unpadded_params_and_returns<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    fn_abi: &FnAbi<'tcx, Ty<'tcx>>,
+) -> (Vec<AbiParam>, Vec<AbiParam>) {
     let inputs = fn_abi.args.iter().flat_map(|arg_abi| arg_abi.get_abi_param(tcx).into_iter());
 
     let (return_ptr, returns) = fn_abi.ret.get_abi_return(tcx);
     // Sometimes the first param is a pointer to the place where the return value needs to be stored.
-    let params: Vec<_> = return_ptr.into_iter().chain(inputs).collect();
+    let params = return_ptr.into_iter().chain(inputs).collect();
 
-    Signature { params, returns, call_conv }
+    (params, returns)
+}
+
+/// Returns how `params` have to be reordered and padded in the Cranelift signature for them to be
+/// passed the way the C ABI of the target expects, or `None` if they can be used as is.
+///
+/// Every entry of the result is either the index of a param in `params` or `None` for a dummy
+/// `i64` param.
+///
+/// The x86_64 System V ABI passes a 128-bit integer either in two integer registers or 16 byte
+/// aligned on the stack, but never split between the last register and the stack. Integer params
+/// after it still use any registers that are left. Cranelift assigns the halves of an `i128` like
+/// two independent `i64` params in the order of the params, so to match what Clang does all
+/// params passed in registers are moved before the params passed on the stack, unused registers
+/// are filled and padding is inserted in front of misaligned `i128` stack params.
+fn i128_param_arrangement(
+    tcx: TyCtxt<'_>,
+    call_conv: CallConv,
+    params: &[AbiParam],
+) -> Option<Vec<Option<usize>>> {
+    if tcx.sess.target.arch != "x86_64"
+        || call_conv != CallConv::SystemV
+        || !params.iter().any(|param| param.value_type == types::I128)
+    {
+        return None;
+    }
+
+    const INT_REGS: usize = 6;
+    const FLOAT_REGS: usize = 8;
+
+    let mut in_regs = vec![];
+    let mut on_stack = vec![];
+    let mut int_regs_used = 0;
+    let mut float_regs_used = 0;
+    let mut stack_offset = 0;
+    let mut i128_on_stack = false;
+    for (index, param) in params.iter().enumerate() {
+        if let ArgumentPurpose::StructArgument(size) = param.purpose {
+            on_stack.push(Some(index));
+            stack_offset += u64::from(size).next_multiple_of(8);
+        } else if param.value_type == types::I128 {
+            if int_regs_used + 2 <= INT_REGS {
+                in_regs.push(Some(index));
+                int_regs_used += 2;
+            } else {
+                if stack_offset % 16 != 0 {
+                    on_stack.push(None);
+                    stack_offset += 8;
+                }
+                on_stack.push(Some(index));
+                stack_offset += 16;
+                i128_on_stack = true;
+            }
+        } else if param.value_type.is_int() {
+            if int_regs_used < INT_REGS {
+                in_regs.push(Some(index));
+                int_regs_used += 1;
+            } else {
+                on_stack.push(Some(index));
+                stack_offset += 8;
+            }
+        } else if float_regs_used < FLOAT_REGS {
+            in_regs.push(Some(index));
+            float_regs_used += 1;
+        } else {
+            on_stack.push(Some(index));
+            stack_offset += u64::from(param.value_type.bytes()).next_multiple_of(8);
+        }
+    }
+
+    if i128_on_stack {
+        // Keep Cranelift from putting the low half of an `i128` in the last integer register.
+        in_regs.extend((int_regs_used..INT_REGS).map(|_| None));
+    }
+    in_regs.extend(on_stack);
+
+    if in_regs.iter().copied().eq((0..params.len()).map(Some)) { None } else { Some(in_regs) }
+}
+
+/// Like [`i128_param_arrangement`], but for the signature [`clif_sig_from_fn_abi`] returns.
+fn fn_abi_param_arrangement<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    call_conv: CallConv,
+    fn_abi: &FnAbi<'tcx, Ty<'tcx>>,
+) -> Option<Vec<Option<usize>>> {
+    let (params, _returns) = unpadded_params_and_returns(tcx, fn_abi);
+    i128_param_arrangement(tcx, call_conv, &params)
 }
 
 pub(crate) fn conv_to_call_conv(sess: &Session, c: Conv, default_call_conv: CallConv) -> CallConv {
@@ -48,7 +160,17 @@ pub(crate) fn conv_to_call_conv(sess: &Session, c: Conv, default_call_conv: Call
         Conv::X86_64SysV => CallConv::SystemV,
         Conv::X86_64Win64 => CallConv::WindowsFastcall,
 
+        // `extern "fastcall"` is only kept on 32bit x86. Everywhere else it has already been turned
+        // into `extern "C"`. On x86_64 `extern "vectorcall"` passes the first four arguments like
+        // the Windows x64 calling convention. See `check_vectorcall_params` for the differences.
+        Conv::X86VectorCall if sess.target.arch == "x86_64" && sess.target.is_like_windows => {
+            CallConv::WindowsFastcall
+        }
+
         // Should already get a back compat warning
+        // FIXME implement `stdcall` (and `extern "system"`, which is `stdcall` on 32bit Windows)
+        // including callee stack cleanup and the `@N` suffix of decorated symbol names once
+        // Cranelift supports 32bit x86. rustc only keeps these calling conventions for 32bit x86.
         Conv::X86Fastcall | Conv::X86Stdcall | Conv::X86ThisCall | Conv::X86VectorCall => {
             default_call_conv
         }
@@ -68,6 +190,45 @@ pub(crate) fn conv_to_call_conv(sess: &Session, c: Conv, default_call_conv: Call
     }
 }
 
+/// Unlike the Windows x64 calling convention, `vectorcall` passes floats in the fifth and sixth
+/// argument position in XMM4 and XMM5 and passes vectors and homogeneous float or vector aggregates
+/// in XMM registers. Cranelift's `WindowsFastcall` doesn't know about either.
+fn check_vectorcall_params<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    fn_abi: &FnAbi<'tcx, Ty<'tcx>>,
+    params: &[AbiParam],
+) {
+    for arg_abi in fn_abi.args.iter().chain(std::iter::once(&fn_abi.ret)) {
+        let is_vector_like = match arg_abi.layout.abi {
+            rustc_target::abi::Abi::Vector { .. } => true,
+            rustc_target::abi::Abi::ScalarPair(..) | rustc_target::abi::Abi::Aggregate { .. } => {
+                matches!(
+                    arg_abi.layout.homogeneous_aggregate(&RevealAllLayoutCx(tcx)),
+                    Ok(HomogeneousAggregate::Homogeneous(Reg {
+                        kind: RegKind::Float | RegKind::Vector,
+                        ..
+                    }))
+                )
+            }
+            _ => false,
+        };
+        if is_vector_like && !arg_abi.is_ignore() {
+            tcx.dcx().fatal(format!(
+                "passing `{}` using the vectorcall calling convention is not yet supported by \
+                 Cranelift",
+                arg_abi.layout.ty,
+            ));
+        }
+    }
+
+    if params.iter().skip(4).take(2).any(|param| param.value_type.is_float()) {
+        tcx.dcx().fatal(
+            "passing floats in XMM4 or XMM5 using the vectorcall calling convention is not yet \
+             supported by Cranelift",
+        );
+    }
+}
+
 pub(crate) fn get_function_sig<'tcx>(
     tcx: TyCtxt<'tcx>,
     default_call_conv: CallConv,
@@ -156,17 +317,29 @@ pub(crate) fn lib_call(
     pub(crate) fn lib_call_unadjusted(
         &mut self,
         name: &str,
-        params: Vec<AbiParam>,
+        mut params: Vec<AbiParam>,
         returns: Vec<AbiParam>,
         args: &[Value],
     ) -> Cow<'_, [Value]> {
-        let sig = Signature { params, returns, call_conv: self.target_config.default_call_conv };
+        let call_conv = self.target_config.default_call_conv;
+        let mut args = args.to_vec();
+        if let Some(arrangement) = i128_param_arrangement(self.tcx, call_conv, &params) {
+            (params, args) = arrangement
+                .iter()
+                .map(|&param| match param {
+                    Some(index) => (params[index], args[index]),
+                    None => (AbiParam::new(types::I64), self.bcx.ins().iconst(types::I64, 0)),
+                })
+                .unzip();
+        }
+
+        let sig = Signature { params, returns, call_conv };
         let func_id = self.module.declare_function(name, Linkage::Import, &sig).unwrap();
         let func_ref = self.module.declare_func_in_func(func_id, &mut self.bcx.func);
         if self.clif_comments.enabled() {
             self.add_comment(func_ref, format!("{:?}", name));
         }
-        let call_inst = self.bcx.ins().call(func_ref, args);
+        let call_inst = self.bcx.ins().call(func_ref, &args);
         if self.clif_comments.enabled() {
             self.add_comment(call_inst, format!("lib_call {}", name));
         }
@@ -211,10 +384,29 @@ pub(crate) fn codegen_fn_prelude<'tcx>(fx: &mut FunctionCx<'_, '_, 'tcx>, start_
     fx.bcx.ins().nop();
 
     let ssa_analyzed = crate::analyze::analyze(fx);
+    fx.readonly_ref_args = crate::analyze::readonly_ref_args(fx);
 
     self::comments::add_args_header_comment(fx);
 
-    let mut block_params_iter = fx.bcx.func.dfg.block_params(start_block).to_vec().into_iter();
+    if fx.fn_abi.c_variadic {
+        c_variadic::check_c_variadic_definition(fx);
+    }
+
+    let mut block_params = fx.bcx.func.dfg.block_params(start_block).to_vec();
+    let register_save_param_count = c_variadic::register_save_params(fx.tcx, fx.fn_abi).len();
+    let register_save_params =
+        block_params.split_off(block_params.len() - register_save_param_count);
+    let call_conv = fx.bcx.func.signature.call_conv;
+    if let Some(arrangement) = fn_abi_param_arrangement(fx.tcx, call_conv, fx.fn_abi) {
+        let mut unpadded = vec![None; block_params.len()];
+        for (&param, block_param) in arrangement.iter().zip(block_params) {
+            if let Some(index) = param {
+                unpadded[index] = Some(block_param);
+            }
+        }
+        block_params = unpadded.into_iter().flatten().collect();
+    }
+    let mut block_params_iter = block_params.into_iter();
     let ret_place =
         self::returning::codegen_return_param(fx, &ssa_analyzed, &mut block_params_iter);
     assert_eq!(fx.local_map.push(ret_place), RETURN_PLACE);
@@ -225,14 +417,6 @@ enum ArgKind<'tcx> {
         Spread(Vec<Option<CValue<'tcx>>>),
     }
 
-    // FIXME implement variadics in cranelift
-    if fx.fn_abi.c_variadic {
-        fx.tcx.dcx().span_fatal(
-            fx.mir.span,
-            "Defining variadic functions is not yet supported by Cranelift",
-        );
-    }
-
     let mut arg_abis_iter = fx.fn_abi.args.iter();
 
     let func_params = fx
@@ -262,6 +446,11 @@ enum ArgKind<'tcx> {
                 }
 
                 (local, ArgKind::Spread(params), arg_ty)
+            } else if fx.fn_abi.c_variadic && local.as_usize() == fx.mir.arg_count {
+                // The `VaListImpl` for the variadic arguments doesn't have an `ArgAbi`.
+                let va_list =
+                    c_variadic::codegen_va_start(fx, fx.layout_of(arg_ty), &register_save_params);
+                (local, ArgKind::Normal(Some(va_list)), arg_ty)
             } else {
                 let arg_abi = arg_abis_iter.next().unwrap();
                 let param =
@@ -337,6 +526,8 @@ enum ArgKind<'tcx> {
         assert_eq!(fx.local_map.push(place), local);
     }
 
+    self::stack_protector::codegen_stack_protector_prologue(fx);
+
     fx.bcx.ins().jump(*fx.block_map.get(START_BLOCK).unwrap(), &[]);
 }
 
@@ -356,6 +547,28 @@ fn codegen_call_argument_operand<'tcx>(
     }
 }
 
+/// Whether a function with the given signature and, if known, definition is `#[cold]` or uses the
+/// `rust-cold` ABI.
+pub(crate) fn is_cold_fn<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    fn_sig: ty::PolyFnSig<'tcx>,
+    def_id: Option<DefId>,
+) -> bool {
+    fn_sig.abi() == Abi::RustCold
+        || def_id.is_some_and(|def_id| {
+            tcx.codegen_fn_attrs(def_id).flags.contains(CodegenFnAttrFlags::COLD)
+        })
+}
+
+/// Where execution continues once a call returns.
+#[derive(Copy, Clone)]
+enum CallContinuation {
+    /// Jump to the given block, or trap if the call can't return.
+    Block(Option<BasicBlock>),
+    /// Return from the current function. Used for tail calls.
+    Return,
+}
+
 pub(crate) fn codegen_terminator_call<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     source_info: mir::SourceInfo,
@@ -364,6 +577,35 @@ pub(crate) fn codegen_terminator_call<'tcx>(
     destination: Place<'tcx>,
     target: Option<BasicBlock>,
 ) {
+    codegen_call(fx, source_info, func, args, destination, CallContinuation::Block(target));
+}
+
+pub(crate) fn codegen_tail_call<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    source_info: mir::SourceInfo,
+    func: &Operand<'tcx>,
+    args: &[Spanned<Operand<'tcx>>],
+) {
+    // FIXME(explicit_tail_calls) use return_call and return_call_indirect. Cranelift only supports
+    // them when both the caller and the callee use the `tail` calling convention, which Rust
+    // functions don't use. Until then a tail call is lowered to a regular call followed by a
+    // return, which doesn't guarantee constant stack usage.
+    codegen_call(fx, source_info, func, args, RETURN_PLACE.into(), CallContinuation::Return);
+}
+
+fn codegen_call<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    source_info: mir::SourceInfo,
+    func: &Operand<'tcx>,
+    args: &[Spanned<Operand<'tcx>>],
+    destination: Place<'tcx>,
+    continuation: CallContinuation,
+) {
+    let target = match continuation {
+        CallContinuation::Block(target) => target,
+        CallContinuation::Return => None,
+    };
+
     let func = codegen_operand(fx, func);
     let fn_sig = func.layout().ty.fn_sig(fx.tcx);
 
@@ -381,7 +623,7 @@ pub(crate) fn codegen_terminator_call<'tcx>(
         .polymorphize(fx.tcx);
 
         if is_call_from_compiler_builtins_to_upstream_monomorphization(fx.tcx, instance) {
-            if target.is_some() {
+            if !matches!(continuation, CallContinuation::Block(None)) {
                 let caller = with_no_trimmed_paths!(fx.tcx.def_path_str(fx.instance.def_id()));
                 let callee = with_no_trimmed_paths!(fx.tcx.def_path_str(def_id));
                 fx.tcx.dcx().emit_err(CompilerBuiltinsCannotCall { caller, callee });
@@ -391,6 +633,16 @@ pub(crate) fn codegen_terminator_call<'tcx>(
             }
         }
 
+        if let CallContinuation::Return = continuation {
+            if matches!(instance.def, InstanceKind::Intrinsic(_))
+                || fx.tcx.symbol_name(instance).name.starts_with("llvm.")
+            {
+                fx.tcx
+                    .dcx()
+                    .span_fatal(source_info.span, "tail calling intrinsics is not supported");
+            }
+        }
+
         if fx.tcx.symbol_name(instance).name.starts_with("llvm.") {
             crate::intrinsics::codegen_llvm_intrinsic_call(
                 fx,
@@ -419,6 +671,10 @@ pub(crate) fn codegen_terminator_call<'tcx>(
             }
             InstanceKind::DropGlue(_, None) | ty::InstanceKind::AsyncDropGlueCtorShim(_, None) => {
                 // empty drop glue - a nop.
+                if let CallContinuation::Return = continuation {
+                    codegen_return(fx);
+                    return;
+                }
                 let dest = target.expect("Non terminating drop_in_place_real???");
                 let ret_block = fx.get_block(dest);
                 fx.bcx.ins().jump(ret_block, &[]);
@@ -440,14 +696,7 @@ pub(crate) fn codegen_terminator_call<'tcx>(
         RevealAllLayoutCx(fx.tcx).fn_abi_of_fn_ptr(fn_sig, extra_args)
     };
 
-    let is_cold = if fn_sig.abi() == Abi::RustCold {
-        true
-    } else {
-        instance.is_some_and(|inst| {
-            fx.tcx.codegen_fn_attrs(inst.def_id()).flags.contains(CodegenFnAttrFlags::COLD)
-        })
-    };
-    if is_cold {
+    if is_cold_fn(fx.tcx, fn_sig, instance.map(|inst| inst.def_id())) {
         fx.bcx.set_cold_block(fx.bcx.current_block().unwrap());
         if let Some(destination_block) = target {
             fx.bcx.set_cold_block(fx.get_block(destination_block));
@@ -554,6 +803,18 @@ enum CallTarget {
         // FIXME: Find a cleaner way to support varargs.
         if fn_abi.c_variadic {
             adjust_call_for_c_variadic(fx, &fn_abi, source_info, func_ref, &mut call_args);
+        } else {
+            let call_conv =
+                conv_to_call_conv(fx.tcx.sess, fn_abi.conv, fx.target_config.default_call_conv);
+            if let Some(arrangement) = fn_abi_param_arrangement(fx.tcx, call_conv, &fn_abi) {
+                call_args = arrangement
+                    .iter()
+                    .map(|&param| match param {
+                        Some(index) => call_args[index],
+                        None => fx.bcx.ins().iconst(types::I64, 0),
+                    })
+                    .collect();
+            }
         }
 
         match func_ref {
@@ -564,11 +825,15 @@ enum CallTarget {
         }
     });
 
-    if let Some(dest) = target {
-        let ret_block = fx.get_block(dest);
-        fx.bcx.ins().jump(ret_block, &[]);
-    } else {
-        fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
+    match continuation {
+        CallContinuation::Block(Some(dest)) => {
+            let ret_block = fx.get_block(dest);
+            fx.bcx.ins().jump(ret_block, &[]);
+        }
+        CallContinuation::Block(None) => {
+            fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
+        }
+        CallContinuation::Return => codegen_return(fx),
     }
 
     fn adjust_call_for_c_variadic<'tcx>(
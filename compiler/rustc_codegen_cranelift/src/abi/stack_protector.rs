@@ -0,0 +1,151 @@
+//! Stack protector (`-Zstack-protector`) support
+//!
+//! A canary read from the stack guard is stored in the stack slot closest to the return address in
+//! the function prologue and checked against the stack guard again before returning.
+//! `__stack_chk_fail` is called when a buffer overflow overwrote the canary.
+
+use cranelift_codegen::ir::InstructionData;
+use rustc_ast::ast::{InlineAsmOptions, InlineAsmTemplatePiece};
+use rustc_target::abi::FieldsShape;
+use rustc_target::asm::*;
+use rustc_target::spec::StackProtector;
+
+use crate::inline_asm::{codegen_inline_asm_inner, CInlineAsmOperand};
+use crate::prelude::*;
+
+/// Store the stack canary if the current function needs a stack protector.
+///
+/// The canary is moved to the last stack slot by [`move_canary_to_last_stack_slot`] once the
+/// whole function has been codegened.
+pub(super) fn codegen_stack_protector_prologue(fx: &mut FunctionCx<'_, '_, '_>) {
+    let needs_stack_protector = match fx.tcx.sess.stack_protector() {
+        StackProtector::None => false,
+        StackProtector::All => true,
+        StackProtector::Strong => any_local_contains_array(fx, &|_array, _elem| true),
+        // Only character arrays of at least 8 bytes, like LLVM's `ssp` attribute.
+        StackProtector::Basic => any_local_contains_array(fx, &|array, elem| {
+            elem.size.bytes() == 1 && array.size.bytes() >= 8
+        }),
+    };
+    if !needs_stack_protector {
+        return;
+    }
+
+    let guard = load_stack_guard(fx);
+    let ptr_size = fx.tcx.data_layout.pointer_size.bytes() as u32;
+    let canary = fx.bcx.create_sized_stack_slot(StackSlotData {
+        kind: StackSlotKind::ExplicitSlot,
+        size: ptr_size,
+        align_shift: ptr_size.ilog2() as u8,
+    });
+    fx.bcx.ins().stack_store(guard, canary, 0);
+    fx.stack_protector_canary = Some(canary);
+}
+
+/// Check that the stack canary is still intact right before returning.
+pub(super) fn codegen_stack_protector_check(fx: &mut FunctionCx<'_, '_, '_>) {
+    let Some(canary) = fx.stack_protector_canary else {
+        return;
+    };
+
+    let guard = load_stack_guard(fx);
+    let canary = fx.bcx.ins().stack_load(fx.pointer_type, canary, 0);
+    let is_intact = fx.bcx.ins().icmp(IntCC::Equal, guard, canary);
+
+    let ok_block = fx.bcx.create_block();
+    let fail_block = fx.bcx.create_block();
+    fx.bcx.set_cold_block(fail_block);
+    fx.bcx.ins().brif(is_intact, ok_block, &[], fail_block, &[]);
+
+    fx.bcx.switch_to_block(fail_block);
+    fx.lib_call("__stack_chk_fail", vec![], vec![], &[]);
+    fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
+
+    fx.bcx.switch_to_block(ok_block);
+}
+
+/// Move the stack canary to a new stack slot created after all other stack slots.
+///
+/// Cranelift lays out stack slots in the order they are created, with later stack slots closer to
+/// the return address. Stack slots for temporaries are still created while codegening the function
+/// body, after the canary was stored, so overflowing those wouldn't overwrite the canary otherwise.
+pub(crate) fn move_canary_to_last_stack_slot(func: &mut Function, canary: StackSlot) {
+    let last_slot = func.create_sized_stack_slot(func.sized_stack_slots[canary].clone());
+    // Stack slots can't be removed, but the old one doesn't need to take up any space anymore.
+    func.sized_stack_slots[canary].size = 0;
+
+    for block in func.layout.blocks() {
+        for inst in func.layout.block_insts(block) {
+            match &mut func.dfg.insts[inst] {
+                InstructionData::StackLoad { stack_slot, .. }
+                | InstructionData::StackStore { stack_slot, .. }
+                    if *stack_slot == canary =>
+                {
+                    *stack_slot = last_slot;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn any_local_contains_array<'tcx>(
+    fx: &FunctionCx<'_, '_, 'tcx>,
+    is_protected: &dyn Fn(TyAndLayout<'tcx>, TyAndLayout<'tcx>) -> bool,
+) -> bool {
+    fn contains_array<'tcx>(
+        fx: &FunctionCx<'_, '_, 'tcx>,
+        layout: TyAndLayout<'tcx>,
+        is_protected: &dyn Fn(TyAndLayout<'tcx>, TyAndLayout<'tcx>) -> bool,
+    ) -> bool {
+        if let ty::Array(..) = layout.ty.kind() {
+            if is_protected(layout, layout.field(fx, 0)) {
+                return true;
+            }
+        }
+        match layout.fields {
+            FieldsShape::Primitive => false,
+            FieldsShape::Array { count: 0, .. } => false,
+            // All elements have the same type, so checking the first one is enough.
+            FieldsShape::Array { .. } => contains_array(fx, layout.field(fx, 0), is_protected),
+            FieldsShape::Union(_) | FieldsShape::Arbitrary { .. } => (0..layout.fields.count())
+                .any(|i| contains_array(fx, layout.field(fx, i), is_protected)),
+        }
+    }
+
+    fx.mir.local_decls.iter().any(|local_decl| {
+        let layout = fx.layout_of(fx.monomorphize(local_decl.ty));
+        layout.is_sized() && contains_array(fx, layout, is_protected)
+    })
+}
+
+fn load_stack_guard(fx: &mut FunctionCx<'_, '_, '_>) -> Value {
+    let target = &fx.tcx.sess.target;
+    if target.arch == "x86_64" && target.os == "linux" && target.env == "gnu" {
+        // glibc only stores the stack guard in the thread control block on x86_64 and doesn't
+        // define `__stack_chk_guard`. Cranelift can't load from the fs segment, so inline asm is
+        // necessary to read it. musl keeps `__stack_chk_guard` in sync with the thread control
+        // block.
+        let guard = CPlace::new_stack_slot(fx, fx.layout_of(fx.tcx.types.usize));
+        codegen_inline_asm_inner(
+            fx,
+            &[InlineAsmTemplatePiece::String("mov rax, qword ptr fs:[0x28]".to_string())],
+            &[CInlineAsmOperand::Out {
+                reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::ax)),
+                late: true,
+                place: Some(guard),
+            }],
+            InlineAsmOptions::NOSTACK | InlineAsmOptions::READONLY,
+        );
+        return guard.to_cvalue(fx).load_scalar(fx);
+    }
+
+    let data_id =
+        fx.module.declare_data("__stack_chk_guard", Linkage::Import, false, false).unwrap();
+    let local_data_id = fx.module.declare_data_in_func(data_id, &mut fx.bcx.func);
+    if fx.clif_comments.enabled() {
+        fx.add_comment(local_data_id, "__stack_chk_guard");
+    }
+    let guard_ptr = fx.bcx.ins().global_value(fx.pointer_type, local_data_id);
+    fx.bcx.ins().load(fx.pointer_type, MemFlags::trusted(), guard_ptr, 0)
+}
@@ -1,6 +1,8 @@
 //! SSA analysis
 
+use rustc_index::bit_set::BitSet;
 use rustc_index::IndexVec;
+use rustc_middle::mir::visit::{MutatingUseContext, PlaceContext, Visitor};
 use rustc_middle::mir::StatementKind::*;
 
 use crate::prelude::*;
@@ -13,7 +15,10 @@ pub(crate) enum SsaKind {
 
 impl SsaKind {
     pub(crate) fn is_ssa<'tcx>(self, fx: &FunctionCx<'_, '_, 'tcx>, ty: Ty<'tcx>) -> bool {
-        self == SsaKind::MaybeSsa && (fx.clif_type(ty).is_some() || fx.clif_pair_type(ty).is_some())
+        self == SsaKind::MaybeSsa
+            && (fx.clif_type(ty).is_some()
+                || fx.clif_pair_type(ty).is_some()
+                || register_vector_type(fx.tcx, fx.layout_of(ty)).is_some())
     }
 }
 
@@ -35,5 +40,74 @@ pub(crate) fn analyze(fx: &FunctionCx<'_, '_, '_>) -> IndexVec<Local, SsaKind> {
         }
     }
 
+    let is_vector = fx
+        .mir
+        .local_decls
+        .iter()
+        .map(|local_decl| fx.monomorphize(local_decl.ty).is_simd())
+        .collect::<IndexVec<Local, bool>>();
+    VectorLocalVisitor { is_vector: &is_vector, flag_map: &mut flag_map }.visit_body(fx.mir);
+
     flag_map
 }
+
+/// Vector locals can only be kept in SSA variables as long as individual lanes are never accessed
+/// through a place projection. In addition call and inline asm destinations may be written to
+/// through a pointer or lane by lane, so those are kept in memory too.
+struct VectorLocalVisitor<'a> {
+    is_vector: &'a IndexVec<Local, bool>,
+    flag_map: &'a mut IndexVec<Local, SsaKind>,
+}
+
+impl<'tcx> Visitor<'tcx> for VectorLocalVisitor<'_> {
+    fn visit_place(&mut self, place: &Place<'tcx>, context: PlaceContext, location: Location) {
+        if self.is_vector[place.local]
+            && (!place.projection.is_empty()
+                || matches!(
+                    context,
+                    PlaceContext::MutatingUse(
+                        MutatingUseContext::Call | MutatingUseContext::AsmOutput
+                    )
+                ))
+        {
+            self.flag_map[place.local] = SsaKind::NotSsa;
+        }
+
+        self.super_place(place, context, location);
+    }
+}
+
+/// Find all `&T` arguments with a sized `T: Freeze` which are never modified. The pointee of
+/// these can't change for the duration of the function as references passed as arguments are
+/// guaranteed to stay valid until the function returns.
+pub(crate) fn readonly_ref_args(fx: &FunctionCx<'_, '_, '_>) -> BitSet<Local> {
+    let mut readonly_ref_args = BitSet::new_empty(fx.mir.local_decls.len());
+    for local in fx.mir.args_iter() {
+        if let ty::Ref(_, pointee, Mutability::Not) =
+            *fx.monomorphize(fx.mir.local_decls[local].ty).kind()
+        {
+            if pointee.is_sized(fx.tcx, ParamEnv::reveal_all())
+                && pointee.is_freeze(fx.tcx, ParamEnv::reveal_all())
+            {
+                readonly_ref_args.insert(local);
+            }
+        }
+    }
+
+    MutatedLocalVisitor { locals: &mut readonly_ref_args }.visit_body(fx.mir);
+
+    readonly_ref_args
+}
+
+/// Removes every local which is modified or which may be modified through a pointer.
+struct MutatedLocalVisitor<'a> {
+    locals: &'a mut BitSet<Local>,
+}
+
+impl<'tcx> Visitor<'tcx> for MutatedLocalVisitor<'_> {
+    fn visit_local(&mut self, local: Local, context: PlaceContext, _location: Location) {
+        if context.is_mutating_use() {
+            self.locals.remove(local);
+        }
+    }
+}
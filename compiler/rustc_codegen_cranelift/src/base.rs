@@ -1,23 +1,33 @@
 //! Codegen of a single function
 
+use std::sync::Arc;
+use std::time::Instant;
+
 use cranelift_codegen::ir::UserFuncName;
+use cranelift_codegen::isa::TargetIsa;
+use cranelift_codegen::settings;
 use cranelift_codegen::CodegenError;
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
 use cranelift_module::ModuleError;
 use rustc_ast::InlineAsmOptions;
+use rustc_attr::OptimizeAttr;
 use rustc_codegen_ssa::base::is_call_from_compiler_builtins_to_upstream_monomorphization;
+use rustc_index::bit_set::BitSet;
 use rustc_index::IndexVec;
 use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
 use rustc_middle::ty::adjustment::PointerCoercion;
 use rustc_middle::ty::layout::FnAbiOf;
 use rustc_middle::ty::print::with_no_trimmed_paths;
 use rustc_middle::ty::TypeVisitableExt;
+use rustc_span::sym;
+use rustc_target::spec::SanitizerSet;
 
 use crate::constant::ConstantCx;
 use crate::debuginfo::{FunctionDebugContext, TypeDebugContext};
 use crate::inline_asm::codegen_naked_asm;
 use crate::prelude::*;
 use crate::pretty_clif::CommentWriter;
+use crate::unwind_module::UnwindModule;
 
 pub(crate) struct CodegenedFunction {
     symbol_name: String,
@@ -25,6 +35,18 @@ pub(crate) struct CodegenedFunction {
     func: Function,
     clif_comments: CommentWriter,
     func_debug_cx: Option<FunctionDebugContext>,
+    /// `TargetIsa` to compile this function with if it has `#[target_feature]` attributes.
+    isa: Option<Arc<dyn TargetIsa + 'static>>,
+}
+
+impl CodegenedFunction {
+    /// Compile this function as the definition of `func_id` rather than of the function it was
+    /// codegened for.
+    #[cfg(feature = "jit")]
+    pub(crate) fn define_as(&mut self, func_id: FuncId) {
+        self.func_id = func_id;
+        self.func.name = UserFuncName::user(0, func_id.as_u32());
+    }
 }
 
 pub(crate) fn codegen_fn<'tcx>(
@@ -50,7 +72,9 @@ pub(crate) fn codegen_fn<'tcx>(
         String::from_utf8_lossy(&buf).into_owned()
     });
 
-    if tcx.codegen_fn_attrs(instance.def_id()).flags.contains(CodegenFnAttrFlags::NAKED) {
+    let fn_attrs = tcx.codegen_fn_attrs(instance.def_id());
+
+    if fn_attrs.flags.contains(CodegenFnAttrFlags::NAKED) {
         assert_eq!(mir.basic_blocks.len(), 1);
         assert!(mir.basic_blocks[START_BLOCK].statements.is_empty());
 
@@ -103,7 +127,11 @@ pub(crate) fn codegen_fn<'tcx>(
     // Make FunctionCx
     let target_config = module.target_config();
     let pointer_type = target_config.pointer_type();
-    let clif_comments = crate::pretty_clif::CommentWriter::new(tcx, instance);
+    let clif_comments = crate::pretty_clif::CommentWriter::new(
+        tcx,
+        instance,
+        cx.should_write_ir || cx.clif_dump_dir.is_some(),
+    );
 
     let fn_abi = RevealAllLayoutCx(tcx).fn_abi_of_instance(instance, ty::List::empty());
 
@@ -113,6 +141,9 @@ pub(crate) fn codegen_fn<'tcx>(
         None
     };
 
+    let sanitize_address = tcx.sess.opts.unstable_opts.sanitizer.contains(SanitizerSet::ADDRESS)
+        && !fn_attrs.no_sanitize.contains(SanitizerSet::ADDRESS);
+
     let mut fx = FunctionCx {
         cx,
         module,
@@ -130,7 +161,10 @@ pub(crate) fn codegen_fn<'tcx>(
         bcx,
         block_map,
         local_map: IndexVec::with_capacity(mir.local_decls.len()),
-        caller_location: None, // set by `codegen_fn_prelude`
+        caller_location: None,                   // set by `codegen_fn_prelude`
+        readonly_ref_args: BitSet::new_empty(0), // set by `codegen_fn_prelude`
+        sanitize_address,
+        stack_protector_canary: None, // set by `codegen_fn_prelude`
 
         clif_comments,
         next_ssa_var: 0,
@@ -140,13 +174,24 @@ pub(crate) fn codegen_fn<'tcx>(
     fx.bcx.seal_all_blocks();
     fx.bcx.finalize();
 
+    if fx.func_debug_cx.is_some() {
+        define_local_debuginfo(&mut fx, type_dbg);
+    }
+
     // Recover all necessary data from fx, before accessing func will prevent future access to it.
     let symbol_name = fx.symbol_name;
     let clif_comments = fx.clif_comments;
     let func_debug_cx = fx.func_debug_cx;
+    let stack_protector_canary = fx.stack_protector_canary;
 
     fx.constants_cx.finalize(fx.tcx, &mut *fx.module);
 
+    if let Some(canary) = stack_protector_canary {
+        crate::abi::move_canary_to_last_stack_slot(&mut func, canary);
+    }
+
+    crate::common::apply_code_model(tcx.sess, &mut func);
+
     if cx.should_write_ir {
         crate::pretty_clif::write_clif_file(
             tcx.output_filenames(()),
@@ -161,13 +206,87 @@ pub(crate) fn codegen_fn<'tcx>(
     // Verify function
     verify_func(tcx, &clif_comments, &func);
 
-    Some(CodegenedFunction { symbol_name, func_id, func, clif_comments, func_debug_cx })
+    // FIXME handle `#[instruction_set(arm::a32 | arm::t32)]` by selecting ARM or Thumb mode and
+    // emitting the matching `$a`/`$t` mapping symbols once Cranelift supports 32bit ARM. rustc only
+    // accepts the attribute on ARM targets, which cg_clif can't compile for yet.
+
+    // Like with the LLVM backend `#[optimize]` doesn't enable optimizations when they are disabled
+    // for the whole crate.
+    let module_opt_level = module.isa().flags().opt_level();
+    let opt_level = match fn_attrs.optimize {
+        _ if module_opt_level == settings::OptLevel::None => None,
+        OptimizeAttr::None => None,
+        OptimizeAttr::Speed => Some(settings::OptLevel::Speed),
+        OptimizeAttr::Size => Some(settings::OptLevel::SpeedAndSize),
+    }
+    .filter(|&opt_level| opt_level != module_opt_level);
+
+    let target_features = &fn_attrs.target_features;
+    let isa = if target_features.is_empty() && opt_level.is_none() {
+        None
+    } else {
+        Some(
+            cx.fn_isas
+                .entry((target_features.clone(), opt_level))
+                .or_insert_with(|| {
+                    crate::build_isa_for_fn(tcx.sess, module.isa(), target_features, opt_level)
+                })
+                .clone(),
+        )
+    };
+
+    Some(CodegenedFunction { symbol_name, func_id, func, clif_comments, func_debug_cx, isa })
+}
+
+fn define_local_debuginfo<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    type_dbg: &mut TypeDebugContext<'tcx>,
+) {
+    let mut vars = vec![];
+    for var_debug_info in &fx.mir.var_debug_info {
+        // FIXME support variables of inlined functions once inlined subroutines are described
+        // in the debuginfo.
+        if var_debug_info.source_info.scope.inlined_instance(&fx.mir.source_scopes).is_some() {
+            continue;
+        }
+
+        // FIXME support fragments, projections, constants and locals stored in memory or in
+        // variable pairs.
+        if var_debug_info.composite.is_some() {
+            continue;
+        }
+        let VarDebugInfoContents::Place(place) = var_debug_info.value else {
+            continue;
+        };
+        if !place.projection.is_empty() {
+            continue;
+        }
+        let Some(var) = fx.local_map[place.local].try_to_var() else {
+            continue;
+        };
+
+        vars.push((var_debug_info, fx.monomorphize(fx.mir.local_decls[place.local].ty), var));
+    }
+
+    let debug_context = fx.cx.debug_context.as_mut().unwrap();
+    let func_debug_cx = fx.func_debug_cx.as_mut().unwrap();
+    for (var_debug_info, ty, var) in vars {
+        func_debug_cx.define_local(
+            debug_context,
+            fx.tcx,
+            type_dbg,
+            fx.mir.span,
+            var_debug_info,
+            ty,
+            var,
+        );
+    }
 }
 
 pub(crate) fn compile_fn(
     cx: &mut crate::CodegenCx,
     cached_context: &mut Context,
-    module: &mut dyn Module,
+    module: &mut UnwindModule<impl Module>,
     codegened_func: CodegenedFunction,
 ) {
     let _timer =
@@ -209,9 +328,15 @@ pub(crate) fn compile_fn(
     };
 
     // Define function
+    let define_start = Instant::now();
     cx.profiler.generic_activity("define function").run(|| {
-        context.want_disasm = cx.should_write_ir;
-        match module.define_function(codegened_func.func_id, context) {
+        context.want_disasm = cx.should_write_ir || cx.should_write_asm;
+        let res = if let Some(isa) = &codegened_func.isa {
+            module.define_function_with_isa(codegened_func.func_id, context, &**isa)
+        } else {
+            module.define_function(codegened_func.func_id, context)
+        };
+        match res {
             Ok(()) => {}
             Err(ModuleError::Compilation(CodegenError::ImplLimitExceeded)) => {
                 let early_dcx = rustc_session::EarlyDiagCtxt::new(
@@ -227,6 +352,24 @@ pub(crate) fn compile_fn(
             }
         }
     });
+    if let Some(function_timings) = &mut cx.function_timings {
+        function_timings.push((codegened_func.symbol_name.clone(), define_start.elapsed()));
+    }
+
+    let isa: &dyn TargetIsa = match &codegened_func.isa {
+        Some(isa) => &**isa,
+        None => module.isa(),
+    };
+
+    if let Some(clif_dump_dir) = &cx.clif_dump_dir {
+        crate::pretty_clif::write_clif_file_to_dir(
+            clif_dump_dir,
+            &codegened_func.symbol_name,
+            isa,
+            &context.func,
+            &clif_comments,
+        );
+    }
 
     if cx.should_write_ir {
         // Write optimized function to file for debugging
@@ -234,7 +377,7 @@ pub(crate) fn compile_fn(
             &cx.output_filenames,
             &codegened_func.symbol_name,
             "opt",
-            module.isa(),
+            isa,
             &context.func,
             &clif_comments,
         );
@@ -248,6 +391,14 @@ pub(crate) fn compile_fn(
         }
     }
 
+    if cx.should_write_asm {
+        let disasm = context.compiled_code().unwrap().vcode.as_deref().unwrap();
+        cx.assembly.push_str(&codegened_func.symbol_name);
+        cx.assembly.push_str(":\n");
+        cx.assembly.push_str(disasm);
+        cx.assembly.push('\n');
+    }
+
     // Define debuginfo for function
     let debug_context = &mut cx.debug_context;
     cx.profiler.generic_activity("generate debug info").run(|| {
@@ -256,6 +407,7 @@ pub(crate) fn compile_fn(
                 debug_context,
                 codegened_func.func_id,
                 context,
+                isa,
             );
         }
     });
@@ -320,6 +472,12 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
             // so for cleanup blocks.
         }
 
+        // Calls to cold functions mark the block they are in as cold, but statements before the
+        // call may already have split the block, so mark the start of the basic block too.
+        if calls_cold_fn(fx, bb_data.terminator()) {
+            fx.bcx.set_cold_block(block);
+        }
+
         fx.bcx.ins().nop();
         for stmt in &bb_data.statements {
             fx.set_debug_loc(stmt.source_info);
@@ -426,6 +584,7 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
             }
 
             TerminatorKind::SwitchInt { discr, targets } => {
+                let expected_cond = expected_switch_condition(fx, bb, discr);
                 let discr = codegen_operand(fx, discr);
                 let switch_ty = discr.layout().ty;
                 let discr = discr.load_scalar(fx);
@@ -434,8 +593,8 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                     || (targets.iter().count() == 1 && targets.iter().next().unwrap().0 == 0);
                 if use_bool_opt {
                     assert_eq!(targets.iter().count(), 1);
-                    let (then_value, then_block) = targets.iter().next().unwrap();
-                    let then_block = fx.get_block(then_block);
+                    let (then_value, then_bb) = targets.iter().next().unwrap();
+                    let then_block = fx.get_block(then_bb);
                     let else_block = fx.get_block(targets.otherwise());
                     let test_zero = match then_value {
                         0 => true,
@@ -443,12 +602,29 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                         _ => unreachable!("{:?}", targets),
                     };
 
+                    // Move the branch that is not expected to be taken out of the hot path.
+                    match expected_cond {
+                        Some(expected) if expected == test_zero => {
+                            fx.bcx.set_cold_block(then_block)
+                        }
+                        Some(_) => fx.bcx.set_cold_block(else_block),
+                        None => {}
+                    }
+
                     let (discr, is_inverted) =
                         crate::optimize::peephole::maybe_unwrap_bool_not(&mut fx.bcx, discr);
                     let test_zero = if is_inverted { !test_zero } else { test_zero };
-                    if let Some(taken) = crate::optimize::peephole::maybe_known_branch_taken(
-                        &fx.bcx, discr, test_zero,
-                    ) {
+                    // A branch to a block that only contains `unreachable` is never taken.
+                    let known_taken = if fx.mir[then_bb].is_empty_unreachable() {
+                        Some(false)
+                    } else if fx.mir[targets.otherwise()].is_empty_unreachable() {
+                        Some(true)
+                    } else {
+                        crate::optimize::peephole::maybe_known_branch_taken(
+                            &fx.bcx, discr, test_zero,
+                        )
+                    };
+                    if let Some(taken) = known_taken {
                         if taken {
                             fx.bcx.ins().jump(then_block, &[]);
                         } else {
@@ -462,12 +638,25 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                         }
                     }
                 } else {
+                    // If the otherwise target is unreachable, as is the case for every exhaustive
+                    // match on an enum, the last target can be used as otherwise target instead.
+                    // This saves a comparison and lets the unreachable block be removed.
+                    let mut entries: Vec<_> = targets.iter().collect();
+                    let otherwise = match entries.last() {
+                        Some(&(_value, last))
+                            if fx.mir[targets.otherwise()].is_empty_unreachable() =>
+                        {
+                            entries.pop();
+                            last
+                        }
+                        _ => targets.otherwise(),
+                    };
                     let mut switch = ::cranelift_frontend::Switch::new();
-                    for (value, block) in targets.iter() {
+                    for (value, block) in entries {
                         let block = fx.get_block(block);
                         switch.set_entry(value, block);
                     }
-                    let otherwise_block = fx.get_block(targets.otherwise());
+                    let otherwise_block = fx.get_block(otherwise);
                     switch.emit(&mut fx.bcx, discr, otherwise_block);
                 }
             }
@@ -491,11 +680,16 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                     )
                 });
             }
-            // FIXME(explicit_tail_calls): add support for tail calls to the cranelift backend, once cranelift supports tail calls
-            TerminatorKind::TailCall { fn_span, .. } => span_bug!(
-                *fn_span,
-                "tail calls are not yet supported in `rustc_codegen_cranelift` backend"
-            ),
+            TerminatorKind::TailCall { func, args, fn_span } => {
+                fx.tcx.prof.generic_activity("codegen call").run(|| {
+                    crate::abi::codegen_tail_call(
+                        fx,
+                        mir::SourceInfo { span: *fn_span, ..source_info },
+                        func,
+                        args,
+                    )
+                });
+            }
             TerminatorKind::InlineAsm {
                 template,
                 operands,
@@ -536,7 +730,10 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
                 codegen_unwind_terminate(fx, source_info, *reason);
             }
             TerminatorKind::UnwindResume => {
-                // FIXME implement unwinding
+                // FIXME implement unwinding. Cleanup blocks are only reachable through unwind
+                // edges, which can't be represented without landing pad support in Cranelift.
+                // Once they can, this should call `_Unwind_Resume` with the exception object
+                // received by the landing pad.
                 fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
             }
             TerminatorKind::Unreachable => {
@@ -548,6 +745,7 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
             | TerminatorKind::CoroutineDrop => {
                 bug!("shouldn't exist at codegen {:?}", bb_data.terminator());
             }
+            // FIXME lower the unwind edge to a landing pad once unwinding is supported
             TerminatorKind::Drop { place, target, unwind: _, replace: _ } => {
                 let drop_place = codegen_place(fx, *place);
                 crate::abi::codegen_drop(fx, source_info, drop_place, *target);
@@ -556,6 +754,47 @@ fn codegen_fn_body(fx: &mut FunctionCx<'_, '_, '_>, start_block: Block) {
     }
 }
 
+/// Whether `terminator` calls a `#[cold]` function or a function using the `rust-cold` ABI.
+fn calls_cold_fn<'tcx>(fx: &FunctionCx<'_, '_, 'tcx>, terminator: &Terminator<'tcx>) -> bool {
+    let TerminatorKind::Call { func, .. } = &terminator.kind else {
+        return false;
+    };
+    let func_ty = fx.monomorphize(func.ty(fx.mir, fx.tcx));
+    let def_id = match *func_ty.kind() {
+        ty::FnDef(def_id, _) => Some(def_id),
+        ty::FnPtr(..) => None,
+        _ => return false,
+    };
+    crate::abi::is_cold_fn(fx.tcx, func_ty.fn_sig(fx.tcx), def_id)
+}
+
+/// If the condition of a `SwitchInt` on a `bool` is the result of `likely` or `unlikely`, return
+/// whether the condition is expected to be true.
+fn expected_switch_condition<'tcx>(
+    fx: &FunctionCx<'_, '_, 'tcx>,
+    bb: BasicBlock,
+    discr: &Operand<'tcx>,
+) -> Option<bool> {
+    let discr_place = discr.place()?;
+    let &[pred] = &fx.mir.basic_blocks.predecessors()[bb][..] else {
+        return None;
+    };
+    let TerminatorKind::Call { func, destination, .. } = &fx.mir[pred].terminator().kind else {
+        return None;
+    };
+    if *destination != discr_place {
+        return None;
+    }
+    let ty::FnDef(def_id, _) = *func.ty(fx.mir, fx.tcx).kind() else {
+        return None;
+    };
+    match fx.tcx.intrinsic(def_id)?.name {
+        sym::likely => Some(true),
+        sym::unlikely => Some(false),
+        _ => None,
+    }
+}
+
 fn codegen_stmt<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     #[allow(unused_variables)] cur_block: Block,
@@ -790,10 +1029,15 @@ fn is_fat_ptr<'tcx>(fx: &FunctionCx<'_, '_, 'tcx>, ty: Ty<'tcx>) -> bool {
                         // Do nothing for ZST's
                     } else if fx.clif_type(operand.layout().ty) == Some(types::I8) {
                         let times = fx.bcx.ins().iconst(fx.pointer_type, times as i64);
-                        // FIXME use emit_small_memset where possible
                         let addr = lval.to_ptr().get_addr(fx);
                         let val = operand.load_scalar(fx);
-                        fx.bcx.call_memset(fx.target_config, addr, val, times);
+                        crate::intrinsics::codegen_write_bytes(
+                            fx,
+                            addr,
+                            val,
+                            times,
+                            operand.layout(),
+                        );
                     } else {
                         let loop_block = fx.bcx.create_block();
                         let loop_block2 = fx.bcx.create_block();
@@ -874,6 +1118,32 @@ fn is_fat_ptr<'tcx>(fx: &FunctionCx<'_, '_, 'tcx>, ty: Ty<'tcx>) -> bool {
                     };
                     lval.write_cvalue(fx, ptr_val);
                 }
+                Rvalue::Aggregate(_, ref operands)
+                    if lval.layout().ty.is_simd() && lval.try_to_var().is_some() =>
+                {
+                    // Build the vector in a register rather than writing it to memory lane by lane.
+                    let layout = lval.layout();
+                    if let [operand] = &*operands.raw {
+                        // `#[repr(simd)]` struct wrapping an array
+                        let operand = codegen_operand(fx, operand);
+                        lval.write_cvalue_transmute(fx, operand);
+                    } else {
+                        let vector_ty = register_vector_type(fx.tcx, layout).unwrap();
+                        let mut vector = None;
+                        for (i, operand) in operands.iter_enumerated() {
+                            let lane = codegen_operand(fx, operand).load_scalar(fx);
+                            vector = Some(match vector {
+                                None => fx.bcx.ins().splat(vector_ty, lane),
+                                Some(vector) => fx.bcx.ins().insertlane(
+                                    vector,
+                                    lane,
+                                    u8::try_from(i.as_u32()).unwrap(),
+                                ),
+                            });
+                        }
+                        lval.write_cvalue(fx, CValue::by_val(vector.unwrap(), layout));
+                    }
+                }
                 Rvalue::Aggregate(ref kind, ref operands) => {
                     let (variant_index, variant_dest, active_field_index) = match **kind {
                         mir::AggregateKind::Adt(_, variant_index, _, _, active_field_index) => {
@@ -911,30 +1181,34 @@ fn is_fat_ptr<'tcx>(fx: &FunctionCx<'_, '_, 'tcx>, ty: Ty<'tcx>) -> bool {
         | StatementKind::PlaceMention(..)
         | StatementKind::AscribeUserType(..) => {}
 
+        // FIXME implement `-Cinstrument-coverage`. This requires emitting the counters into
+        // `__llvm_prf_cnts` and the coverage mappings into `__llvm_covmap`/`__llvm_covfun` in
+        // exactly the format the profiler runtime and `llvm-cov` expect. Until then `init`
+        // rejects `-Cinstrument-coverage`, so the MIR never contains coverage statements.
         StatementKind::Coverage { .. } => unreachable!(),
         StatementKind::Intrinsic(ref intrinsic) => match &**intrinsic {
             // We ignore `assume` intrinsics, they are only useful for optimizations
-            NonDivergingIntrinsic::Assume(_) => {}
+            // FIXME pass the assumption on to Cranelift once it can make use of it. Cranelift has
+            // no `unreachable` instruction and doesn't derive facts from dominating conditions, so
+            // lowering to `trapz` would only add a real trap without removing any later checks.
+            // With UB checks enabled a violated assumption is reported by trapping instead.
+            NonDivergingIntrinsic::Assume(cond) => {
+                if fx.tcx.sess.ub_checks() {
+                    let cond = codegen_operand(fx, cond).load_scalar(fx);
+                    fx.bcx.ins().trapz(cond, TrapCode::UnreachableCodeReached);
+                }
+            }
             NonDivergingIntrinsic::CopyNonOverlapping(mir::CopyNonOverlapping {
                 src,
                 dst,
                 count,
             }) => {
                 let dst = codegen_operand(fx, dst);
-                let pointee = dst
-                    .layout()
-                    .pointee_info_at(fx, rustc_target::abi::Size::ZERO)
-                    .expect("Expected pointer");
+                let elem_layout = fx.layout_of(dst.layout().ty.builtin_deref(true).unwrap());
                 let dst = dst.load_scalar(fx);
                 let src = codegen_operand(fx, src).load_scalar(fx);
                 let count = codegen_operand(fx, count).load_scalar(fx);
-                let elem_size: u64 = pointee.size.bytes();
-                let bytes = if elem_size != 1 {
-                    fx.bcx.ins().imul_imm(count, elem_size as i64)
-                } else {
-                    count
-                };
-                fx.bcx.call_memcpy(fx.target_config, dst, src, bytes);
+                crate::intrinsics::codegen_copy_elems(fx, dst, src, count, elem_layout, true);
             }
         },
     }
@@ -957,10 +1231,21 @@ pub(crate) fn codegen_place<'tcx>(
 ) -> CPlace<'tcx> {
     let mut cplace = fx.get_local_place(place.local);
 
+    // Only mark loads as readonly when they stay within the pointee. Cranelift may hoist
+    // readonly loads, which would be unsound for an out of bounds index guarded by a bounds check.
+    let readonly = fx.readonly_ref_args.contains(place.local)
+        && place.projection.first() == Some(&PlaceElem::Deref)
+        && place.projection[1..].iter().all(|elem| {
+            matches!(elem, PlaceElem::Field(..) | PlaceElem::Downcast(..) | PlaceElem::Subtype(..))
+        });
+
     for elem in place.projection {
         match elem {
             PlaceElem::Deref => {
                 cplace = cplace.place_deref(fx);
+                if readonly {
+                    cplace = cplace.readonly();
+                }
             }
             PlaceElem::OpaqueCast(ty) => bug!("encountered OpaqueCast({ty}) in codegen"),
             PlaceElem::Subtype(ty) => cplace = cplace.place_transmute_type(fx, fx.monomorphize(ty)),
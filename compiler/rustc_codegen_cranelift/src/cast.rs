@@ -1,7 +1,29 @@
 //! Various number casting functions
 
+use rustc_session::config::Options;
+
 use crate::prelude::*;
 
+/// How float to int casts handle NaN.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum FloatCastMode {
+    /// NaN is turned into 0. This is what `as` casts do.
+    Saturating,
+    /// The result for NaN is whatever the conversion instruction produces.
+    /// Used with `-Zsaturating-float-casts=no`.
+    NonSaturating,
+}
+
+impl FloatCastMode {
+    /// The mode selected by `-Zsaturating-float-casts`, which is saturating unless disabled.
+    pub(crate) fn from_opts(opts: &Options) -> Self {
+        match opts.unstable_opts.saturating_float_casts {
+            Some(false) => FloatCastMode::NonSaturating,
+            None | Some(true) => FloatCastMode::Saturating,
+        }
+    }
+}
+
 pub(crate) fn clif_intcast(
     fx: &mut FunctionCx<'_, '_, '_>,
     val: Value,
@@ -36,6 +58,38 @@ pub(crate) fn clif_int_or_float_cast(
 ) -> Value {
     let from_ty = fx.bcx.func.dfg.value_type(from);
 
+    if from_ty.is_vector() {
+        return clif_vector_int_or_float_cast(fx, from, from_signed, to_ty, to_signed);
+    }
+
+    // Cranelift doesn't implement any arithmetic or conversions on f16, so f16 is converted to
+    // and from f32 using libcalls and the actual conversion is done on the f32 value.
+    // FIXME use native conversions where the ISA has them (F16C or AVX512-FP16 on x86_64, FEAT_FP16
+    // on aarch64) once Cranelift can lower `fpromote`, `fdemote` and `fcvt_*` for f16 and f128.
+    // None of its backends support these for either type yet, so f128 uses libcalls too.
+    if from_ty == types::F16 && to_ty != types::F16 {
+        let from = f16_to_f32(fx, from);
+        return match to_ty {
+            types::F32 => from,
+            // f16 -> f32 is exact, so this doesn't introduce any double rounding.
+            types::F64 => fx.bcx.ins().fpromote(types::F64, from),
+            _ => clif_int_or_float_cast(fx, from, from_signed, to_ty, to_signed),
+        };
+    } else if to_ty == types::F16 && from_ty != types::F16 {
+        return match from_ty {
+            types::F32 => f32_to_f16(fx, from),
+            // Going through f32 would round twice, which can give a different result.
+            types::F64 => f16_libcall(fx, "__truncdfhf2", types::F64, types::F16, from),
+            types::F128 => f16_libcall(fx, "__trunctfhf2", types::F128, types::F16, from),
+            _ => {
+                // Any integer that isn't exactly representable as f32 is larger than the largest
+                // finite f16, so rounding twice gives the same result as rounding once.
+                let val = clif_int_or_float_cast(fx, from, from_signed, types::F32, to_signed);
+                f32_to_f16(fx, val)
+            }
+        };
+    }
+
     if from_ty.is_int() && to_ty.is_int() {
         // int-like -> int-like
         clif_intcast(
@@ -47,26 +101,30 @@ pub(crate) fn clif_int_or_float_cast(
             from_signed,
         )
     } else if from_ty.is_int() && to_ty.is_float() {
-        if from_ty == types::I128 {
+        if from_ty == types::I128 || to_ty == types::F128 {
             // _______ss__f_
             // __float  tisf: i128 -> f32
             // __float  tidf: i128 -> f64
+            // __float  sitf: i32 -> f128
+            // __float  ditf: i64 -> f128
+            // __float  titf: i128 -> f128
             // __floatuntisf: u128 -> f32
             // __floatuntidf: u128 -> f64
+            // __floatunsitf: u32 -> f128
+            // __floatunditf: u64 -> f128
+            // __floatuntitf: u128 -> f128
 
-            let name = format!(
-                "__float{sign}ti{flt}f",
-                sign = if from_signed { "" } else { "un" },
-                flt = match to_ty {
-                    types::F32 => "s",
-                    types::F64 => "d",
-                    _ => unreachable!("{:?}", to_ty),
-                },
-            );
+            let (from, from_ty) = if from_ty.bits() < 32 {
+                (clif_intcast(fx, from, types::I32, from_signed), types::I32)
+            } else {
+                (from, from_ty)
+            };
+
+            let name = int_float_libcall_name(true, from_signed, from_ty, to_ty);
 
             return fx.lib_call(
                 &name,
-                vec![AbiParam::new(types::I128)],
+                vec![AbiParam::new(from_ty)],
                 vec![AbiParam::new(to_ty)],
                 &[from],
             )[0];
@@ -83,18 +141,12 @@ pub(crate) fn clif_int_or_float_cast(
             // _____sssf___
             // __fix   sfti: f32 -> i128
             // __fix   dfti: f64 -> i128
+            // __fix   tfti: f128 -> i128
             // __fixunssfti: f32 -> u128
             // __fixunsdfti: f64 -> u128
+            // __fixunstfti: f128 -> u128
 
-            let name = format!(
-                "__fix{sign}{flt}fti",
-                sign = if to_signed { "" } else { "uns" },
-                flt = match from_ty {
-                    types::F32 => "s",
-                    types::F64 => "d",
-                    _ => unreachable!("{:?}", to_ty),
-                },
-            );
+            let name = int_float_libcall_name(false, to_signed, to_ty, from_ty);
 
             if fx.tcx.sess.target.is_like_windows {
                 let ret = fx.lib_call(
@@ -115,40 +167,43 @@ pub(crate) fn clif_int_or_float_cast(
                     &[from],
                 )[0]
             }
-        } else if to_ty == types::I8 || to_ty == types::I16 {
-            // FIXME implement fcvt_to_*int_sat.i8/i16
+        } else if from_ty == types::F128 {
+            // __fix   tfsi: f128 -> i32
+            // __fix   tfdi: f128 -> i64
+            // __fixunstfsi: f128 -> u32
+            // __fixunstfdi: f128 -> u64
+
+            let lib_ty = if to_ty.bits() < 32 { types::I32 } else { to_ty };
+            let name = int_float_libcall_name(false, to_signed, lib_ty, from_ty);
+            let val = fx.lib_call(
+                &name,
+                vec![AbiParam::new(from_ty)],
+                vec![AbiParam::new(lib_ty)],
+                &[from],
+            )[0];
+            if lib_ty == to_ty { val } else { clamp_to_small_int(fx, val, to_ty, to_signed) }
+        } else if (to_ty == types::I8 || to_ty == types::I16)
+            && !supports_narrow_saturating_float_to_int(fx)
+        {
             let val = if to_signed {
                 fx.bcx.ins().fcvt_to_sint_sat(types::I32, from)
             } else {
                 fx.bcx.ins().fcvt_to_uint_sat(types::I32, from)
             };
-            let (min, max) = match (to_ty, to_signed) {
-                (types::I8, false) => (0, i64::from(u8::MAX)),
-                (types::I16, false) => (0, i64::from(u16::MAX)),
-                (types::I8, true) => (i64::from(i8::MIN as u32), i64::from(i8::MAX as u32)),
-                (types::I16, true) => (i64::from(i16::MIN as u32), i64::from(i16::MAX as u32)),
-                _ => unreachable!(),
-            };
-            let min_val = fx.bcx.ins().iconst(types::I32, min);
-            let max_val = fx.bcx.ins().iconst(types::I32, max);
-
-            let val = if to_signed {
-                let has_underflow = fx.bcx.ins().icmp_imm(IntCC::SignedLessThan, val, min);
-                let has_overflow = fx.bcx.ins().icmp_imm(IntCC::SignedGreaterThan, val, max);
-                let bottom_capped = fx.bcx.ins().select(has_underflow, min_val, val);
-                fx.bcx.ins().select(has_overflow, max_val, bottom_capped)
-            } else {
-                let has_overflow = fx.bcx.ins().icmp_imm(IntCC::UnsignedGreaterThan, val, max);
-                fx.bcx.ins().select(has_overflow, max_val, val)
-            };
-            fx.bcx.ins().ireduce(to_ty, val)
+            clamp_to_small_int(fx, val, to_ty, to_signed)
         } else if to_signed {
             fx.bcx.ins().fcvt_to_sint_sat(to_ty, from)
         } else {
             fx.bcx.ins().fcvt_to_uint_sat(to_ty, from)
         };
 
-        if let Some(false) = fx.tcx.sess.opts.unstable_opts.saturating_float_casts {
+        if fx.float_cast_mode() == FloatCastMode::NonSaturating {
+            return val;
+        }
+
+        if from_ty == types::F128 {
+            // The compiler-builtins conversions already saturate and return 0 for NaN. Cranelift
+            // can't compare f128 values, so skip the NaN check below.
             return val;
         }
 
@@ -160,9 +215,204 @@ pub(crate) fn clif_int_or_float_cast(
         match (from_ty, to_ty) {
             (types::F32, types::F64) => fx.bcx.ins().fpromote(types::F64, from),
             (types::F64, types::F32) => fx.bcx.ins().fdemote(types::F32, from),
+            (types::F32 | types::F64, types::F128) => {
+                // __extendsftf2: f32 -> f128
+                // __extenddftf2: f64 -> f128
+                let name = format!("__extend{}tf2", float_libcall_abbrev(from_ty));
+                fx.lib_call(
+                    &name,
+                    vec![AbiParam::new(from_ty)],
+                    vec![AbiParam::new(to_ty)],
+                    &[from],
+                )[0]
+            }
+            (types::F128, types::F32 | types::F64) => {
+                // __trunctfsf2: f128 -> f32
+                // __trunctfdf2: f128 -> f64
+                let name = format!("__trunctf{}2", float_libcall_abbrev(to_ty));
+                fx.lib_call(
+                    &name,
+                    vec![AbiParam::new(from_ty)],
+                    vec![AbiParam::new(to_ty)],
+                    &[from],
+                )[0]
+            }
             _ => from,
         }
     } else {
         unreachable!("cast value from {:?} to {:?}", from_ty, to_ty);
     }
 }
+
+/// Returns whether [`clif_int_or_float_cast`] can cast between the given vector types using vector
+/// instructions. If not, the cast has to be done lane by lane.
+pub(crate) fn supports_vector_cast(
+    fx: &FunctionCx<'_, '_, '_>,
+    from_ty: Type,
+    to_ty: Type,
+) -> bool {
+    if !from_ty.is_vector()
+        || from_ty.lane_count() != to_ty.lane_count()
+        || from_ty.lane_bits() != to_ty.lane_bits()
+    {
+        // Integer casts between different lane sizes are handled by `codegen_simd_int_resize`.
+        return false;
+    }
+
+    if from_ty.lane_type().is_int() == to_ty.lane_type().is_int() {
+        // Either a no-op or only the signedness changes.
+        return true;
+    }
+
+    match &*fx.tcx.sess.target.arch {
+        "aarch64" | "s390x" => true,
+        // Cranelift only implements the 32bit lane conversions without AVX-512.
+        "x86_64" => from_ty.lane_bits() == 32,
+        _ => false,
+    }
+}
+
+fn clif_vector_int_or_float_cast(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    from: Value,
+    from_signed: bool,
+    to_ty: Type,
+    to_signed: bool,
+) -> Value {
+    let from_ty = fx.bcx.func.dfg.value_type(from);
+    assert!(
+        supports_vector_cast(fx, from_ty, to_ty),
+        "cast value from {:?} to {:?}",
+        from_ty,
+        to_ty
+    );
+
+    let (from_lane_ty, to_lane_ty) = (from_ty.lane_type(), to_ty.lane_type());
+    if from_lane_ty.is_int() && to_lane_ty.is_float() {
+        if from_signed {
+            fx.bcx.ins().fcvt_from_sint(to_ty, from)
+        } else {
+            fx.bcx.ins().fcvt_from_uint(to_ty, from)
+        }
+    } else if from_lane_ty.is_float() && to_lane_ty.is_int() {
+        // The saturating conversions already return 0 for NaN.
+        if to_signed {
+            fx.bcx.ins().fcvt_to_sint_sat(to_ty, from)
+        } else {
+            fx.bcx.ins().fcvt_to_uint_sat(to_ty, from)
+        }
+    } else {
+        from
+    }
+}
+
+/// Whether Cranelift can lower `fcvt_to_*int_sat` with an i8 or i16 result for the current target.
+fn supports_narrow_saturating_float_to_int(fx: &FunctionCx<'_, '_, '_>) -> bool {
+    matches!(&*fx.tcx.sess.target.arch, "aarch64" | "riscv64" | "s390x")
+}
+
+fn clamp_to_small_int(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    val: Value,
+    to_ty: Type,
+    to_signed: bool,
+) -> Value {
+    let (min, max) = match (to_ty, to_signed) {
+        (types::I8, false) => (0, i64::from(u8::MAX)),
+        (types::I16, false) => (0, i64::from(u16::MAX)),
+        (types::I8, true) => (i64::from(i8::MIN as u32), i64::from(i8::MAX as u32)),
+        (types::I16, true) => (i64::from(i16::MIN as u32), i64::from(i16::MAX as u32)),
+        _ => unreachable!(),
+    };
+    let min_val = fx.bcx.ins().iconst(types::I32, min);
+    let max_val = fx.bcx.ins().iconst(types::I32, max);
+
+    let val = if to_signed {
+        let has_underflow = fx.bcx.ins().icmp_imm(IntCC::SignedLessThan, val, min);
+        let has_overflow = fx.bcx.ins().icmp_imm(IntCC::SignedGreaterThan, val, max);
+        let bottom_capped = fx.bcx.ins().select(has_underflow, min_val, val);
+        fx.bcx.ins().select(has_overflow, max_val, bottom_capped)
+    } else {
+        let has_overflow = fx.bcx.ins().icmp_imm(IntCC::UnsignedGreaterThan, val, max);
+        fx.bcx.ins().select(has_overflow, max_val, val)
+    };
+    fx.bcx.ins().ireduce(to_ty, val)
+}
+
+/// Returns the name of the compiler-builtins function converting between the given integer and
+/// float types, like `__floatuntidf` (u128 -> f64) or `__fixtfsi` (f128 -> i32).
+fn int_float_libcall_name(to_float: bool, signed: bool, int_ty: Type, float_ty: Type) -> String {
+    let int = match int_ty {
+        types::I32 => "si",
+        types::I64 => "di",
+        types::I128 => "ti",
+        _ => unreachable!("{:?}", int_ty),
+    };
+    let flt = float_libcall_abbrev(float_ty);
+    if to_float {
+        format!("__float{sign}{int}{flt}", sign = if signed { "" } else { "un" })
+    } else {
+        format!("__fix{sign}{flt}{int}", sign = if signed { "" } else { "uns" })
+    }
+}
+
+fn float_libcall_abbrev(float_ty: Type) -> &'static str {
+    match float_ty {
+        types::F16 => "hf",
+        types::F32 => "sf",
+        types::F64 => "df",
+        types::F128 => "tf",
+        _ => unreachable!("{:?}", float_ty),
+    }
+}
+
+fn f16_to_f32(fx: &mut FunctionCx<'_, '_, '_>, val: Value) -> Value {
+    f16_libcall(fx, "__extendhfsf2", types::F16, types::F32, val)
+}
+
+fn f32_to_f16(fx: &mut FunctionCx<'_, '_, '_>, val: Value) -> Value {
+    f16_libcall(fx, "__truncsfhf2", types::F32, types::F16, val)
+}
+
+fn f16_libcall(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    name: &str,
+    from_ty: Type,
+    to_ty: Type,
+    val: Value,
+) -> Value {
+    // On x86 Apple targets compiler-builtins passes f16 as u16 rather than in an xmm register.
+    let f16_abi_ty = if fx.tcx.sess.target.is_like_osx && fx.tcx.sess.target.arch == "x86_64" {
+        types::I16
+    } else {
+        types::F16
+    };
+
+    let (param_ty, val) = if from_ty == types::F16 && f16_abi_ty == types::I16 {
+        (types::I16, fx.bcx.ins().bitcast(types::I16, MemFlags::new(), val))
+    } else {
+        (from_ty, val)
+    };
+    let ret_ty = if to_ty == types::F16 { f16_abi_ty } else { to_ty };
+
+    let ret =
+        fx.lib_call(name, vec![AbiParam::new(param_ty)], vec![AbiParam::new(ret_ty)], &[val])[0];
+    if ret_ty != to_ty { fx.bcx.ins().bitcast(to_ty, MemFlags::new(), ret) } else { ret }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_session::config::Options;
+
+    use super::FloatCastMode;
+
+    #[test]
+    fn float_cast_mode_follows_saturating_float_casts() {
+        let mut opts = Options::default();
+        assert_eq!(FloatCastMode::from_opts(&opts), FloatCastMode::Saturating);
+        opts.unstable_opts.saturating_float_casts = Some(true);
+        assert_eq!(FloatCastMode::from_opts(&opts), FloatCastMode::Saturating);
+        opts.unstable_opts.saturating_float_casts = Some(false);
+        assert_eq!(FloatCastMode::from_opts(&opts), FloatCastMode::NonSaturating);
+    }
+}
@@ -38,35 +38,9 @@ pub(crate) fn maybe_codegen<'tcx>(
         }
         BinOp::Offset => unreachable!("offset should only be used on pointers, not 128bit ints"),
         BinOp::Div | BinOp::Rem => {
-            let name = match (bin_op, is_signed) {
-                (BinOp::Div, false) => "__udivti3",
-                (BinOp::Div, true) => "__divti3",
-                (BinOp::Rem, false) => "__umodti3",
-                (BinOp::Rem, true) => "__modti3",
-                _ => unreachable!(),
-            };
-            if fx.tcx.sess.target.is_like_windows {
-                let args = [lhs.load_scalar(fx), rhs.load_scalar(fx)];
-                let ret = fx.lib_call(
-                    name,
-                    vec![AbiParam::new(types::I128), AbiParam::new(types::I128)],
-                    vec![AbiParam::new(types::I64X2)],
-                    &args,
-                )[0];
-                // FIXME(bytecodealliance/wasmtime#6104) use bitcast instead of store to get from i64x2 to i128
-                let ret_place = CPlace::new_stack_slot(fx, lhs.layout());
-                ret_place.to_ptr().store(fx, ret, MemFlags::trusted());
-                Some(ret_place.to_cvalue(fx))
-            } else {
-                let args = [lhs.load_scalar(fx), rhs.load_scalar(fx)];
-                let ret_val = fx.lib_call(
-                    name,
-                    vec![AbiParam::new(types::I128), AbiParam::new(types::I128)],
-                    vec![AbiParam::new(types::I128)],
-                    &args,
-                )[0];
-                Some(CValue::by_val(ret_val, lhs.layout()))
-            }
+            let args = [lhs.load_scalar(fx), rhs.load_scalar(fx)];
+            let ret_val = codegen_div_rem(fx, bin_op, is_signed, args[0], args[1]);
+            Some(CValue::by_val(ret_val, lhs.layout()))
         }
         BinOp::Lt | BinOp::Le | BinOp::Eq | BinOp::Ge | BinOp::Gt | BinOp::Ne | BinOp::Cmp => None,
         BinOp::Shl | BinOp::ShlUnchecked | BinOp::Shr | BinOp::ShrUnchecked => None,
@@ -92,27 +66,8 @@ pub(crate) fn maybe_codegen_checked<'tcx>(
 
     match bin_op {
         BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => unreachable!(),
-        BinOp::Mul if is_signed => {
-            let out_ty = Ty::new_tup(fx.tcx, &[lhs.layout().ty, fx.tcx.types.bool]);
-            let oflow = CPlace::new_stack_slot(fx, fx.layout_of(fx.tcx.types.i32));
-            let lhs = lhs.load_scalar(fx);
-            let rhs = rhs.load_scalar(fx);
-            let oflow_ptr = oflow.to_ptr().get_addr(fx);
-            let res = fx.lib_call_unadjusted(
-                "__muloti4",
-                vec![
-                    AbiParam::new(types::I128),
-                    AbiParam::new(types::I128),
-                    AbiParam::new(fx.pointer_type),
-                ],
-                vec![AbiParam::new(types::I128)],
-                &[lhs, rhs, oflow_ptr],
-            )[0];
-            let oflow = oflow.to_cvalue(fx).load_scalar(fx);
-            let oflow = fx.bcx.ins().ireduce(types::I8, oflow);
-            Some(CValue::by_val_pair(res, oflow, fx.layout_of(out_ty)))
-        }
-        BinOp::Add | BinOp::Sub | BinOp::Mul => {
+        BinOp::Mul => maybe_codegen_checked_mul(fx, lhs, rhs),
+        BinOp::Add | BinOp::Sub => {
             let out_ty = Ty::new_tup(fx.tcx, &[lhs.layout().ty, fx.tcx.types.bool]);
             let out_place = CPlace::new_stack_slot(fx, fx.layout_of(out_ty));
             let param_types = vec![
@@ -126,7 +81,6 @@ pub(crate) fn maybe_codegen_checked<'tcx>(
                 (BinOp::Add, true) => "__rust_i128_addo",
                 (BinOp::Sub, false) => "__rust_u128_subo",
                 (BinOp::Sub, true) => "__rust_i128_subo",
-                (BinOp::Mul, false) => "__rust_u128_mulo",
                 _ => unreachable!(),
             };
             fx.lib_call(name, param_types, vec![], &args);
@@ -141,3 +95,248 @@ pub(crate) fn maybe_codegen_checked<'tcx>(
         BinOp::Shl | BinOp::ShlUnchecked | BinOp::Shr | BinOp::ShrUnchecked => unreachable!(),
     }
 }
+
+/// Multiplies two 128bit ints and checks for overflow without calling into compiler-builtins.
+pub(crate) fn maybe_codegen_checked_mul<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    lhs: CValue<'tcx>,
+    rhs: CValue<'tcx>,
+) -> Option<CValue<'tcx>> {
+    if lhs.layout().ty != fx.tcx.types.u128 && lhs.layout().ty != fx.tcx.types.i128 {
+        return None;
+    }
+
+    let is_signed = type_sign(lhs.layout().ty);
+    let out_layout = fx.layout_of(Ty::new_tup(fx.tcx, &[lhs.layout().ty, fx.tcx.types.bool]));
+    let lhs = lhs.load_scalar(fx);
+    let rhs = rhs.load_scalar(fx);
+
+    if !is_signed {
+        let (res, has_overflow) = codegen_u128_checked_mul(fx, lhs, rhs);
+        return Some(CValue::by_val_pair(res, has_overflow, out_layout));
+    }
+
+    // Multiply the absolute values and fix up the sign of the result afterwards.
+    let zero = type_zero_value(&mut fx.bcx, types::I128);
+    let lhs_neg = fx.bcx.ins().icmp(IntCC::SignedLessThan, lhs, zero);
+    let rhs_neg = fx.bcx.ins().icmp(IntCC::SignedLessThan, rhs, zero);
+    let lhs_negated = fx.bcx.ins().ineg(lhs);
+    let rhs_negated = fx.bcx.ins().ineg(rhs);
+    // |i128::MIN| wraps back to i128::MIN, which is the correct value when treated as u128.
+    let lhs_abs = fx.bcx.ins().select(lhs_neg, lhs_negated, lhs);
+    let rhs_abs = fx.bcx.ins().select(rhs_neg, rhs_negated, rhs);
+    let (res_abs, abs_overflow) = codegen_u128_checked_mul(fx, lhs_abs, rhs_abs);
+
+    // A negative result may be at most 2^127 (i128::MIN), a positive result at most 2^127 - 1.
+    let res_neg = fx.bcx.ins().bxor(lhs_neg, rhs_neg);
+    let zero_64 = fx.bcx.ins().iconst(types::I64, 0);
+    let min_msb = fx.bcx.ins().iconst(types::I64, i64::MIN);
+    let i128_min = fx.bcx.ins().iconcat(zero_64, min_msb);
+    let neg_overflow = fx.bcx.ins().icmp(IntCC::UnsignedGreaterThan, res_abs, i128_min);
+    let pos_overflow = fx.bcx.ins().icmp(IntCC::UnsignedGreaterThanOrEqual, res_abs, i128_min);
+    let sign_overflow = fx.bcx.ins().select(res_neg, neg_overflow, pos_overflow);
+    let has_overflow = fx.bcx.ins().bor(abs_overflow, sign_overflow);
+
+    let res_abs_negated = fx.bcx.ins().ineg(res_abs);
+    let res = fx.bcx.ins().select(res_neg, res_abs_negated, res_abs);
+
+    Some(CValue::by_val_pair(res, has_overflow, out_layout))
+}
+
+/// Multiplies two u128, returning the wrapped result and whether it overflowed.
+fn codegen_u128_checked_mul(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    lhs: Value,
+    rhs: Value,
+) -> (Value, Value) {
+    let (res_lo, res_hi) = codegen_u128_widening_mul(fx, lhs, rhs);
+    let zero = type_zero_value(&mut fx.bcx, types::I128);
+    let has_overflow = fx.bcx.ins().icmp(IntCC::NotEqual, res_hi, zero);
+    (res_lo, has_overflow)
+}
+
+/// Computes the full 256bit product of two u128 using schoolbook multiplication of 64bit limbs.
+/// Returns the low and high 128 bits of the product.
+pub(crate) fn codegen_u128_widening_mul(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    lhs: Value,
+    rhs: Value,
+) -> (Value, Value) {
+    let (lhs_lo, lhs_hi) = fx.bcx.ins().isplit(lhs);
+    let (rhs_lo, rhs_hi) = fx.bcx.ins().isplit(rhs);
+
+    // lhs * rhs = lhs_lo * rhs_lo
+    //           + ((lhs_lo * rhs_hi + lhs_hi * rhs_lo) << 64)
+    //           + ((lhs_hi * rhs_hi) << 128)
+    let lo_lo_lo = fx.bcx.ins().imul(lhs_lo, rhs_lo);
+    let lo_lo_hi = fx.bcx.ins().umulhi(lhs_lo, rhs_lo);
+    let lo_hi_lo = fx.bcx.ins().imul(lhs_lo, rhs_hi);
+    let lo_hi_hi = fx.bcx.ins().umulhi(lhs_lo, rhs_hi);
+    let hi_lo_lo = fx.bcx.ins().imul(lhs_hi, rhs_lo);
+    let hi_lo_hi = fx.bcx.ins().umulhi(lhs_hi, rhs_lo);
+    let hi_hi_lo = fx.bcx.ins().imul(lhs_hi, rhs_hi);
+    let hi_hi_hi = fx.bcx.ins().umulhi(lhs_hi, rhs_hi);
+
+    // Sum the limbs of each column in 128bit arithmetic and carry the upper half into the next
+    // column. Each sum is at most 4 * (2^64 - 1), so none of them can overflow.
+    let column = |fx: &mut FunctionCx<'_, '_, '_>, limbs: &[Value]| {
+        let mut sum = fx.bcx.ins().uextend(types::I128, limbs[0]);
+        for &limb in &limbs[1..] {
+            let limb = fx.bcx.ins().uextend(types::I128, limb);
+            sum = fx.bcx.ins().iadd(sum, limb);
+        }
+        fx.bcx.ins().isplit(sum)
+    };
+    let (word1, carry1) = column(fx, &[lo_lo_hi, lo_hi_lo, hi_lo_lo]);
+    let (word2, carry2) = column(fx, &[carry1, lo_hi_hi, hi_lo_hi, hi_hi_lo]);
+    let word3 = fx.bcx.ins().iadd(hi_hi_hi, carry2);
+
+    let res_lo = fx.bcx.ins().iconcat(lo_lo_lo, word1);
+    let res_hi = fx.bcx.ins().iconcat(word2, word3);
+    (res_lo, res_hi)
+}
+
+/// Divides two 128bit ints. If both fit in 64bit ints, which is the common case, a native 64bit
+/// division is used. Otherwise this falls back to the compiler-builtins implementation.
+fn codegen_div_rem(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    bin_op: BinOp,
+    is_signed: bool,
+    lhs: Value,
+    rhs: Value,
+) -> Value {
+    let (lhs_lsb, lhs_msb) = fx.bcx.ins().isplit(lhs);
+    let (rhs_lsb, rhs_msb) = fx.bcx.ins().isplit(rhs);
+
+    let fits_in_64bit = if is_signed {
+        let lhs_sign = fx.bcx.ins().sshr_imm(lhs_lsb, 63);
+        let rhs_sign = fx.bcx.ins().sshr_imm(rhs_lsb, 63);
+        let lhs_fits = fx.bcx.ins().icmp(IntCC::Equal, lhs_msb, lhs_sign);
+        let rhs_fits = fx.bcx.ins().icmp(IntCC::Equal, rhs_msb, rhs_sign);
+        // i64::MIN / -1 overflows a 64bit division, but not a 128bit one.
+        let lhs_not_min = fx.bcx.ins().icmp_imm(IntCC::NotEqual, lhs_lsb, i64::MIN);
+        let both_fit = fx.bcx.ins().band(lhs_fits, rhs_fits);
+        fx.bcx.ins().band(both_fit, lhs_not_min)
+    } else {
+        let msb = fx.bcx.ins().bor(lhs_msb, rhs_msb);
+        fx.bcx.ins().icmp_imm(IntCC::Equal, msb, 0)
+    };
+
+    let fast_block = fx.bcx.create_block();
+    let slow_block = fx.bcx.create_block();
+    let done_block = fx.bcx.create_block();
+    let res = fx.bcx.append_block_param(done_block, types::I128);
+    fx.bcx.ins().brif(fits_in_64bit, fast_block, &[], slow_block, &[]);
+
+    fx.bcx.switch_to_block(fast_block);
+    let res_64bit = match (bin_op, is_signed) {
+        (BinOp::Div, false) => fx.bcx.ins().udiv(lhs_lsb, rhs_lsb),
+        (BinOp::Div, true) => fx.bcx.ins().sdiv(lhs_lsb, rhs_lsb),
+        (BinOp::Rem, false) => fx.bcx.ins().urem(lhs_lsb, rhs_lsb),
+        (BinOp::Rem, true) => fx.bcx.ins().srem(lhs_lsb, rhs_lsb),
+        _ => unreachable!(),
+    };
+    let res_128bit = if is_signed {
+        fx.bcx.ins().sextend(types::I128, res_64bit)
+    } else {
+        fx.bcx.ins().uextend(types::I128, res_64bit)
+    };
+    fx.bcx.ins().jump(done_block, &[res_128bit]);
+
+    fx.bcx.switch_to_block(slow_block);
+    let name = match (bin_op, is_signed) {
+        (BinOp::Div, false) => "__udivti3",
+        (BinOp::Div, true) => "__divti3",
+        (BinOp::Rem, false) => "__umodti3",
+        (BinOp::Rem, true) => "__modti3",
+        _ => unreachable!(),
+    };
+    let res_libcall = if fx.tcx.sess.target.is_like_windows {
+        let ret = fx.lib_call(
+            name,
+            vec![AbiParam::new(types::I128), AbiParam::new(types::I128)],
+            vec![AbiParam::new(types::I64X2)],
+            &[lhs, rhs],
+        )[0];
+        // FIXME(bytecodealliance/wasmtime#6104) use bitcast instead of store to get from i64x2 to i128
+        let ret_ptr = fx.create_stack_slot(16, 16);
+        ret_ptr.store(fx, ret, MemFlags::trusted());
+        ret_ptr.load(fx, types::I128, MemFlags::trusted())
+    } else {
+        fx.lib_call(
+            name,
+            vec![AbiParam::new(types::I128), AbiParam::new(types::I128)],
+            vec![AbiParam::new(types::I128)],
+            &[lhs, rhs],
+        )[0]
+    };
+    fx.bcx.ins().jump(done_block, &[res_libcall]);
+
+    fx.bcx.switch_to_block(done_block);
+    res
+}
+
+/// Counts the leading zeros of a 128bit int using the 64bit halves.
+pub(crate) fn codegen_i128_clz(fx: &mut FunctionCx<'_, '_, '_>, val: Value) -> Value {
+    let (lo, hi) = fx.bcx.ins().isplit(val);
+    let lo_clz = fx.bcx.ins().clz(lo);
+    let hi_clz = fx.bcx.ins().clz(hi);
+    // clz of a zero 64bit int is 64, so this also gives 128 for a zero input.
+    let lo_clz = fx.bcx.ins().iadd_imm(lo_clz, 64);
+    let hi_is_zero = fx.bcx.ins().icmp_imm(IntCC::Equal, hi, 0);
+    fx.bcx.ins().select(hi_is_zero, lo_clz, hi_clz)
+}
+
+/// Counts the trailing zeros of a 128bit int using the 64bit halves.
+pub(crate) fn codegen_i128_ctz(fx: &mut FunctionCx<'_, '_, '_>, val: Value) -> Value {
+    let (lo, hi) = fx.bcx.ins().isplit(val);
+    let lo_ctz = fx.bcx.ins().ctz(lo);
+    let hi_ctz = fx.bcx.ins().ctz(hi);
+    // ctz of a zero 64bit int is 64, so this also gives 128 for a zero input.
+    let hi_ctz = fx.bcx.ins().iadd_imm(hi_ctz, 64);
+    let lo_is_zero = fx.bcx.ins().icmp_imm(IntCC::Equal, lo, 0);
+    fx.bcx.ins().select(lo_is_zero, hi_ctz, lo_ctz)
+}
+
+/// Counts the ones of a 128bit int using the 64bit halves.
+pub(crate) fn codegen_i128_popcnt(fx: &mut FunctionCx<'_, '_, '_>, val: Value) -> Value {
+    let (lo, hi) = fx.bcx.ins().isplit(val);
+    let lo_popcnt = fx.bcx.ins().popcnt(lo);
+    let hi_popcnt = fx.bcx.ins().popcnt(hi);
+    fx.bcx.ins().iadd(lo_popcnt, hi_popcnt)
+}
+
+/// Reverses the bytes of a 128bit int by reversing and swapping the 64bit halves.
+pub(crate) fn codegen_i128_bswap(fx: &mut FunctionCx<'_, '_, '_>, val: Value) -> Value {
+    let (lo, hi) = fx.bcx.ins().isplit(val);
+    let lo = fx.bcx.ins().bswap(lo);
+    let hi = fx.bcx.ins().bswap(hi);
+    fx.bcx.ins().iconcat(hi, lo)
+}
+
+/// Reverses the bits of a 128bit int by reversing and swapping the 64bit halves.
+pub(crate) fn codegen_i128_bitrev(fx: &mut FunctionCx<'_, '_, '_>, val: Value) -> Value {
+    let (lo, hi) = fx.bcx.ins().isplit(val);
+    let lo = fx.bcx.ins().bitrev(lo);
+    let hi = fx.bcx.ins().bitrev(hi);
+    fx.bcx.ins().iconcat(hi, lo)
+}
+
+/// Rotates a 128bit int using shifts. The rotation amount is taken modulo 128.
+pub(crate) fn codegen_i128_rotate(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    val: Value,
+    amount: Value,
+    is_left: bool,
+) -> Value {
+    // Cranelift shifts take the shift amount modulo the bit width, so shifting by `-amount`
+    // shifts by `128 - amount` without an extra case for a zero amount.
+    let amount = fx.bcx.ins().band_imm(amount, 127);
+    let inv_amount = fx.bcx.ins().ineg(amount);
+    let (lhs, rhs) = if is_left {
+        (fx.bcx.ins().ishl(val, amount), fx.bcx.ins().ushr(val, inv_amount))
+    } else {
+        (fx.bcx.ins().ushr(val, amount), fx.bcx.ins().ishl(val, inv_amount))
+    };
+    fx.bcx.ins().bor(lhs, rhs)
+}
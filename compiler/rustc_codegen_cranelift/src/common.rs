@@ -1,14 +1,17 @@
+use cranelift_codegen::ir::GlobalValueData;
 use cranelift_codegen::isa::TargetFrontendConfig;
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use rustc_index::bit_set::BitSet;
 use rustc_index::IndexVec;
 use rustc_middle::ty::layout::{
     self, FnAbiError, FnAbiOfHelpers, FnAbiRequest, LayoutError, LayoutOfHelpers,
 };
 use rustc_middle::ty::TypeFoldable;
+use rustc_session::Session;
 use rustc_span::source_map::Spanned;
 use rustc_target::abi::call::FnAbi;
 use rustc_target::abi::{Float, Integer, Primitive};
-use rustc_target::spec::{HasTargetSpec, Target};
+use rustc_target::spec::{CodeModel, HasTargetSpec, Target};
 
 use crate::constant::ConstantCx;
 use crate::debuginfo::FunctionDebugContext;
@@ -33,16 +36,50 @@ pub(crate) fn scalar_to_clif_type(tcx: TyCtxt<'_>, scalar: Scalar) -> Type {
             Integer::I128 => types::I128,
         },
         Primitive::Float(float) => match float {
-            Float::F16 => unimplemented!("f16_f128"),
+            Float::F16 => types::F16,
             Float::F32 => types::F32,
             Float::F64 => types::F64,
-            Float::F128 => unimplemented!("f16_f128"),
+            Float::F128 => types::F128,
         },
         // FIXME(erikdesjardins): handle non-default addrspace ptr sizes
         Primitive::Pointer(_) => pointer_ty(tcx),
     }
 }
 
+/// Returns the Cranelift vector type for a value with the given `#[repr(simd)]` layout, if
+/// Cranelift has one with the same lane type and lane count.
+pub(crate) fn try_clif_vector_type<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    layout: TyAndLayout<'tcx>,
+) -> Option<Type> {
+    let (element, count) = match layout.abi {
+        Abi::Vector { element, count } => (element, count),
+        _ => return None,
+    };
+
+    scalar_to_clif_type(tcx, element).by(u32::try_from(count).ok()?)
+}
+
+/// Returns the Cranelift vector type that a value with the given `#[repr(simd)]` layout can be
+/// kept in a register as, if any.
+pub(crate) fn register_vector_type<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    layout: TyAndLayout<'tcx>,
+) -> Option<Type> {
+    // Single lane vectors don't have a Cranelift vector type. Vectors wider than 128 bits exceed
+    // the widest vector register Cranelift supports and are kept in memory instead.
+    match try_clif_vector_type(tcx, layout) {
+        Some(vector_ty)
+            if vector_ty.lane_count() >= 2
+                && vector_ty.bits() == 128
+                && vector_ty.lane_type() != types::F16 =>
+        {
+            Some(vector_ty)
+        }
+        _ => None,
+    }
+}
+
 fn clif_type_from_ty<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Option<types::Type> {
     Some(match ty.kind() {
         ty::Bool => types::I8,
@@ -64,10 +101,10 @@ fn clif_type_from_ty<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Option<types::Typ
         },
         ty::Char => types::I32,
         ty::Float(size) => match size {
-            FloatTy::F16 => unimplemented!("f16_f128"),
+            FloatTy::F16 => types::F16,
             FloatTy::F32 => types::F32,
             FloatTy::F64 => types::F64,
-            FloatTy::F128 => unimplemented!("f16_f128"),
+            FloatTy::F128 => types::F128,
         },
         ty::FnPtr(_) => pointer_ty(tcx),
         ty::RawPtr(pointee_ty, _) | ty::Ref(_, pointee_ty, _) => {
@@ -77,6 +114,7 @@ fn clif_type_from_ty<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Option<types::Typ
                 pointer_ty(tcx)
             }
         }
+        ty::Adt(..) => return clif_type_from_ty(tcx, transparent_field_ty(tcx, ty)?),
         ty::Param(_) => bug!("ty param {:?}", ty),
         _ => return None,
     })
@@ -97,10 +135,28 @@ fn clif_pair_type_from_ty<'tcx>(
                 return None;
             }
         }
+        ty::Adt(..) => return clif_pair_type_from_ty(tcx, transparent_field_ty(tcx, ty)?),
         _ => return None,
     })
 }
 
+/// If `ty` is a `#[repr(transparent)]` struct, returns the type of the field it has the same
+/// layout and ABI as, which is the only field that isn't a 1-ZST.
+pub(crate) fn transparent_field_ty<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Option<Ty<'tcx>> {
+    let ty::Adt(adt_def, args) = ty.kind() else {
+        return None;
+    };
+    if !adt_def.is_struct() || !adt_def.repr().transparent() {
+        return None;
+    }
+    adt_def
+        .non_enum_variant()
+        .fields
+        .iter()
+        .map(|field| tcx.normalize_erasing_regions(ParamEnv::reveal_all(), field.ty(tcx, args)))
+        .find(|&field_ty| !tcx.layout_of(ParamEnv::reveal_all().and(field_ty)).unwrap().is_1zst())
+}
+
 /// Is a pointer to this type a fat ptr?
 pub(crate) fn has_ptr_meta<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> bool {
     if ty.is_sized(tcx, ParamEnv::reveal_all()) {
@@ -245,6 +301,33 @@ pub(crate) fn type_sign(ty: Ty<'_>) -> bool {
     }
 }
 
+/// Stop assuming that the functions and statics referenced by `func` are within range of a 32bit
+/// pc-relative relocation when the code model requires it. Cranelift then materializes their
+/// addresses using 64bit absolute relocations, or loads them from the GOT when generating position
+/// independent code. `build_isa` rejects the large code model for position independent code.
+///
+/// FIXME also apply this to the functions created by `create_wrapper_function`
+pub(crate) fn apply_code_model(sess: &Session, func: &mut Function) {
+    let (far_functions, far_data) = match sess.code_model() {
+        None | Some(CodeModel::Tiny | CodeModel::Small | CodeModel::Kernel) => return,
+        Some(CodeModel::Medium) => (false, true),
+        Some(CodeModel::Large) => (true, true),
+    };
+
+    if far_functions {
+        for ext_func in func.dfg.ext_funcs.values_mut() {
+            ext_func.colocated = false;
+        }
+    }
+    if far_data {
+        for global_value in func.global_values.values_mut() {
+            if let GlobalValueData::Symbol { colocated, .. } = global_value {
+                *colocated = false;
+            }
+        }
+    }
+}
+
 pub(crate) fn create_wrapper_function(
     module: &mut dyn Module,
     sig: Signature,
@@ -281,6 +364,36 @@ pub(crate) fn create_wrapper_function(
     module.define_function(wrapper_func_id, &mut ctx).unwrap();
 }
 
+/// Define a local function which returns its pointer argument unchanged.
+///
+/// Cranelift doesn't look into called functions, so calling this function hides where the
+/// returned pointer points to from the optimizer.
+pub(crate) fn create_identity_function(module: &mut dyn Module, pointer_type: Type) -> FuncId {
+    let sig = Signature {
+        params: vec![AbiParam::new(pointer_type)],
+        returns: vec![AbiParam::new(pointer_type)],
+        call_conv: module.target_config().default_call_conv,
+    };
+    let func_id = module.declare_anonymous_function(&sig).unwrap();
+
+    let mut ctx = Context::new();
+    ctx.func.signature = sig;
+    {
+        let mut func_ctx = FunctionBuilderContext::new();
+        let mut bcx = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+
+        let block = bcx.create_block();
+        bcx.switch_to_block(block);
+        let ptr = bcx.append_block_param(block, pointer_type);
+        bcx.ins().return_(&[ptr]);
+        bcx.seal_all_blocks();
+        bcx.finalize();
+    }
+    module.define_function(func_id, &mut ctx).unwrap();
+
+    func_id
+}
+
 pub(crate) struct FunctionCx<'m, 'clif, 'tcx: 'm> {
     pub(crate) cx: &'clif mut crate::CodegenCx,
     pub(crate) module: &'m mut dyn Module,
@@ -301,6 +414,12 @@ pub(crate) struct FunctionCx<'m, 'clif, 'tcx: 'm> {
 
     /// When `#[track_caller]` is used, the implicit caller location is stored in this variable.
     pub(crate) caller_location: Option<CValue<'tcx>>,
+    /// `&T` arguments whose pointee can be loaded from using `readonly` loads.
+    pub(crate) readonly_ref_args: BitSet<Local>,
+    /// Memory accesses need to be checked by AddressSanitizer.
+    pub(crate) sanitize_address: bool,
+    /// Stack slot containing the stack protector canary if the function has a stack protector.
+    pub(crate) stack_protector_canary: Option<StackSlot>,
 
     pub(crate) clif_comments: crate::pretty_clif::CommentWriter,
 
@@ -375,6 +494,10 @@ pub(crate) fn clif_pair_type(&self, ty: Ty<'tcx>) -> Option<(Type, Type)> {
         clif_pair_type_from_ty(self.tcx, ty)
     }
 
+    pub(crate) fn float_cast_mode(&self) -> FloatCastMode {
+        FloatCastMode::from_opts(&self.tcx.sess.opts)
+    }
+
     pub(crate) fn get_block(&self, bb: BasicBlock) -> Block {
         *self.block_map.get(bb).unwrap()
     }
@@ -418,8 +541,15 @@ pub(crate) fn set_debug_loc(&mut self, source_info: mir::SourceInfo) {
             let (file_id, line, column) =
                 debug_context.get_span_loc(self.tcx, self.mir.span, source_info.span);
 
-            let source_loc =
-                self.func_debug_cx.as_mut().unwrap().add_dbg_loc(file_id, line, column);
+            let func_debug_cx = self.func_debug_cx.as_mut().unwrap();
+            let inlined_scope = func_debug_cx.define_inlined_scope(
+                debug_context,
+                self.tcx,
+                self.instance,
+                self.mir,
+                source_info.scope,
+            );
+            let source_loc = func_debug_cx.add_dbg_loc(file_id, line, column, inlined_scope);
             self.bcx.set_srcloc(source_loc);
         }
     }
@@ -39,7 +39,6 @@ pub(crate) fn $register(builder: &mut cranelift_jit::JITBuilder) {
 
     // integers
     fn __multi3(a: i128, b: i128) -> i128;
-    fn __muloti4(n: i128, d: i128, oflow: &mut i32) -> i128;
     fn __udivti3(n: u128, d: u128) -> u128;
     fn __divti3(n: i128, d: i128) -> i128;
     fn __umodti3(n: u128, d: u128) -> u128;
@@ -48,8 +47,6 @@ pub(crate) fn $register(builder: &mut cranelift_jit::JITBuilder) {
     fn __rust_i128_addo(a: i128, b: i128) -> (i128, bool);
     fn __rust_u128_subo(a: u128, b: u128) -> (u128, bool);
     fn __rust_i128_subo(a: i128, b: i128) -> (i128, bool);
-    fn __rust_u128_mulo(a: u128, b: u128) -> (u128, bool);
-    fn __rust_i128_mulo(a: i128, b: i128) -> (i128, bool);
 
     // floats
     fn __floattisf(i: i128) -> f32;
@@ -60,6 +57,28 @@ pub(crate) fn $register(builder: &mut cranelift_jit::JITBuilder) {
     fn __fixdfti(f: f64) -> i128;
     fn __fixunssfti(f: f32) -> u128;
     fn __fixunsdfti(f: f64) -> u128;
+    // f16 and f128 are passed as their bit pattern here as only the address of these functions
+    // is used.
+    fn __extendhfsf2(a: u16) -> f32;
+    fn __truncsfhf2(a: f32) -> u16;
+    fn __truncdfhf2(a: f64) -> u16;
+    fn __trunctfhf2(a: u128) -> u16;
+    fn __extendsftf2(a: f32) -> u128;
+    fn __extenddftf2(a: f64) -> u128;
+    fn __trunctfsf2(a: u128) -> f32;
+    fn __trunctfdf2(a: u128) -> f64;
+    fn __floatsitf(i: i32) -> u128;
+    fn __floatditf(i: i64) -> u128;
+    fn __floattitf(i: i128) -> u128;
+    fn __floatunsitf(i: u32) -> u128;
+    fn __floatunditf(i: u64) -> u128;
+    fn __floatuntitf(i: u128) -> u128;
+    fn __fixtfsi(f: u128) -> i32;
+    fn __fixtfdi(f: u128) -> i64;
+    fn __fixtfti(f: u128) -> i128;
+    fn __fixunstfsi(f: u128) -> u32;
+    fn __fixunstfdi(f: u128) -> u64;
+    fn __fixunstfti(f: u128) -> u128;
 
     // allocator
     // NOTE: These need to be mentioned here despite not being part of compiler_builtins because
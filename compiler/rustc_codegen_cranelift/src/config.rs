@@ -1,4 +1,5 @@
 use std::env;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 fn bool_env_var(key: &str) -> bool {
@@ -57,6 +58,43 @@ pub struct BackendConfig {
     /// Defaults to true when the `CG_CLIF_DISABLE_INCR_CACHE` env var is set to 1 or false
     /// otherwise. Can be set using `-Cllvm-args=disable_incr_cache=...`.
     pub disable_incr_cache: bool,
+
+    /// Codegen every codegen unit twice and check that both copies result in identical object
+    /// files. Useful to find sources of non-determinism like iteration over hash maps.
+    ///
+    /// Defaults to true when the `CG_CLIF_VERIFY_DETERMINISTIC_CODEGEN` env var is set to 1 or
+    /// false otherwise. Can be set using `-Cllvm-args=verify_deterministic_codegen=...`.
+    pub verify_deterministic_codegen: bool,
+
+    /// Cranelift settings to set after all settings chosen by cg_clif, given as `name=value`
+    /// pairs. Both shared and target specific settings are accepted. Useful for experimenting
+    /// with Cranelift settings and for bisecting miscompilations.
+    ///
+    /// Defaults to none. Can be set using `-Cllvm-args=cranelift_flags=name=value,name2=value2`.
+    pub cranelift_flags: Vec<(String, String)>,
+
+    /// Directory to write the final clif ir of every compiled function to, one
+    /// `<symbol name>.clif` file per function. The ir is annotated with comments mapping it back
+    /// to the MIR it was generated from. Functions in codegen units that are reused from the
+    /// incremental cache are not written.
+    ///
+    /// Defaults to none. Can be set using `-Cllvm-args=emit_clif=<dir>`.
+    pub emit_clif: Option<PathBuf>,
+
+    /// Measure how long Cranelift takes to compile every function and print the given number of
+    /// slowest functions once all codegen units have been compiled. Functions in codegen units
+    /// that are reused from the incremental cache are not counted.
+    ///
+    /// Defaults to none. Can be set using `-Cllvm-args=timings=<n>`.
+    pub timings: Option<usize>,
+
+    /// Intrinsics to codegen as if cg_clif didn't implement them. Their fallback body is used if
+    /// they have one and an error is emitted otherwise. Only meant for testing the handling of
+    /// intrinsics cg_clif doesn't implement.
+    ///
+    /// Defaults to the comma separated list of intrinsic names in the
+    /// `CG_CLIF_UNSUPPORTED_INTRINSICS` env var.
+    pub unsupported_intrinsics: Vec<String>,
 }
 
 impl Default for BackendConfig {
@@ -74,6 +112,14 @@ fn default() -> Self {
             },
             enable_verifier: cfg!(debug_assertions) || bool_env_var("CG_CLIF_ENABLE_VERIFIER"),
             disable_incr_cache: bool_env_var("CG_CLIF_DISABLE_INCR_CACHE"),
+            verify_deterministic_codegen: bool_env_var("CG_CLIF_VERIFY_DETERMINISTIC_CODEGEN"),
+            cranelift_flags: vec![],
+            emit_clif: None,
+            timings: None,
+            unsupported_intrinsics: match env::var("CG_CLIF_UNSUPPORTED_INTRINSICS") {
+                Ok(names) => names.split(',').map(|name| name.to_owned()).collect(),
+                Err(_) => vec![],
+            },
         }
     }
 }
@@ -97,6 +143,28 @@ fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
                     "mode" => config.codegen_mode = value.parse()?,
                     "enable_verifier" => config.enable_verifier = parse_bool(name, value)?,
                     "disable_incr_cache" => config.disable_incr_cache = parse_bool(name, value)?,
+                    "verify_deterministic_codegen" => {
+                        config.verify_deterministic_codegen = parse_bool(name, value)?
+                    }
+                    "emit_clif" => config.emit_clif = Some(PathBuf::from(value)),
+                    "timings" => {
+                        config.timings = Some(value.parse().map_err(|_| {
+                            format!("failed to parse value `{}` for {}", value, name)
+                        })?)
+                    }
+                    "cranelift_flags" => {
+                        for flag in value.split(',') {
+                            let Some((flag_name, flag_value)) = flag.split_once('=') else {
+                                return Err(format!(
+                                    "Invalid Cranelift flag `{}`, expected `name=value`",
+                                    flag
+                                ));
+                            };
+                            config
+                                .cranelift_flags
+                                .push((flag_name.to_owned(), flag_value.to_owned()));
+                        }
+                    }
                     _ => return Err(format!("Unknown option `{}`", name)),
                 }
             } else {
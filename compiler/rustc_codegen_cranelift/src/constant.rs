@@ -4,6 +4,7 @@
 
 use cranelift_module::*;
 use rustc_data_structures::fx::FxHashSet;
+use rustc_data_structures::stable_hasher::{Hash128, HashStable, StableHasher};
 use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
 use rustc_middle::mir::interpret::{read_target_uint, AllocId, GlobalAlloc, Scalar};
 use rustc_middle::ty::{Binder, ExistentialTraitRef, ScalarInt};
@@ -13,6 +14,9 @@
 pub(crate) struct ConstantCx {
     todo: Vec<TodoItem>,
     anon_allocs: FxHashMap<AllocId, DataId>,
+    /// Data objects named after the hash of their contents. These may already have been defined
+    /// by another function in the same codegen unit.
+    deduplicated: FxHashSet<DataId>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -23,7 +27,11 @@ enum TodoItem {
 
 impl ConstantCx {
     pub(crate) fn new() -> Self {
-        ConstantCx { todo: vec![], anon_allocs: FxHashMap::default() }
+        ConstantCx {
+            todo: vec![],
+            anon_allocs: FxHashMap::default(),
+            deduplicated: FxHashSet::default(),
+        }
     }
 
     pub(crate) fn finalize(mut self, tcx: TyCtxt<'_>, module: &mut dyn Module) {
@@ -142,6 +150,7 @@ pub(crate) fn codegen_const_value<'tcx>(
                             fx.bcx.ins().iconst(fx.pointer_type, alloc.inner().align.bytes() as i64)
                         } else {
                             let data_id = data_id_for_alloc_id(
+                                fx.tcx,
                                 &mut fx.constants_cx,
                                 fx.module,
                                 alloc_id,
@@ -215,8 +224,13 @@ fn pointer_for_allocation<'tcx>(
     alloc_id: AllocId,
 ) -> crate::pointer::Pointer {
     let alloc = fx.tcx.global_alloc(alloc_id).unwrap_memory();
-    let data_id =
-        data_id_for_alloc_id(&mut fx.constants_cx, fx.module, alloc_id, alloc.inner().mutability);
+    let data_id = data_id_for_alloc_id(
+        fx.tcx,
+        &mut fx.constants_cx,
+        fx.module,
+        alloc_id,
+        alloc.inner().mutability,
+    );
 
     let local_data_id = fx.module.declare_data_in_func(data_id, &mut fx.bcx.func);
     if fx.clif_comments.enabled() {
@@ -227,15 +241,47 @@ fn pointer_for_allocation<'tcx>(
 }
 
 pub(crate) fn data_id_for_alloc_id(
+    tcx: TyCtxt<'_>,
     cx: &mut ConstantCx,
     module: &mut dyn Module,
     alloc_id: AllocId,
     mutability: rustc_hir::Mutability,
 ) -> DataId {
     cx.todo.push(TodoItem::Alloc(alloc_id));
-    *cx.anon_allocs
-        .entry(alloc_id)
-        .or_insert_with(|| module.declare_anonymous_data(mutability.is_mut(), false).unwrap())
+    if let Some(&data_id) = cx.anon_allocs.get(&alloc_id) {
+        return data_id;
+    }
+    let data_id = if let Some(name) = dedup_symbol_name_for_alloc(tcx, alloc_id) {
+        let data_id = module.declare_data(&name, Linkage::Local, false, false).unwrap();
+        cx.deduplicated.insert(data_id);
+        data_id
+    } else {
+        module.declare_anonymous_data(mutability.is_mut(), false).unwrap()
+    };
+    cx.anon_allocs.insert(alloc_id, data_id);
+    data_id
+}
+
+/// Returns a symbol name derived from the contents of the allocation if it is safe to merge it
+/// with other allocations with identical contents.
+///
+/// Only immutable allocations without any pointers are merged. Mutable allocations have an
+/// identity that can be observed and allocations containing pointers may need relocations that
+/// differ depending on which function references them.
+fn dedup_symbol_name_for_alloc(tcx: TyCtxt<'_>, alloc_id: AllocId) -> Option<String> {
+    let GlobalAlloc::Memory(alloc) = tcx.global_alloc(alloc_id) else {
+        return None;
+    };
+    let alloc = alloc.inner();
+    if alloc.mutability.is_mut() || !alloc.provenance().ptrs().is_empty() {
+        return None;
+    }
+    let hash = tcx.with_stable_hashing_context(|mut hcx| {
+        let mut hasher = StableHasher::new();
+        alloc.hash_stable(&mut hcx, &mut hasher);
+        hasher.finish::<Hash128>()
+    });
+    Some(format!("alloc_{hash:032x}"))
 }
 
 pub(crate) fn data_id_for_vtable<'tcx>(
@@ -246,7 +292,7 @@ pub(crate) fn data_id_for_vtable<'tcx>(
     trait_ref: Option<Binder<'tcx, ExistentialTraitRef<'tcx>>>,
 ) -> DataId {
     let alloc_id = tcx.vtable_allocation((ty, trait_ref));
-    data_id_for_alloc_id(cx, module, alloc_id, Mutability::Not)
+    data_id_for_alloc_id(tcx, cx, module, alloc_id, Mutability::Not)
 }
 
 fn data_id_for_static(
@@ -357,14 +403,21 @@ fn define_all_allocs(tcx: TyCtxt<'_>, module: &mut dyn Module, cx: &mut Constant
                         unreachable!()
                     }
                 };
-                // FIXME: should we have a cache so we don't do this multiple times for the same `ConstAllocation`?
-                let data_id = *cx.anon_allocs.entry(alloc_id).or_insert_with(|| {
-                    module.declare_anonymous_data(alloc.inner().mutability.is_mut(), false).unwrap()
-                });
+                // Allocations are only pushed by `data_id_for_alloc_id`, which declares them.
+                let data_id = cx.anon_allocs[&alloc_id];
                 (data_id, alloc, None)
             }
             TodoItem::Static(def_id) => {
-                let section_name = tcx.codegen_fn_attrs(def_id).link_section;
+                let attrs = tcx.codegen_fn_attrs(def_id);
+                let section_name = attrs.link_section;
+                if section_name.is_some() && attrs.flags.contains(CodegenFnAttrFlags::THREAD_LOCAL)
+                {
+                    // Cranelift places TLS data in the TLS sections it picks itself.
+                    tcx.dcx().span_fatal(
+                        tcx.def_span(def_id),
+                        "#[link_section] is not yet supported for thread local statics",
+                    );
+                }
 
                 let alloc = tcx.eval_static_initializer(def_id).unwrap();
 
@@ -454,7 +507,7 @@ fn define_all_allocs(tcx: TyCtxt<'_>, module: &mut dyn Module, cx: &mut Constant
                     continue;
                 }
                 GlobalAlloc::Memory(target_alloc) => {
-                    data_id_for_alloc_id(cx, module, alloc_id, target_alloc.inner().mutability)
+                    data_id_for_alloc_id(tcx, cx, module, alloc_id, target_alloc.inner().mutability)
                 }
                 GlobalAlloc::VTable(ty, trait_ref) => {
                     data_id_for_vtable(tcx, cx, module, ty, trait_ref)
@@ -483,7 +536,11 @@ fn define_all_allocs(tcx: TyCtxt<'_>, module: &mut dyn Module, cx: &mut Constant
             data.write_data_addr(offset.bytes() as u32, global_value, addend as i64);
         }
 
-        module.define_data(data_id, &data).unwrap();
+        match module.define_data(data_id, &data) {
+            // Another function in this codegen unit already defined an identical allocation.
+            Err(ModuleError::DuplicateDefinition(_)) if cx.deduplicated.contains(&data_id) => {}
+            res => res.unwrap(),
+        }
     }
 
     assert!(cx.todo.is_empty(), "{:?}", cx.todo);
@@ -0,0 +1,157 @@
+//! Inlined subroutine generation (`DW_TAG_inlined_subroutine`)
+
+use cranelift_codegen::MachSrcLoc;
+use gimli::write::{AttributeValue, Range, RangeList, UnitEntryId};
+
+use crate::debuginfo::emit::address_for_func_offset;
+use crate::debuginfo::{function_name, DebugContext, FunctionDebugContext};
+use crate::prelude::*;
+
+/// A function body inlined by the MIR inliner.
+pub(super) struct InlinedScope {
+    entry_id: UnitEntryId,
+    /// The innermost inlined scope containing the call site, if any.
+    parent: Option<SourceScope>,
+    /// Code ranges relative to the start of the function.
+    ranges: Vec<(u32, u32)>,
+}
+
+impl DebugContext {
+    /// Get the abstract `DW_TAG_subprogram` that inlined copies of `instance` refer to.
+    fn inlined_subprogram<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        instance: Instance<'tcx>,
+    ) -> UnitEntryId {
+        let linkage_name = tcx.symbol_name(instance).name;
+        if let Some(&entry_id) = self.inlined_subprograms.get(linkage_name) {
+            return entry_id;
+        }
+
+        let scope = self.item_namespace(tcx, tcx.parent(instance.def_id()));
+        let span = tcx.def_span(instance.def_id());
+        let (file_id, line, _column) = self.get_span_loc(tcx, span, span);
+
+        let name = function_name(tcx, instance);
+        let linkage_name_id =
+            if name != linkage_name { Some(self.dwarf.strings.add(linkage_name)) } else { None };
+        let name_id = self.dwarf.strings.add(name);
+
+        let entry_id = self.dwarf.unit.add(scope, gimli::DW_TAG_subprogram);
+        let entry = self.dwarf.unit.get_mut(entry_id);
+        if let Some(linkage_name_id) = linkage_name_id {
+            entry.set(gimli::DW_AT_linkage_name, AttributeValue::StringRef(linkage_name_id));
+        }
+        entry.set(gimli::DW_AT_name, AttributeValue::StringRef(name_id));
+        entry.set(gimli::DW_AT_decl_file, AttributeValue::FileIndex(Some(file_id)));
+        entry.set(gimli::DW_AT_decl_line, AttributeValue::Udata(line));
+        entry.set(gimli::DW_AT_inline, AttributeValue::Inline(gimli::DW_INL_inlined));
+
+        self.inlined_subprograms.insert(linkage_name.to_owned(), entry_id);
+        entry_id
+    }
+}
+
+impl FunctionDebugContext {
+    /// Define the `DW_TAG_inlined_subroutine` for the innermost inlined function body containing
+    /// `scope` as well as for all inlined function bodies it is nested in.
+    ///
+    /// Returns the root scope of the innermost inlined function body, or `None` if `scope` is
+    /// not part of an inlined function body.
+    pub(crate) fn define_inlined_scope<'tcx>(
+        &mut self,
+        debug_context: &mut DebugContext,
+        tcx: TyCtxt<'tcx>,
+        instance: Instance<'tcx>,
+        mir: &Body<'tcx>,
+        scope: SourceScope,
+    ) -> Option<SourceScope> {
+        let scope_data = &mir.source_scopes[scope];
+        let inlined_scope =
+            if scope_data.inlined.is_some() { scope } else { scope_data.inlined_parent_scope? };
+        if self.inlined_scopes.contains_key(&inlined_scope) {
+            return Some(inlined_scope);
+        }
+
+        let inlined_scope_data = &mir.source_scopes[inlined_scope];
+        let (callee, call_span) = inlined_scope_data.inlined.unwrap();
+        let callee = instance.instantiate_mir_and_normalize_erasing_regions(
+            tcx,
+            ty::ParamEnv::reveal_all(),
+            ty::EarlyBinder::bind(callee),
+        );
+
+        let parent = self.define_inlined_scope(
+            debug_context,
+            tcx,
+            instance,
+            mir,
+            inlined_scope_data.parent_scope.unwrap(),
+        );
+        let parent_entry_id = match parent {
+            Some(parent) => self.inlined_scopes[&parent].entry_id,
+            None => self.entry_id,
+        };
+
+        let abstract_origin = debug_context.inlined_subprogram(tcx, callee);
+        let (file_id, line, column) = debug_context.get_span_loc(tcx, mir.span, call_span);
+
+        let entry_id =
+            debug_context.dwarf.unit.add(parent_entry_id, gimli::DW_TAG_inlined_subroutine);
+        let entry = debug_context.dwarf.unit.get_mut(entry_id);
+        entry.set(gimli::DW_AT_abstract_origin, AttributeValue::UnitRef(abstract_origin));
+        entry.set(gimli::DW_AT_call_file, AttributeValue::FileIndex(Some(file_id)));
+        entry.set(gimli::DW_AT_call_line, AttributeValue::Udata(line));
+        entry.set(gimli::DW_AT_call_column, AttributeValue::Udata(column));
+
+        self.inlined_scopes
+            .insert(inlined_scope, InlinedScope { entry_id, parent, ranges: vec![] });
+        Some(inlined_scope)
+    }
+
+    pub(super) fn create_inlined_scope_ranges(
+        &mut self,
+        debug_context: &mut DebugContext,
+        func_id: FuncId,
+        context: &Context,
+    ) {
+        if self.inlined_scopes.is_empty() {
+            return;
+        }
+
+        let mcr = context.compiled_code().unwrap();
+        for &MachSrcLoc { start, end, loc } in mcr.buffer.get_srclocs_sorted() {
+            if loc.is_default() || start == end {
+                continue;
+            }
+
+            // Code belonging to an inlined function body is also part of every inlined function
+            // body it is nested in.
+            let mut inlined_scope = self.source_loc_set[loc.bits() as usize].1;
+            while let Some(scope) = inlined_scope {
+                let inlined_scope_data = &mut self.inlined_scopes[&scope];
+                match inlined_scope_data.ranges.last_mut() {
+                    Some((_, range_end)) if *range_end == start => *range_end = end,
+                    _ => inlined_scope_data.ranges.push((start, end)),
+                }
+                inlined_scope = inlined_scope_data.parent;
+            }
+        }
+
+        for inlined_scope_data in self.inlined_scopes.values() {
+            let range_list = RangeList(
+                inlined_scope_data
+                    .ranges
+                    .iter()
+                    .map(|&(start, end)| Range::StartLength {
+                        begin: address_for_func_offset(func_id, start),
+                        length: u64::from(end - start),
+                    })
+                    .collect(),
+            );
+            let range_list_id = debug_context.dwarf.unit.ranges.add(range_list);
+            let entry = debug_context.dwarf.unit.get_mut(inlined_scope_data.entry_id);
+            entry.set(gimli::DW_AT_ranges, AttributeValue::RangeListRef(range_list_id));
+        }
+    }
+}
@@ -69,9 +69,18 @@ pub(crate) fn get_span_loc(
             Ok(SourceFileAndLine { sf: file, line }) => {
                 let file_id = self.add_source_file(&file);
                 let line_pos = file.lines()[line];
-                let col = file.relative_position(span.lo()) - line_pos;
-
-                (file_id, u64::try_from(line).unwrap() + 1, u64::from(col.to_u32()) + 1)
+                // Spans from macro expansions that weren't collapsed into the call site may point
+                // into the macro definition or be synthesized by a proc macro, in which case the
+                // column doesn't correspond to anything the user wrote. Use column 0 (meaning
+                // "unknown column") for those.
+                let col = if span.from_expansion() && span.ctxt() != function_span.ctxt() {
+                    0
+                } else {
+                    let col = file.relative_position(span.lo()) - line_pos;
+                    u64::from(col.to_u32()) + 1
+                };
+
+                (file_id, u64::try_from(line).unwrap() + 1, col)
             }
             Err(file) => (self.add_source_file(&file), 0, 0),
         }
@@ -120,8 +129,14 @@ pub(crate) fn add_source_file(&mut self, source_file: &SourceFile) -> FileId {
 }
 
 impl FunctionDebugContext {
-    pub(crate) fn add_dbg_loc(&mut self, file_id: FileId, line: u64, column: u64) -> SourceLoc {
-        let (index, _) = self.source_loc_set.insert_full((file_id, line, column));
+    pub(crate) fn add_dbg_loc(
+        &mut self,
+        file_id: FileId,
+        line: u64,
+        column: u64,
+        inlined_scope: Option<SourceScope>,
+    ) -> SourceLoc {
+        let (index, _) = self.source_loc_set.insert_full(((file_id, line, column), inlined_scope));
         SourceLoc::new(u32::try_from(index).unwrap())
     }
 
@@ -149,7 +164,7 @@ pub(super) fn create_debug_lines(
         for &MachSrcLoc { start, end, loc } in mcr.buffer.get_srclocs_sorted() {
             debug_context.dwarf.unit.line_program.row().address_offset = u64::from(start);
             if !loc.is_default() {
-                let source_loc = self.source_loc_set[loc.bits() as usize];
+                let (source_loc, _inlined_scope) = self.source_loc_set[loc.bits() as usize];
                 create_row_for_span(debug_context, source_loc);
             } else {
                 create_row_for_span(debug_context, self.function_source_loc);
@@ -1,17 +1,20 @@
 //! Handling of everything related to debuginfo.
 
 mod emit;
+mod inline;
 mod line_info;
 mod object;
 mod types;
 mod unwind;
 
-use cranelift_codegen::ir::Endianness;
+use cranelift_codegen::ir::{Endianness, ValueLabel};
 use cranelift_codegen::isa::TargetIsa;
+use cranelift_codegen::LabelValueLoc;
+use cranelift_frontend::Variable;
 use cranelift_module::DataId;
 use gimli::write::{
-    Address, AttributeValue, DwarfUnit, Expression, FileId, LineProgram, LineString, Range,
-    RangeList, UnitEntryId,
+    Address, AttributeValue, DwarfUnit, Expression, FileId, LineProgram, LineString, Location,
+    LocationList, Range, RangeList, UnitEntryId,
 };
 use gimli::{AArch64, Encoding, Format, LineEncoding, Register, RiscV, RunTimeEndian, X86_64};
 use indexmap::IndexSet;
@@ -25,7 +28,7 @@
 pub(crate) use self::emit::{DebugReloc, DebugRelocName};
 pub(crate) use self::types::TypeDebugContext;
 pub(crate) use self::unwind::UnwindContext;
-use crate::debuginfo::emit::{address_for_data, address_for_func};
+use crate::debuginfo::emit::{address_for_data, address_for_func, address_for_func_offset};
 use crate::prelude::*;
 
 pub(crate) fn producer(sess: &Session) -> String {
@@ -41,6 +44,8 @@ pub(crate) struct DebugContext {
     stack_pointer_register: Register,
     namespace_map: DefIdMap<UnitEntryId>,
     array_size_type: UnitEntryId,
+    /// Abstract `DW_TAG_subprogram`s referenced by inlined subroutines, keyed by symbol name.
+    inlined_subprograms: FxHashMap<String, UnitEntryId>,
 
     filename_display_preference: FileNameDisplayPreference,
 }
@@ -48,7 +53,11 @@ pub(crate) struct DebugContext {
 pub(crate) struct FunctionDebugContext {
     entry_id: UnitEntryId,
     function_source_loc: (FileId, u64, u64),
-    source_loc_set: IndexSet<(FileId, u64, u64)>,
+    source_loc_set: IndexSet<((FileId, u64, u64), Option<SourceScope>)>,
+    inlined_scopes: FxIndexMap<SourceScope, self::inline::InlinedScope>,
+    /// Variable DIEs whose `DW_AT_location` is derived from the value label ranges of the
+    /// corresponding SSA variable once the function has been compiled.
+    local_vars: Vec<(UnitEntryId, ValueLabel)>,
 }
 
 impl DebugContext {
@@ -76,6 +85,9 @@ pub(crate) fn new(tcx: TyCtxt<'_>, isa: &dyn TargetIsa, cgu_name: &str) -> Self
         let stack_pointer_register = match isa.triple().architecture {
             target_lexicon::Architecture::Aarch64(_) => AArch64::SP,
             target_lexicon::Architecture::Riscv64(_) => RiscV::SP,
+            // gimli doesn't define the s390x register set. %r15 is the stack pointer and maps to
+            // DWARF register 15.
+            target_lexicon::Architecture::S390x => Register(15),
             target_lexicon::Architecture::X86_64 | target_lexicon::Architecture::X86_64h => {
                 X86_64::RSP
             }
@@ -152,6 +164,7 @@ pub(crate) fn new(tcx: TyCtxt<'_>, isa: &dyn TargetIsa, cgu_name: &str) -> Self
             stack_pointer_register,
             namespace_map: DefIdMap::default(),
             array_size_type,
+            inlined_subprograms: FxHashMap::default(),
             filename_display_preference,
         }
     }
@@ -195,24 +208,7 @@ pub(crate) fn define_function<'tcx>(
 
         let scope = self.item_namespace(tcx, tcx.parent(instance.def_id()));
 
-        let mut name = String::new();
-        type_names::push_item_name(tcx, instance.def_id(), false, &mut name);
-
-        // Find the enclosing function, in case this is a closure.
-        let enclosing_fn_def_id = tcx.typeck_root_def_id(instance.def_id());
-
-        // We look up the generics of the enclosing function and truncate the args
-        // to their length in order to cut off extra stuff that might be in there for
-        // closures or coroutines.
-        let generics = tcx.generics_of(enclosing_fn_def_id);
-        let args = instance.args.truncate_to(tcx, generics);
-
-        type_names::push_generic_params(
-            tcx,
-            tcx.normalize_erasing_regions(ty::ParamEnv::reveal_all(), args),
-            enclosing_fn_def_id,
-            &mut name,
-        );
+        let name = function_name(tcx, instance);
 
         let entry_id = self.dwarf.unit.add(scope, gimli::DW_TAG_subprogram);
         let entry = self.dwarf.unit.get_mut(entry_id);
@@ -254,6 +250,8 @@ pub(crate) fn define_function<'tcx>(
             entry_id,
             function_source_loc: (file_id, line, column),
             source_loc_set: IndexSet::new(),
+            inlined_scopes: FxIndexMap::default(),
+            local_vars: Vec::new(),
         }
     }
 
@@ -314,14 +312,98 @@ pub(crate) fn define_static<'tcx>(
     }
 }
 
+fn function_name<'tcx>(tcx: TyCtxt<'tcx>, instance: Instance<'tcx>) -> String {
+    let mut name = String::new();
+    type_names::push_item_name(tcx, instance.def_id(), false, &mut name);
+
+    // Find the enclosing function, in case this is a closure.
+    let enclosing_fn_def_id = tcx.typeck_root_def_id(instance.def_id());
+
+    // We look up the generics of the enclosing function and truncate the args
+    // to their length in order to cut off extra stuff that might be in there for
+    // closures or coroutines.
+    let generics = tcx.generics_of(enclosing_fn_def_id);
+    let args = instance.args.truncate_to(tcx, generics);
+
+    type_names::push_generic_params(
+        tcx,
+        tcx.normalize_erasing_regions(ty::ParamEnv::reveal_all(), args),
+        enclosing_fn_def_id,
+        &mut name,
+    );
+
+    name
+}
+
 impl FunctionDebugContext {
+    /// Define a user variable stored in the SSA variable `var`. Its location is filled in by
+    /// [`finalize`](Self::finalize) based on where the register allocator placed it.
+    pub(crate) fn define_local<'tcx>(
+        &mut self,
+        debug_context: &mut DebugContext,
+        tcx: TyCtxt<'tcx>,
+        type_dbg: &mut TypeDebugContext<'tcx>,
+        function_span: Span,
+        var_debug_info: &VarDebugInfo<'tcx>,
+        ty: Ty<'tcx>,
+        var: Variable,
+    ) {
+        let (file_id, line, _column) =
+            debug_context.get_span_loc(tcx, function_span, var_debug_info.source_info.span);
+        let dw_ty = debug_context.debug_type(tcx, type_dbg, ty);
+
+        let tag = if var_debug_info.argument_index.is_some() {
+            gimli::DW_TAG_formal_parameter
+        } else {
+            gimli::DW_TAG_variable
+        };
+        let name_id = debug_context.dwarf.strings.add(var_debug_info.name.as_str());
+
+        let entry_id = debug_context.dwarf.unit.add(self.entry_id, tag);
+        let entry = debug_context.dwarf.unit.get_mut(entry_id);
+        entry.set(gimli::DW_AT_name, AttributeValue::StringRef(name_id));
+        entry.set(gimli::DW_AT_decl_file, AttributeValue::FileIndex(Some(file_id)));
+        entry.set(gimli::DW_AT_decl_line, AttributeValue::Udata(line));
+        entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(dw_ty));
+
+        self.local_vars.push((entry_id, ValueLabel::from_u32(var.as_u32())));
+    }
+
     pub(crate) fn finalize(
         mut self,
         debug_context: &mut DebugContext,
         func_id: FuncId,
         context: &Context,
+        isa: &dyn TargetIsa,
     ) {
         let end = self.create_debug_lines(debug_context, func_id, context);
+        self.create_inlined_scope_ranges(debug_context, func_id, context);
+
+        let value_labels_ranges = &context.compiled_code().unwrap().value_labels_ranges;
+        for &(entry_id, label) in &self.local_vars {
+            let Some(ranges) = value_labels_ranges.get(&label) else {
+                // The variable has been optimized out.
+                continue;
+            };
+            let locations = ranges
+                .iter()
+                .filter(|range| range.start < range.end)
+                .filter_map(|range| {
+                    Some(Location::StartLength {
+                        begin: address_for_func_offset(func_id, range.start),
+                        length: u64::from(range.end - range.start),
+                        data: translate_loc(isa, range.loc)?,
+                    })
+                })
+                .collect::<Vec<_>>();
+            if locations.is_empty() {
+                continue;
+            }
+
+            let loc_list_id = debug_context.dwarf.unit.locations.add(LocationList(locations));
+            let entry = debug_context.dwarf.unit.get_mut(entry_id);
+            entry.set(gimli::DW_AT_location, AttributeValue::LocationListRef(loc_list_id));
+        }
 
         debug_context
             .unit_range_list
@@ -335,3 +417,22 @@ pub(crate) fn finalize(
         func_entry.set(gimli::DW_AT_high_pc, AttributeValue::Udata(u64::from(end)));
     }
 }
+
+/// Translate the location of a value as chosen by the register allocator into a DWARF expression.
+fn translate_loc(isa: &dyn TargetIsa, loc: LabelValueLoc) -> Option<Expression> {
+    match loc {
+        LabelValueLoc::Reg(reg) => {
+            let machine_reg = isa.map_regalloc_reg_to_dwarf(reg).ok()?;
+            let mut expr = Expression::new();
+            expr.op_reg(Register(machine_reg));
+            Some(expr)
+        }
+        LabelValueLoc::CFAOffset(offset) => {
+            let mut expr = Expression::new();
+            expr.op(gimli::DW_OP_call_frame_cfa);
+            expr.op_consts(offset);
+            expr.op(gimli::DW_OP_plus);
+            Some(expr)
+        }
+    }
+}
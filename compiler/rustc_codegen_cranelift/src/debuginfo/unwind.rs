@@ -1,10 +1,12 @@
-//! Unwind info generation (`.eh_frame`)
+//! Unwind info generation (`.eh_frame` and `.pdata`/`.xdata`)
 
 use cranelift_codegen::ir::Endianness;
 use cranelift_codegen::isa::{unwind::UnwindInfo, TargetIsa};
 use cranelift_object::ObjectProduct;
 use gimli::write::{CieId, EhFrame, FrameTable, Section};
 use gimli::RunTimeEndian;
+use object::write::{Relocation, StandardSegment};
+use object::{RelocationEncoding, RelocationFlags, RelocationKind, SectionKind};
 
 use super::emit::address_for_func;
 use super::object::WriteDebugInfo;
@@ -14,6 +16,8 @@ pub(crate) struct UnwindContext {
     endian: RunTimeEndian,
     frame_table: FrameTable,
     cie_id: Option<CieId>,
+    /// Encoded `UNWIND_INFO` structures together with the function and code size they belong to.
+    windows_unwind_info: Vec<(FuncId, u32, Vec<u8>)>,
 }
 
 impl UnwindContext {
@@ -34,7 +38,7 @@ pub(crate) fn new(isa: &dyn TargetIsa, pic_eh_frame: bool) -> Self {
             None
         };
 
-        UnwindContext { endian, frame_table, cie_id }
+        UnwindContext { endian, frame_table, cie_id, windows_unwind_info: vec![] }
     }
 
     pub(crate) fn add_function(&mut self, func_id: FuncId, context: &Context, isa: &dyn TargetIsa) {
@@ -46,8 +50,8 @@ pub(crate) fn add_function(&mut self, func_id: FuncId, context: &Context, isa: &
             return;
         }
 
-        let unwind_info = if let Some(unwind_info) =
-            context.compiled_code().unwrap().create_unwind_info(isa).unwrap()
+        let compiled_code = context.compiled_code().unwrap();
+        let unwind_info = if let Some(unwind_info) = compiled_code.create_unwind_info(isa).unwrap()
         {
             unwind_info
         } else {
@@ -55,12 +59,17 @@ pub(crate) fn add_function(&mut self, func_id: FuncId, context: &Context, isa: &
         };
 
         match unwind_info {
+            // Cranelift already maps its registers to the DWARF register numbers of the target
+            // architecture (x86_64, aarch64, riscv64 and s390x) when producing SystemV unwind info.
             UnwindInfo::SystemV(unwind_info) => {
                 self.frame_table
                     .add_fde(self.cie_id.unwrap(), unwind_info.to_fde(address_for_func(func_id)));
             }
-            UnwindInfo::WindowsX64(_) => {
-                // FIXME implement this
+            UnwindInfo::WindowsX64(unwind_info) => {
+                let code_size = compiled_code.code_info().total_size;
+                let mut data = vec![0; unwind_info.emit_size()];
+                unwind_info.emit(&mut data);
+                self.windows_unwind_info.push((func_id, code_size, data));
             }
             unwind_info => unimplemented!("{:?}", unwind_info),
         }
@@ -80,6 +89,10 @@ pub(crate) fn emit(self, product: &mut ObjectProduct) {
                 product.add_debug_reloc(&section_map, &section_id, reloc);
             }
         }
+
+        if !self.windows_unwind_info.is_empty() {
+            emit_windows_unwind_info(product, self.windows_unwind_info);
+        }
     }
 
     #[cfg(all(feature = "jit", windows))]
@@ -136,6 +149,62 @@ pub(crate) unsafe fn register_jit(self, jit_module: &cranelift_jit::JITModule) {
     }
 }
 
+/// Write the `UNWIND_INFO` structures to `.xdata` and a `RUNTIME_FUNCTION` entry pointing to the
+/// function and its `UNWIND_INFO` for every function to `.pdata`.
+fn emit_windows_unwind_info(
+    product: &mut ObjectProduct,
+    windows_unwind_info: Vec<(FuncId, u32, Vec<u8>)>,
+) {
+    let segment = product.object.segment_name(StandardSegment::Data).to_vec();
+    let xdata_section =
+        product.object.add_section(segment.clone(), b".xdata".to_vec(), SectionKind::ReadOnlyData);
+    let pdata_section =
+        product.object.add_section(segment, b".pdata".to_vec(), SectionKind::ReadOnlyData);
+    let xdata_symbol = product.object.section_symbol(xdata_section);
+
+    let mut xdata = vec![];
+    let mut pdata = vec![];
+    let mut relocs = vec![];
+    for (func_id, code_size, unwind_info) in windows_unwind_info {
+        // `UNWIND_INFO` structures must be 4 byte aligned.
+        while xdata.len() % 4 != 0 {
+            xdata.push(0);
+        }
+        let unwind_info_offset = xdata.len();
+        xdata.extend_from_slice(&unwind_info);
+
+        // A `RUNTIME_FUNCTION` consists of the begin and end address of the function and the
+        // address of the `UNWIND_INFO`, all relative to the image base.
+        let func_symbol = product.function_symbol(func_id);
+        let pdata_offset = pdata.len() as u64;
+        relocs.push((pdata_offset, func_symbol, 0));
+        relocs.push((pdata_offset + 4, func_symbol, i64::from(code_size)));
+        relocs.push((pdata_offset + 8, xdata_symbol, unwind_info_offset as i64));
+        pdata.extend_from_slice(&[0; 12]);
+    }
+
+    product.object.section_mut(xdata_section).set_data(xdata, 4);
+    product.object.section_mut(pdata_section).set_data(pdata, 4);
+    for (offset, symbol, addend) in relocs {
+        product
+            .object
+            .add_relocation(
+                pdata_section,
+                Relocation {
+                    offset,
+                    symbol,
+                    flags: RelocationFlags::Generic {
+                        kind: RelocationKind::ImageOffset,
+                        encoding: RelocationEncoding::Generic,
+                        size: 32,
+                    },
+                    addend,
+                },
+            )
+            .unwrap();
+    }
+}
+
 extern "C" {
     // libunwind import
     fn __register_frame(fde: *const u8);
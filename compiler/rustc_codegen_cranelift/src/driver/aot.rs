@@ -1,12 +1,15 @@
 //! The AOT driver uses [`cranelift_object`] to write object files suitable for linking into a
 //! standalone executable.
 
+use std::cmp;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
-use cranelift_object::{ObjectBuilder, ObjectModule};
+use cranelift_module::DataId;
+use cranelift_object::{ObjectBuilder, ObjectModule, ObjectProduct};
 use rustc_codegen_ssa::assert_module_sources::CguReuse;
 use rustc_codegen_ssa::back::link::ensure_removed;
 use rustc_codegen_ssa::back::metadata::create_compressed_metadata_file;
@@ -19,10 +22,12 @@
 use rustc_metadata::fs::copy_to_stdout;
 use rustc_metadata::EncodedMetadata;
 use rustc_middle::dep_graph::{WorkProduct, WorkProductId};
-use rustc_middle::mir::mono::{CodegenUnit, MonoItem};
-use rustc_session::config::{DebugInfo, OutFileName, OutputFilenames, OutputType};
+use rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
+use rustc_middle::mir::mono::{CodegenUnit, Linkage as RLinkage, MonoItem};
+use rustc_session::config::{DebugInfo, OutFileName, OutputFilenames, OutputType, OutputTypes};
 use rustc_session::Session;
 
+use crate::base::CodegenedFunction;
 use crate::concurrency_limiter::{ConcurrencyLimiter, ConcurrencyLimiterToken};
 use crate::debuginfo::TypeDebugContext;
 use crate::global_asm::GlobalAsmConfig;
@@ -33,6 +38,8 @@ struct ModuleCodegenResult {
     module_regular: CompiledModule,
     module_global_asm: Option<CompiledModule>,
     existing_work_product: Option<(WorkProductId, WorkProduct)>,
+    /// Compile time of every function in this codegen unit for `-Cllvm-args=timings`.
+    function_timings: Vec<(String, Duration)>,
 }
 
 enum OngoingModuleCodegen {
@@ -46,6 +53,32 @@ fn hash_stable(&self, _: &mut HCX, _: &mut StableHasher) {
     }
 }
 
+/// Compile times of the functions in all codegen units for `-Cllvm-args=timings`.
+#[derive(Default)]
+struct FunctionTimings {
+    timings: Vec<(String, Duration)>,
+}
+
+impl FunctionTimings {
+    /// Print the total compile time followed by the `top_n` slowest functions.
+    fn print_summary(mut self, top_n: usize) {
+        // Sort by name too to keep the output deterministic when times are equal.
+        self.timings.sort_by(|(a_name, a_time), (b_name, b_time)| {
+            b_time.cmp(a_time).then_with(|| a_name.cmp(b_name))
+        });
+
+        let total = self.timings.iter().map(|&(_, time)| time).sum::<Duration>();
+        eprintln!(
+            "cg_clif timings: compiled {} functions in {:.3}ms",
+            self.timings.len(),
+            total.as_secs_f64() * 1000.0,
+        );
+        for (name, time) in self.timings.iter().take(top_n) {
+            eprintln!("{:>12.3}ms  {}", time.as_secs_f64() * 1000.0, name);
+        }
+    }
+}
+
 pub(crate) struct OngoingCodegen {
     modules: Vec<OngoingModuleCodegen>,
     allocator_module: Option<CompiledModule>,
@@ -64,6 +97,7 @@ pub(crate) fn join(
     ) -> (CodegenResults, FxIndexMap<WorkProductId, WorkProduct>) {
         let mut work_products = FxIndexMap::default();
         let mut modules = vec![];
+        let mut function_timings = FunctionTimings::default();
 
         for module_codegen in self.modules {
             let module_codegen_result = match module_codegen {
@@ -78,8 +112,13 @@ pub(crate) fn join(
                 Ok(module_codegen_result) => module_codegen_result,
                 Err(err) => sess.dcx().fatal(err),
             };
-            let ModuleCodegenResult { module_regular, module_global_asm, existing_work_product } =
-                module_codegen_result;
+            let ModuleCodegenResult {
+                module_regular,
+                module_global_asm,
+                existing_work_product,
+                function_timings: module_function_timings,
+            } = module_codegen_result;
+            function_timings.timings.extend(module_function_timings);
 
             if let Some((work_product_id, work_product)) = existing_work_product {
                 work_products.insert(work_product_id, work_product);
@@ -115,6 +154,10 @@ pub(crate) fn join(
 
         self.concurrency_limiter.finished();
 
+        if let Some(top_n) = backend_config.timings {
+            function_timings.print_summary(top_n);
+        }
+
         sess.dcx().abort_if_errors();
 
         let codegen_results = CodegenResults {
@@ -214,8 +257,8 @@ fn produce_final_output_artifacts(
                 // copy_if_one_unit(OutputType::LlvmAssembly, false);
             }
             OutputType::Assembly => {
-                // Currently no support for emitting raw assembly files
-                // copy_if_one_unit(OutputType::Assembly, false);
+                // Contains the disassembly of the compiled functions
+                copy_if_one_unit(OutputType::Assembly, false);
             }
             OutputType::Object => {
                 user_wants_objects = true;
@@ -330,8 +373,11 @@ fn make_module(
         ObjectBuilder::new(isa, name + ".o", cranelift_module::default_libcall_names()).unwrap();
     // Unlike cg_llvm, cg_clif defaults to disabling -Zfunction-sections. For cg_llvm binary size
     // is important, while cg_clif cares more about compilation times. Enabling -Zfunction-sections
-    // can easily double the amount of time necessary to perform linking.
-    builder.per_function_section(sess.opts.unstable_opts.function_sections.unwrap_or(false));
+    // can easily double the amount of time necessary to perform linking. With -Clink-dead-code
+    // the linker doesn't GC unused sections, so separate sections wouldn't be of any use.
+    let function_sections =
+        sess.opts.unstable_opts.function_sections.unwrap_or(false) && !sess.link_dead_code();
+    builder.per_function_section(function_sections);
     UnwindModule::new(ObjectModule::new(builder), true)
 }
 
@@ -341,8 +387,11 @@ fn emit_cgu(
     name: String,
     module: UnwindModule<ObjectModule>,
     debug: Option<DebugContext>,
+    used_statics: &[DataId],
+    assembly: Option<String>,
     global_asm_object_file: Option<PathBuf>,
     producer: &str,
+    function_timings: Vec<(String, Duration)>,
 ) -> Result<ModuleCodegenResult, String> {
     let mut product = module.finish();
 
@@ -350,7 +399,9 @@ fn emit_cgu(
         debug.emit(&mut product);
     }
 
-    let module_regular = emit_module(
+    mark_used_statics_retained(&mut product, used_statics);
+
+    let mut module_regular = emit_module(
         output_filenames,
         prof,
         product.object,
@@ -359,6 +410,14 @@ fn emit_cgu(
         producer,
     )?;
 
+    if let Some(assembly) = assembly {
+        let asm_file = output_filenames.temp_path(OutputType::Assembly, Some(&name));
+        if let Err(err) = fs::write(&asm_file, assembly) {
+            return Err(format!("error writing assembly file: {}", err));
+        }
+        module_regular.assembly = Some(asm_file);
+    }
+
     Ok(ModuleCodegenResult {
         module_regular,
         module_global_asm: global_asm_object_file.map(|global_asm_object_file| CompiledModule {
@@ -371,17 +430,44 @@ fn emit_cgu(
             llvm_ir: None,
         }),
         existing_work_product: None,
+        function_timings,
     })
 }
 
-fn emit_module(
-    output_filenames: &OutputFilenames,
-    prof: &SelfProfilerRef,
-    mut object: cranelift_object::object::write::Object<'_>,
-    kind: ModuleKind,
-    name: String,
+/// Prevent the linker from garbage collecting statics marked `#[used(linker)]`.
+fn mark_used_statics_retained(product: &mut ObjectProduct, used_statics: &[DataId]) {
+    use cranelift_object::object::write::SymbolSection;
+    use cranelift_object::object::{elf, macho, BinaryFormat, SectionFlags, SymbolFlags};
+
+    for &data_id in used_statics {
+        let symbol_id = product.data_symbol(data_id);
+        match product.object.format() {
+            BinaryFormat::Elf => {
+                // Unless -Zfunction-sections is enabled, statics share their section with other
+                // data, in which case this retains the whole section.
+                let SymbolSection::Section(section_id) = product.object.symbol(symbol_id).section
+                else {
+                    continue;
+                };
+                if let SectionFlags::Elf { sh_flags } = product.object.section_flags_mut(section_id)
+                {
+                    *sh_flags |= u64::from(elf::SHF_GNU_RETAIN);
+                }
+            }
+            BinaryFormat::MachO => {
+                product.object.symbol_mut(symbol_id).flags =
+                    SymbolFlags::MachO { n_desc: macho::N_NO_DEAD_STRIP };
+            }
+            // FIXME pass /INCLUDE to the linker for COFF
+            _ => {}
+        }
+    }
+}
+
+fn add_producer_comment(
+    object: &mut cranelift_object::object::write::Object<'_>,
     producer_str: &str,
-) -> Result<CompiledModule, String> {
+) {
     if object.format() == cranelift_object::object::BinaryFormat::Elf {
         let comment_section = object.add_section(
             Vec::new(),
@@ -393,6 +479,17 @@ fn emit_module(
         producer.push(0);
         object.set_section_data(comment_section, producer, 1);
     }
+}
+
+fn emit_module(
+    output_filenames: &OutputFilenames,
+    prof: &SelfProfilerRef,
+    mut object: cranelift_object::object::write::Object<'_>,
+    kind: ModuleKind,
+    name: String,
+    producer_str: &str,
+) -> Result<CompiledModule, String> {
+    add_producer_comment(&mut object, producer_str);
 
     let tmp_file = output_filenames.temp_path(OutputType::Object, Some(&name));
     let mut file = match File::create(&tmp_file) {
@@ -475,9 +572,171 @@ fn reuse_workproduct_for_cgu(
             llvm_ir: None,
         }),
         existing_work_product: Some((cgu.work_product_id(), work_product)),
+        function_timings: vec![],
+    })
+}
+
+/// Codegen all mono items of a codegen unit without compiling them yet.
+///
+/// For the second copy of a codegen unit used by `verify_deterministic_codegen`,
+/// `is_verify_copy` disables writing clif ir, disassembly and function timings as the first copy
+/// already produces those.
+fn codegen_cgu_items(
+    tcx: TyCtxt<'_>,
+    backend_config: &BackendConfig,
+    cgu_name: rustc_span::Symbol,
+    is_verify_copy: bool,
+) -> (String, crate::CodegenCx, UnwindModule<ObjectModule>, Vec<CodegenedFunction>) {
+    let cgu = tcx.codegen_unit(cgu_name);
+    let mono_items = cgu.items_in_deterministic_order(tcx);
+
+    let mut module = make_module(tcx.sess, backend_config, cgu_name.as_str().to_string());
+
+    let mut cx = crate::CodegenCx::new(
+        tcx,
+        module.isa(),
+        tcx.sess.opts.debuginfo != DebugInfo::None,
+        cgu_name,
+    );
+    cx.unsupported_intrinsics = backend_config
+        .unsupported_intrinsics
+        .iter()
+        .map(|name| rustc_span::Symbol::intern(name))
+        .collect();
+    if is_verify_copy {
+        cx.should_write_ir = false;
+        cx.should_write_asm = false;
+    } else {
+        cx.clif_dump_dir = backend_config.emit_clif.clone();
+        if backend_config.timings.is_some() {
+            cx.function_timings = Some(vec![]);
+        }
+    }
+    let mut type_dbg = TypeDebugContext::default();
+    super::predefine_mono_items(tcx, &mut module, &mono_items);
+    let mut codegened_functions = vec![];
+    for (mono_item, data) in mono_items {
+        if data.linkage == RLinkage::AvailableExternally {
+            // Declared as import by `predefine_mono_items` and `data_id_for_static`.
+            continue;
+        }
+        match mono_item {
+            MonoItem::Fn(inst) => {
+                if let Some(codegened_function) = crate::base::codegen_fn(
+                    tcx,
+                    &mut cx,
+                    &mut type_dbg,
+                    Function::new(),
+                    &mut module,
+                    inst,
+                ) {
+                    codegened_functions.push(codegened_function);
+                }
+            }
+            MonoItem::Static(def_id) => {
+                let data_id = crate::constant::codegen_static(tcx, &mut module, def_id);
+                if let Some(debug_context) = &mut cx.debug_context {
+                    debug_context.define_static(tcx, &mut type_dbg, def_id, data_id);
+                }
+                // `#[used(compiler)]` is already satisfied as statics are never removed
+                // before emitting the object file.
+                if tcx.codegen_fn_attrs(def_id).flags.contains(CodegenFnAttrFlags::USED_LINKER) {
+                    cx.used_statics.push(data_id);
+                }
+            }
+            MonoItem::GlobalAsm(item_id) => {
+                crate::global_asm::codegen_global_asm_item(tcx, &mut cx, &mut module, item_id);
+            }
+        }
+    }
+    crate::main_shim::maybe_create_entry_wrapper(tcx, &mut module, false, cgu.is_primary());
+
+    let cgu_name = cgu.name().as_str().to_owned();
+
+    (cgu_name, cx, module, codegened_functions)
+}
+
+/// Compile all functions and the global assembly of a codegen unit and write the resulting object
+/// files to the temporary object paths of `global_asm_config.output_filenames`.
+fn compile_cgu(
+    mut cx: crate::CodegenCx,
+    mut module: UnwindModule<ObjectModule>,
+    codegened_functions: Vec<CodegenedFunction>,
+    global_asm_config: &GlobalAsmConfig,
+    cgu_name: String,
+    producer: &str,
+) -> Result<ModuleCodegenResult, String> {
+    cx.profiler.clone().generic_activity_with_arg("compile functions", &*cgu_name).run(|| {
+        cranelift_codegen::timing::set_thread_profiler(Box::new(super::MeasuremeProfiler(
+            cx.profiler.clone(),
+        )));
+
+        let mut cached_context = Context::new();
+        for codegened_func in codegened_functions {
+            crate::base::compile_fn(&mut cx, &mut cached_context, &mut module, codegened_func);
+        }
+    });
+
+    let global_asm_object_file =
+        cx.profiler.generic_activity_with_arg("compile assembly", &*cgu_name).run(|| {
+            crate::global_asm::compile_global_asm(global_asm_config, &cgu_name, &cx.global_asm)
+        })?;
+
+    let profiler = cx.profiler.clone();
+    profiler.generic_activity_with_arg("write object file", &*cgu_name).run(|| {
+        emit_cgu(
+            &global_asm_config.output_filenames,
+            &cx.profiler,
+            cgu_name.clone(),
+            module,
+            cx.debug_context,
+            &cx.used_statics,
+            cx.should_write_asm.then_some(cx.assembly),
+            global_asm_object_file,
+            producer,
+            cx.function_timings.unwrap_or_default(),
+        )
     })
 }
 
+/// Compile a single codegen unit into an object file.
+///
+/// This is meant for tools embedding the backend. Unlike the regular driver it compiles on the
+/// current thread and never reuses a codegen unit from the incremental cache. The object files are
+/// written to `out_dir` rather than to the paths used by the regular driver, so that they don't
+/// clash when the embedder continues with the regular codegen afterwards. Returns the object file
+/// for the codegen unit and, if it contains `global_asm!`, the object file for the assembly.
+pub fn compile_codegen_unit(
+    tcx: TyCtxt<'_>,
+    backend_config: &BackendConfig,
+    cgu_name: rustc_span::Symbol,
+    out_dir: &Path,
+) -> Result<(CompiledModule, Option<CompiledModule>), String> {
+    let (cgu_name, cx, module, codegened_functions) =
+        codegen_cgu_items(tcx, backend_config, cgu_name, false);
+
+    let crate_name = tcx.crate_name(LOCAL_CRATE).to_string();
+    let global_asm_config = GlobalAsmConfig::new(tcx).with_output_filenames(OutputFilenames::new(
+        out_dir.to_owned(),
+        crate_name.clone(),
+        crate_name,
+        None,
+        None,
+        String::new(),
+        OutputTypes::new(&[]),
+    ));
+
+    let codegen_result = compile_cgu(
+        cx,
+        module,
+        codegened_functions,
+        &global_asm_config,
+        cgu_name,
+        &crate::debuginfo::producer(tcx.sess),
+    )?;
+    Ok((codegen_result.module_regular, codegen_result.module_global_asm))
+}
+
 fn module_codegen(
     tcx: TyCtxt<'_>,
     (backend_config, global_asm_config, cgu_name, token): (
@@ -487,94 +746,90 @@ fn module_codegen(
         ConcurrencyLimiterToken,
     ),
 ) -> OngoingModuleCodegen {
-    let (cgu_name, mut cx, mut module, codegened_functions) =
-        tcx.prof.generic_activity_with_arg("codegen cgu", cgu_name.as_str()).run(|| {
-            let cgu = tcx.codegen_unit(cgu_name);
-            let mono_items = cgu.items_in_deterministic_order(tcx);
-
-            let mut module = make_module(tcx.sess, &backend_config, cgu_name.as_str().to_string());
-
-            let mut cx = crate::CodegenCx::new(
-                tcx,
-                module.isa(),
-                tcx.sess.opts.debuginfo != DebugInfo::None,
-                cgu_name,
-            );
-            let mut type_dbg = TypeDebugContext::default();
-            super::predefine_mono_items(tcx, &mut module, &mono_items);
-            let mut codegened_functions = vec![];
-            for (mono_item, _) in mono_items {
-                match mono_item {
-                    MonoItem::Fn(inst) => {
-                        if let Some(codegened_function) = crate::base::codegen_fn(
-                            tcx,
-                            &mut cx,
-                            &mut type_dbg,
-                            Function::new(),
-                            &mut module,
-                            inst,
-                        ) {
-                            codegened_functions.push(codegened_function);
-                        }
-                    }
-                    MonoItem::Static(def_id) => {
-                        let data_id = crate::constant::codegen_static(tcx, &mut module, def_id);
-                        if let Some(debug_context) = &mut cx.debug_context {
-                            debug_context.define_static(tcx, &mut type_dbg, def_id, data_id);
-                        }
-                    }
-                    MonoItem::GlobalAsm(item_id) => {
-                        crate::global_asm::codegen_global_asm_item(
-                            tcx,
-                            &mut cx.global_asm,
-                            item_id,
-                        );
-                    }
-                }
-            }
-            crate::main_shim::maybe_create_entry_wrapper(tcx, &mut module, false, cgu.is_primary());
-
-            let cgu_name = cgu.name().as_str().to_owned();
-
-            (cgu_name, cx, module, codegened_functions)
-        });
+    let (cgu_name, cx, module, codegened_functions) = tcx
+        .prof
+        .generic_activity_with_arg("codegen cgu", cgu_name.as_str())
+        .run(|| codegen_cgu_items(tcx, &backend_config, cgu_name, false));
+
+    let verify_copy = backend_config.verify_deterministic_codegen.then(|| {
+        let cgu_name = rustc_span::Symbol::intern(&cgu_name);
+        tcx.prof
+            .generic_activity_with_arg("codegen cgu (determinism check)", cgu_name.as_str())
+            .run(|| codegen_cgu_items(tcx, &backend_config, cgu_name, true))
+    });
 
     let producer = crate::debuginfo::producer(tcx.sess);
 
     OngoingModuleCodegen::Async(std::thread::spawn(move || {
-        cx.profiler.clone().generic_activity_with_arg("compile functions", &*cgu_name).run(|| {
-            cranelift_codegen::timing::set_thread_profiler(Box::new(super::MeasuremeProfiler(
-                cx.profiler.clone(),
-            )));
-
-            let mut cached_context = Context::new();
-            for codegened_func in codegened_functions {
-                crate::base::compile_fn(&mut cx, &mut cached_context, &mut module, codegened_func);
-            }
-        });
+        let profiler = cx.profiler.clone();
+        let codegen_result = compile_cgu(
+            cx,
+            module,
+            codegened_functions,
+            &global_asm_config,
+            cgu_name.clone(),
+            &producer,
+        );
 
-        let global_asm_object_file =
-            cx.profiler.generic_activity_with_arg("compile assembly", &*cgu_name).run(|| {
-                crate::global_asm::compile_global_asm(&global_asm_config, &cgu_name, &cx.global_asm)
-            })?;
-
-        let codegen_result =
-            cx.profiler.generic_activity_with_arg("write object file", &*cgu_name).run(|| {
-                emit_cgu(
-                    &global_asm_config.output_filenames,
-                    &cx.profiler,
-                    cgu_name,
-                    module,
-                    cx.debug_context,
-                    global_asm_object_file,
-                    &producer,
-                )
-            });
+        let codegen_result = match (codegen_result, verify_copy) {
+            (Ok(codegen_result), Some((_, verify_cx, verify_module, verify_functions))) => profiler
+                .generic_activity_with_arg("verify deterministic codegen", &*cgu_name)
+                .run(|| {
+                    verify_deterministic_codegen(
+                        verify_cx,
+                        verify_module,
+                        verify_functions,
+                        &producer,
+                        codegen_result.module_regular.object.as_ref().unwrap(),
+                    )
+                })
+                .map(|()| codegen_result),
+            (codegen_result, _) => codegen_result,
+        };
         std::mem::drop(token);
         codegen_result
     }))
 }
 
+/// Compile the second copy of a codegen unit produced for `verify_deterministic_codegen` and
+/// check that it results in exactly the same object file as the first copy.
+fn verify_deterministic_codegen(
+    mut cx: crate::CodegenCx,
+    mut module: UnwindModule<ObjectModule>,
+    codegened_functions: Vec<CodegenedFunction>,
+    producer: &str,
+    object_file: &Path,
+) -> Result<(), String> {
+    let mut cached_context = Context::new();
+    for codegened_func in codegened_functions {
+        crate::base::compile_fn(&mut cx, &mut cached_context, &mut module, codegened_func);
+    }
+
+    let mut product = module.finish();
+    if let Some(mut debug) = cx.debug_context {
+        debug.emit(&mut product);
+    }
+    mark_used_statics_retained(&mut product, &cx.used_statics);
+    add_producer_comment(&mut product.object, producer);
+
+    let expected = match product.object.write() {
+        Ok(expected) => expected,
+        Err(err) => return Err(format!("error writing object file: {}", err)),
+    };
+    let actual = match fs::read(object_file) {
+        Ok(actual) => actual,
+        Err(err) => return Err(format!("error reading object file: {}", err)),
+    };
+    if actual != expected {
+        return Err(format!(
+            "codegen is not deterministic: compiling {} twice gave different results",
+            object_file.display(),
+        ));
+    }
+
+    Ok(())
+}
+
 pub(crate) fn run_aot(
     tcx: TyCtxt<'_>,
     backend_config: BackendConfig,
@@ -623,19 +878,28 @@ pub(crate) fn run_aot(
 
     let global_asm_config = Arc::new(crate::global_asm::GlobalAsmConfig::new(tcx));
 
-    let (todo_cgus, done_cgus) =
+    let (mut todo_cgus, done_cgus) =
         cgus.into_iter().enumerate().partition::<Vec<_>, _>(|&(i, _)| match cgu_reuse[i] {
             _ if backend_config.disable_incr_cache => true,
+            // Reused codegen units wouldn't get checked for determinism.
+            _ if backend_config.verify_deterministic_codegen => true,
+            // The disassembly is not stored in the incremental cache.
+            _ if tcx.sess.opts.output_types.contains_key(&OutputType::Assembly) => true,
             CguReuse::No => true,
             CguReuse::PreLto | CguReuse::PostLto => false,
         });
 
+    // Start with the largest codegen units as those take the longest to compile. Otherwise a big
+    // codegen unit which happens to be scheduled last would stall the end of the build.
+    todo_cgus.sort_by_key(|&(i, cgu)| (cmp::Reverse(cgu.size_estimate()), i));
+
     let concurrency_limiter = IntoDynSyncSend(ConcurrencyLimiter::new(tcx.sess, todo_cgus.len()));
 
     let modules = tcx.sess.time("codegen mono items", || {
-        let mut modules: Vec<_> = par_map(todo_cgus, |(_, cgu)| {
+        let mut modules: Vec<_> = par_map(todo_cgus, |(i, cgu)| {
             let dep_node = cgu.codegen_dep_node(tcx);
-            tcx.dep_graph
+            let module = tcx
+                .dep_graph
                 .with_task(
                     dep_node,
                     tcx,
@@ -648,8 +912,13 @@ pub(crate) fn run_aot(
                     module_codegen,
                     Some(rustc_middle::dep_graph::hash_result),
                 )
-                .0
+                .0;
+            (i, module)
         });
+        // Restore the original codegen unit order to keep the order in which the object files are
+        // passed to the linker independent of the scheduling order.
+        modules.sort_by_key(|&(i, _)| i);
+        let mut modules: Vec<_> = modules.into_iter().map(|(_, module)| module).collect();
         modules.extend(
             done_cgus
                 .into_iter()
@@ -2,14 +2,15 @@
 //! files.
 
 use std::cell::RefCell;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
 use std::sync::{mpsc, Mutex, OnceLock};
 
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
 use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::FuncOrDataId;
 use rustc_codegen_ssa::CrateInfo;
-use rustc_middle::mir::mono::MonoItem;
+use rustc_middle::mir::mono::{Linkage as RLinkage, MonoItem};
 use rustc_session::Session;
 use rustc_span::Symbol;
 
@@ -18,8 +19,13 @@
 use crate::{prelude::*, BackendConfig};
 use crate::{CodegenCx, CodegenMode};
 
-struct JitState {
+/// The state of the JIT driver while it JIT executes a program, see [`with_jit_state`].
+pub struct JitState {
     jit_module: UnwindModule<JITModule>,
+    /// Whether functions can be redefined, which is only the case in lazy JIT mode.
+    hotswap: bool,
+    /// Intrinsics to treat as unimplemented, see `CodegenCx::unsupported_intrinsics`.
+    unsupported_intrinsics: Vec<Symbol>,
 }
 
 thread_local! {
@@ -41,6 +47,15 @@ enum UnsafeMessage {
         trampoline_ptr: *const u8,
         tx: mpsc::Sender<*const u8>,
     },
+    /// Request the address of the function or static with the symbol name at `name`.
+    ///
+    /// `name` must stay valid until the response has been received.
+    LookupSymbol { name: *const c_char, tx: mpsc::Sender<*const u8> },
+    /// Request that the function with the symbol name at `name` be compiled again, using the body
+    /// of the function with the symbol name at `body`.
+    ///
+    /// `name` and `body` must stay valid until the response has been received.
+    Recompile { name: *const c_char, body: *const c_char, tx: mpsc::Sender<*const u8> },
 }
 unsafe impl Send for UnsafeMessage {}
 
@@ -72,9 +87,15 @@ fn create_jit_module(
     crate::compiler_builtins::register_functions_for_jit(&mut jit_builder);
     jit_builder.symbol_lookup_fn(dep_symbol_lookup_fn(tcx.sess, crate_info));
     jit_builder.symbol("__clif_jit_fn", clif_jit_fn as *const u8);
+    jit_builder.symbol("__clif_jit_lookup_symbol", clif_jit_lookup_symbol as *const u8);
+    jit_builder.symbol("__clif_jit_recompile", clif_jit_recompile as *const u8);
+    jit_builder.symbol("__clif_jit_redefine", clif_jit_redefine as *const u8);
     let mut jit_module = UnwindModule::new(JITModule::new(jit_builder), false);
 
-    let cx = crate::CodegenCx::new(tcx, jit_module.isa(), false, Symbol::intern("dummy_cgu_name"));
+    let mut cx =
+        crate::CodegenCx::new(tcx, jit_module.isa(), false, Symbol::intern("dummy_cgu_name"));
+    cx.unsupported_intrinsics =
+        backend_config.unsupported_intrinsics.iter().map(|name| Symbol::intern(name)).collect();
 
     crate::allocator::codegen(tcx, &mut jit_module);
 
@@ -108,7 +129,11 @@ pub(crate) fn run_jit(tcx: TyCtxt<'_>, backend_config: BackendConfig) -> ! {
 
     tcx.sess.time("codegen mono items", || {
         super::predefine_mono_items(tcx, &mut jit_module, &mono_items);
-        for (mono_item, _) in mono_items {
+        for (mono_item, data) in mono_items {
+            if data.linkage == RLinkage::AvailableExternally {
+                // Declared as import by `predefine_mono_items` and `data_id_for_static`.
+                continue;
+            }
             match mono_item {
                 MonoItem::Fn(inst) => match backend_config.codegen_mode {
                     CodegenMode::Aot => unreachable!(),
@@ -119,6 +144,7 @@ pub(crate) fn run_jit(tcx: TyCtxt<'_>, backend_config: BackendConfig) -> ! {
                             &mut cached_context,
                             &mut jit_module,
                             inst,
+                            None,
                         );
                     }
                     CodegenMode::JitLazy => {
@@ -155,21 +181,15 @@ pub(crate) fn run_jit(tcx: TyCtxt<'_>, backend_config: BackendConfig) -> ! {
         .map(|arg| CString::new(arg).unwrap())
         .collect::<Vec<_>>();
 
-    let start_sig = Signature {
-        params: vec![
-            AbiParam::new(jit_module.target_config().pointer_type()),
-            AbiParam::new(jit_module.target_config().pointer_type()),
-        ],
-        returns: vec![AbiParam::new(jit_module.target_config().pointer_type() /*isize*/)],
-        call_conv: jit_module.target_config().default_call_conv,
-    };
-    let start_func_id = jit_module.declare_function("main", Linkage::Import, &start_sig).unwrap();
-    let finalized_start: *const u8 = jit_module.module.get_finalized_function(start_func_id);
+    let hotswap = matches!(backend_config.codegen_mode, CodegenMode::JitLazy);
+    let jit_state =
+        JitState { jit_module, hotswap, unsupported_intrinsics: cx.unsupported_intrinsics };
+    let finalized_start = jit_state.lookup_symbol("main").unwrap();
 
     LAZY_JIT_STATE.with(|lazy_jit_state| {
         let mut lazy_jit_state = lazy_jit_state.borrow_mut();
         assert!(lazy_jit_state.is_none());
-        *lazy_jit_state = Some(JitState { jit_module });
+        *lazy_jit_state = Some(jit_state);
     });
 
     let f: extern "C" fn(c_int, *const *const c_char) -> c_int =
@@ -199,6 +219,16 @@ pub(crate) fn run_jit(tcx: TyCtxt<'_>, backend_config: BackendConfig) -> ! {
                 tx.send(jit_fn(instance_ptr, trampoline_ptr))
                     .expect("jitted runtime hung up before response to lazy JIT request was sent");
             }
+            UnsafeMessage::LookupSymbol { name, tx } => {
+                tx.send(lookup_symbol(unsafe { CStr::from_ptr(name) })).expect(
+                    "jitted runtime hung up before response to symbol lookup request was sent",
+                );
+            }
+            UnsafeMessage::Recompile { name, body, tx } => {
+                let (name, body) = unsafe { (CStr::from_ptr(name), CStr::from_ptr(body)) };
+                tx.send(recompile_symbol(tcx, name, body))
+                    .expect("jitted runtime hung up before response to recompile request was sent");
+            }
         }
     }
 }
@@ -207,8 +237,9 @@ pub(crate) fn codegen_and_compile_fn<'tcx>(
     tcx: TyCtxt<'tcx>,
     cx: &mut crate::CodegenCx,
     cached_context: &mut Context,
-    module: &mut dyn Module,
+    module: &mut UnwindModule<JITModule>,
     instance: Instance<'tcx>,
+    define_as: Option<FuncId>,
 ) {
     cranelift_codegen::timing::set_thread_profiler(Box::new(super::MeasuremeProfiler(
         cx.profiler.clone(),
@@ -219,7 +250,7 @@ pub(crate) fn codegen_and_compile_fn<'tcx>(
             crate::PrintOnPanic(|| format!("{:?} {}", instance, tcx.symbol_name(instance).name));
 
         let cached_func = std::mem::replace(&mut cached_context.func, Function::new());
-        if let Some(codegened_func) = crate::base::codegen_fn(
+        if let Some(mut codegened_func) = crate::base::codegen_fn(
             tcx,
             cx,
             &mut TypeDebugContext::default(),
@@ -227,6 +258,9 @@ pub(crate) fn codegen_and_compile_fn<'tcx>(
             module,
             instance,
         ) {
+            if let Some(func_id) = define_as {
+                codegened_func.define_as(func_id);
+            }
             crate::base::compile_fn(cx, cached_context, module, codegened_func);
         }
     });
@@ -246,6 +280,70 @@ extern "C" fn clif_jit_fn(
     rx.recv().expect("rustc thread hung up before responding to sent lazy JIT request")
 }
 
+/// Returns the address of the function or static with the given symbol name. Exposed to the jitted
+/// program as `extern "C" fn __clif_jit_lookup_symbol(name: *const c_char) -> *const u8`.
+///
+/// Returns null if there is no function or static with this name. See [`JitState::lookup_symbol`]
+/// for functions which haven't been compiled yet in lazy JIT mode.
+extern "C" fn clif_jit_lookup_symbol(name: *const c_char) -> *const u8 {
+    let (tx, rx) = mpsc::channel();
+    UnsafeMessage::LookupSymbol { name, tx }
+        .send()
+        .expect("rustc thread hung up before symbol lookup request was sent");
+    rx.recv().expect("rustc thread hung up before responding to sent symbol lookup request")
+}
+
+fn lookup_symbol(name: &CStr) -> *const u8 {
+    let Ok(name) = name.to_str() else {
+        return std::ptr::null();
+    };
+    LAZY_JIT_STATE.with(|lazy_jit_state| {
+        let lazy_jit_state = lazy_jit_state.borrow();
+        lazy_jit_state.as_ref().unwrap().lookup_symbol(name).unwrap_or(std::ptr::null())
+    })
+}
+
+/// Compiles the function with the given symbol name again. Exposed to the jitted program as
+/// `extern "C" fn __clif_jit_recompile(name: *const c_char) -> *const u8`.
+///
+/// Returns a pointer to the new body, or null if there is no function with this name or when not
+/// running in lazy JIT mode. See [`JitState::recompile`] for which calls see the new body.
+extern "C" fn clif_jit_recompile(name: *const c_char) -> *const u8 {
+    clif_jit_redefine(name, name)
+}
+
+/// Compiles the body of the function with the symbol name `body` as the new definition of the
+/// function with the symbol name `name`. Exposed to the jitted program as
+/// `extern "C" fn __clif_jit_redefine(name: *const c_char, body: *const c_char) -> *const u8`.
+///
+/// Returns a pointer to the new body, or null if either function doesn't exist, if their
+/// signatures differ or when not running in lazy JIT mode. See [`JitState::redefine`].
+extern "C" fn clif_jit_redefine(name: *const c_char, body: *const c_char) -> *const u8 {
+    let (tx, rx) = mpsc::channel();
+    UnsafeMessage::Recompile { name, body, tx }
+        .send()
+        .expect("rustc thread hung up before recompile request was sent");
+    rx.recv().expect("rustc thread hung up before responding to sent recompile request")
+}
+
+fn recompile_symbol(tcx: TyCtxt<'_>, name: &CStr, body: &CStr) -> *const u8 {
+    let find_instance = |name: &CStr| {
+        let name = name.to_str().ok()?;
+        let (_, cgus) = tcx.collect_and_partition_mono_items(());
+        cgus.iter().flat_map(|cgu| cgu.items().keys()).find_map(|item| match *item {
+            MonoItem::Fn(instance) if tcx.symbol_name(instance).name == name => Some(instance),
+            _ => None,
+        })
+    };
+    let (Some(instance), Some(body)) = (find_instance(name), find_instance(body)) else {
+        return std::ptr::null();
+    };
+
+    with_jit_state(|jit_state| jit_state.redefine_instance(tcx, instance, body))
+        .flatten()
+        .unwrap_or(std::ptr::null())
+}
+
 fn jit_fn(instance_ptr: *const Instance<'static>, trampoline_ptr: *const u8) -> *const u8 {
     rustc_middle::ty::tls::with(|tcx| {
         // lift is used to ensure the correct lifetime for instance.
@@ -274,23 +372,104 @@ fn jit_fn(instance_ptr: *const Instance<'static>, trampoline_ptr: *const u8) ->
                 return current_ptr;
             }
 
-            jit_module.module.prepare_for_function_redefine(func_id).unwrap();
-
-            let mut cx = crate::CodegenCx::new(
-                tcx,
-                jit_module.isa(),
-                false,
-                Symbol::intern("dummy_cgu_name"),
-            );
-            codegen_and_compile_fn(tcx, &mut cx, &mut Context::new(), jit_module, instance);
-
-            assert!(cx.global_asm.is_empty());
-            jit_module.finalize_definitions();
-            jit_module.module.get_finalized_function(func_id)
+            lazy_jit_state.define_instance(tcx, instance, instance)
         })
     })
 }
 
+/// Runs `f` with the state of the JIT driver. Returns `None` when no program is being JIT executed,
+/// which is always the case on threads other than the rustc thread.
+pub fn with_jit_state<R>(f: impl FnOnce(&mut JitState) -> R) -> Option<R> {
+    LAZY_JIT_STATE.with(|lazy_jit_state| lazy_jit_state.borrow_mut().as_mut().map(f))
+}
+
+impl JitState {
+    /// Returns the address of the function or static with the given symbol name.
+    ///
+    /// In lazy JIT mode functions which haven't been compiled yet resolve to their lazy JIT
+    /// trampoline, so the function gets compiled the first time it is called through the returned
+    /// pointer.
+    fn lookup_symbol(&self, name: &str) -> Option<*const u8> {
+        match self.jit_module.module.get_name(name)? {
+            FuncOrDataId::Func(func_id) => {
+                Some(self.jit_module.module.get_finalized_function(func_id))
+            }
+            FuncOrDataId::Data(data_id) => {
+                Some(self.jit_module.module.get_finalized_data(data_id).0)
+            }
+        }
+    }
+
+    /// Compiles the non-generic function `def_id` again and points its GOT entry at the new body.
+    /// All calls made after this returns dispatch to the new body, including calls from functions
+    /// that were compiled before, as those always call through the GOT. Returns a pointer to the
+    /// new body, or `None` when not running in lazy JIT mode.
+    ///
+    /// This must be called on the rustc thread, as that thread owns both the `JITModule` and the
+    /// `TyCtxt`. Use [`with_jit_state`] to get at the `JitState` there. Calls into the old body
+    /// which are still running on the thread executing the jitted program are not affected and
+    /// will finish using the old code.
+    pub fn recompile(&mut self, tcx: TyCtxt<'_>, def_id: DefId) -> Option<*const u8> {
+        let instance = Instance::mono(tcx, def_id);
+        self.redefine_instance(tcx, instance, instance)
+    }
+
+    /// Like [`JitState::recompile`], but uses the body of the non-generic function `body` as the
+    /// new definition of `def_id`. This allows replacing a function with an edited copy defined
+    /// as a new item, like a REPL would do. Returns `None` if both functions have different
+    /// signatures.
+    pub fn redefine(&mut self, tcx: TyCtxt<'_>, def_id: DefId, body: DefId) -> Option<*const u8> {
+        self.redefine_instance(tcx, Instance::mono(tcx, def_id), Instance::mono(tcx, body))
+    }
+
+    fn redefine_instance<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        instance: Instance<'tcx>,
+        body: Instance<'tcx>,
+    ) -> Option<*const u8> {
+        let call_conv = self.jit_module.target_config().default_call_conv;
+        if !self.hotswap
+            || crate::abi::get_function_sig(tcx, call_conv, instance)
+                != crate::abi::get_function_sig(tcx, call_conv, body)
+        {
+            return None;
+        }
+        Some(self.define_instance(tcx, instance, body))
+    }
+
+    /// Compiles the body of `body` as the definition of `instance`. If `instance` has been defined
+    /// before, this requires the JIT module to have been created with hotswapping enabled.
+    fn define_instance<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        instance: Instance<'tcx>,
+        body: Instance<'tcx>,
+    ) -> *const u8 {
+        let jit_module = &mut self.jit_module;
+
+        let name = tcx.symbol_name(instance).name;
+        let sig = crate::abi::get_function_sig(
+            tcx,
+            jit_module.target_config().default_call_conv,
+            instance,
+        );
+        let func_id = jit_module.declare_function(name, Linkage::Export, &sig).unwrap();
+
+        jit_module.module.prepare_for_function_redefine(func_id).unwrap();
+
+        let mut cx =
+            crate::CodegenCx::new(tcx, jit_module.isa(), false, Symbol::intern("dummy_cgu_name"));
+        cx.unsupported_intrinsics = self.unsupported_intrinsics.clone();
+        let define_as = (body != instance).then_some(func_id);
+        codegen_and_compile_fn(tcx, &mut cx, &mut Context::new(), jit_module, body, define_as);
+
+        assert!(cx.global_asm.is_empty());
+        jit_module.finalize_definitions();
+        jit_module.module.get_finalized_function(func_id)
+    }
+}
+
 fn dep_symbol_lookup_fn(
     sess: &Session,
     crate_info: CrateInfo,
@@ -29,6 +29,7 @@ fn predefine_mono_items<'tcx>(
                     let sig =
                         get_function_sig(tcx, module.target_config().default_call_conv, instance);
                     let linkage = crate::linkage::get_clif_linkage(
+                        tcx,
                         mono_item,
                         data.linkage,
                         data.visibility,
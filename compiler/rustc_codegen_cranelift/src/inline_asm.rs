@@ -35,6 +35,31 @@ pub(crate) enum CInlineAsmOperand<'tcx> {
     },
 }
 
+/// Create a wrapper around `instance` to pass to assembly code in place of the function itself.
+///
+/// The function itself may not be exported from the codegen unit that defines it and may thus be
+/// unreachable from the object file created by an external assembler.
+pub(crate) fn create_asm_sym_fn_wrapper<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    cx: &crate::CodegenCx,
+    module: &mut dyn Module,
+    instance: Instance<'tcx>,
+) -> String {
+    let symbol = tcx.symbol_name(instance);
+
+    let inline_asm_index = cx.inline_asm_index.get();
+    cx.inline_asm_index.set(inline_asm_index + 1);
+    let wrapper_name = format!(
+        "__inline_asm_{}_wrapper_n{}",
+        cx.cgu_name.as_str().replace('.', "__").replace('-', "_"),
+        inline_asm_index
+    );
+    let sig = get_function_sig(tcx, module.target_config().default_call_conv, instance);
+    create_wrapper_function(module, sig, &wrapper_name, symbol.name);
+
+    wrapper_name
+}
+
 pub(crate) fn codegen_inline_asm_terminator<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     span: Span,
@@ -84,12 +109,6 @@ pub(crate) fn codegen_inline_asm_terminator<'tcx>(
                 CInlineAsmOperand::Const { value }
             }
             InlineAsmOperand::SymFn { ref value } => {
-                if cfg!(not(feature = "inline_asm_sym")) {
-                    fx.tcx
-                        .dcx()
-                        .span_err(span, "asm! and global_asm! sym operands are not yet supported");
-                }
-
                 let const_ = fx.monomorphize(value.const_);
                 if let ty::FnDef(def_id, args) = *const_.ty().kind() {
                     let instance = ty::Instance::resolve_for_fn_ptr(
@@ -99,22 +118,8 @@ pub(crate) fn codegen_inline_asm_terminator<'tcx>(
                         args,
                     )
                     .unwrap();
-                    let symbol = fx.tcx.symbol_name(instance);
-
-                    // Pass a wrapper rather than the function itself as the function itself may not
-                    // be exported from the main codegen unit and may thus be unreachable from the
-                    // object file created by an external assembler.
-                    let inline_asm_index = fx.cx.inline_asm_index.get();
-                    fx.cx.inline_asm_index.set(inline_asm_index + 1);
-                    let wrapper_name = format!(
-                        "__inline_asm_{}_wrapper_n{}",
-                        fx.cx.cgu_name.as_str().replace('.', "__").replace('-', "_"),
-                        inline_asm_index
-                    );
-                    let sig =
-                        get_function_sig(fx.tcx, fx.target_config.default_call_conv, instance);
-                    create_wrapper_function(fx.module, sig, &wrapper_name, symbol.name);
-
+                    let wrapper_name =
+                        create_asm_sym_fn_wrapper(fx.tcx, fx.cx, fx.module, instance);
                     CInlineAsmOperand::Symbol { symbol: wrapper_name }
                 } else {
                     span_bug!(span, "invalid type for asm sym (fn)");
@@ -245,11 +250,6 @@ pub(crate) fn codegen_naked_asm<'tcx>(
                 CInlineAsmOperand::Const { value }
             }
             InlineAsmOperand::SymFn { ref value } => {
-                if cfg!(not(feature = "inline_asm_sym")) {
-                    tcx.dcx()
-                        .span_err(span, "asm! and global_asm! sym operands are not yet supported");
-                }
-
                 let const_ = instance.instantiate_mir_and_normalize_erasing_regions(
                     tcx,
                     ty::ParamEnv::reveal_all(),
@@ -263,22 +263,7 @@ pub(crate) fn codegen_naked_asm<'tcx>(
                         args,
                     )
                     .unwrap();
-                    let symbol = tcx.symbol_name(instance);
-
-                    // Pass a wrapper rather than the function itself as the function itself may not
-                    // be exported from the main codegen unit and may thus be unreachable from the
-                    // object file created by an external assembler.
-                    let inline_asm_index = cx.inline_asm_index.get();
-                    cx.inline_asm_index.set(inline_asm_index + 1);
-                    let wrapper_name = format!(
-                        "__inline_asm_{}_wrapper_n{}",
-                        cx.cgu_name.as_str().replace('.', "__").replace('-', "_"),
-                        inline_asm_index
-                    );
-                    let sig =
-                        get_function_sig(tcx, module.target_config().default_call_conv, instance);
-                    create_wrapper_function(module, sig, &wrapper_name, symbol.name);
-
+                    let wrapper_name = create_asm_sym_fn_wrapper(tcx, cx, module, instance);
                     CInlineAsmOperand::Symbol { symbol: wrapper_name }
                 } else {
                     span_bug!(span, "invalid type for asm sym (fn)");
@@ -473,7 +458,9 @@ fn allocate_stack_slots(&mut self) {
         };
         let mut new_slot = |x| new_slot_fn(&mut slot_size, x);
 
-        // Allocate stack slots for saving clobbered registers
+        // Allocate stack slots for saving clobbered registers. Note that `clobber_abi` has already
+        // been expanded into lateout operands without a place by AST lowering, so registers
+        // clobbered through it are handled here too.
         let abi_clobber = InlineAsmClobberAbi::parse(self.arch, &self.tcx.sess.target, sym::C)
             .unwrap()
             .clobbered_regs();
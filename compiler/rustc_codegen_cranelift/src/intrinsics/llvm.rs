@@ -17,10 +17,23 @@ pub(crate) fn codegen_llvm_intrinsic_call<'tcx>(
     if intrinsic.starts_with("llvm.x86") {
         return llvm_x86::codegen_x86_llvm_intrinsic_call(fx, intrinsic, args, ret, target, span);
     }
+    // FIXME lower the `llvm.riscv.*` vector intrinsics once `core::arch::riscv64` exposes the
+    // RISC-V vector extension. It currently has no vector intrinsics as Rust doesn't support
+    // scalable vector types. Portable SIMD on riscv64 only uses the generic `simd_*` intrinsics.
 
     match intrinsic {
         "llvm.prefetch" => {
-            // Nothing to do. This is merely a perf hint.
+            intrinsic_args!(fx, args => (ptr, _rw, _locality, _cache_type); intrinsic);
+            let ptr = ptr.load_scalar(fx);
+
+            let [rw, locality, cache_type] = [1, 2, 3].map(|i| {
+                crate::constant::mir_operand_get_const_val(fx, &args[i].node)
+                    .unwrap_or_else(|| {
+                        fx.tcx.dcx().span_fatal(span, "`llvm.prefetch` argument is not a constant")
+                    })
+                    .to_i32()
+            });
+            codegen_prefetch(fx, ptr, rw == 1, locality.clamp(0, 3) as u8, cache_type == 1);
         }
 
         _ if intrinsic.starts_with("llvm.ctlz.v") => {
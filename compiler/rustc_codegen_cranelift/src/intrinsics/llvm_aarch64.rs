@@ -241,6 +241,49 @@ pub(crate) fn codegen_aarch64_llvm_intrinsic_call<'tcx>(
             );
         }
 
+        _ if intrinsic.starts_with("llvm.aarch64.neon.smull.v") => {
+            intrinsic_args!(fx, args => (a, b); intrinsic);
+
+            simd_pair_for_each_lane(fx, a, b, ret, &|fx, _lane_ty, res_lane_ty, a, b| {
+                let res_ty = fx.clif_type(res_lane_ty).unwrap();
+                let a = fx.bcx.ins().sextend(res_ty, a);
+                let b = fx.bcx.ins().sextend(res_ty, b);
+                fx.bcx.ins().imul(a, b)
+            });
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.umull.v") => {
+            intrinsic_args!(fx, args => (a, b); intrinsic);
+
+            simd_pair_for_each_lane(fx, a, b, ret, &|fx, _lane_ty, res_lane_ty, a, b| {
+                let res_ty = fx.clif_type(res_lane_ty).unwrap();
+                let a = fx.bcx.ins().uextend(res_ty, a);
+                let b = fx.bcx.ins().uextend(res_ty, b);
+                fx.bcx.ins().imul(a, b)
+            });
+        }
+
+        _ if intrinsic.starts_with("llvm.aarch64.neon.pmull.v") => {
+            intrinsic_args!(fx, args => (a, b); intrinsic);
+
+            // Polynomial (carry-less) multiplication
+            simd_pair_for_each_lane(fx, a, b, ret, &|fx, lane_ty, res_lane_ty, a, b| {
+                let lane_bits = fx.clif_type(lane_ty).unwrap().bits();
+                let res_ty = fx.clif_type(res_lane_ty).unwrap();
+                let a = fx.bcx.ins().uextend(res_ty, a);
+                let b = fx.bcx.ins().uextend(res_ty, b);
+                let zero = fx.bcx.ins().iconst(res_ty, 0);
+                let mut res = zero;
+                for i in 0..lane_bits {
+                    let b_bit = fx.bcx.ins().band_imm(b, 1i64 << i);
+                    let shifted_a = fx.bcx.ins().ishl_imm(a, i64::from(i));
+                    let partial = fx.bcx.ins().select(b_bit, shifted_a, zero);
+                    res = fx.bcx.ins().bxor(res, partial);
+                }
+                res
+            });
+        }
+
         // FIXME generalize vector types
         "llvm.aarch64.neon.tbl1.v8i8" => {
             intrinsic_args!(fx, args => (t, idx); intrinsic);
@@ -373,19 +373,44 @@ pub(crate) fn codegen_x86_llvm_intrinsic_call<'tcx>(
                 }
             }
         }
-        "llvm.x86.avx2.permd" => {
+        "llvm.x86.avx2.permd" | "llvm.x86.avx2.permps" => {
             // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_permutevar8x32_epi32
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_permutevar8x32_ps
             intrinsic_args!(fx, args => (a, idx); intrinsic);
 
             for j in 0..=7 {
                 let index = idx.value_typed_lane(fx, fx.tcx.types.u32, j).load_scalar(fx);
+                // Only the lowest 3 bits of the index are used.
+                let index = fx.bcx.ins().band_imm(index, 7);
                 let index = fx.bcx.ins().uextend(fx.pointer_type, index);
-                let value = a.value_lane_dyn(fx, index).load_scalar(fx);
-                ret.place_typed_lane(fx, fx.tcx.types.u32, j).to_ptr().store(
-                    fx,
-                    value,
-                    MemFlags::trusted(),
-                );
+                let value = a.value_lane_dyn(fx, index);
+                ret.place_lane(fx, j).write_cvalue(fx, value);
+            }
+        }
+        "llvm.x86.avx.vpermilvar.ps"
+        | "llvm.x86.avx.vpermilvar.ps.256"
+        | "llvm.x86.avx.vpermilvar.pd"
+        | "llvm.x86.avx.vpermilvar.pd.256" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_permutevar_ps
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_permutevar_ps
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_permutevar_pd
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_permutevar_pd
+            intrinsic_args!(fx, args => (a, idx); intrinsic);
+
+            let (lane_count, lane_ty) = a.layout().ty.simd_size_and_type(fx.tcx);
+            // Lanes are only permuted within each 128bit part of the vector.
+            let lanes_per_128bit = 16 / fx.layout_of(lane_ty).size.bytes();
+            for j in 0..lane_count {
+                let index = idx.value_lane(fx, j).load_scalar(fx);
+                // The pd variants use bit 1 of each index rather than bit 0.
+                let index =
+                    if lanes_per_128bit == 2 { fx.bcx.ins().ushr_imm(index, 1) } else { index };
+                let index = fx.bcx.ins().band_imm(index, (lanes_per_128bit - 1) as i64);
+                let index = crate::cast::clif_intcast(fx, index, fx.pointer_type, false);
+                let index =
+                    fx.bcx.ins().iadd_imm(index, (j / lanes_per_128bit * lanes_per_128bit) as i64);
+                let value = a.value_lane_dyn(fx, index);
+                ret.place_lane(fx, j).write_cvalue(fx, value);
             }
         }
         "llvm.x86.avx2.vperm2i128"
@@ -544,6 +569,45 @@ fn select4(
                 fx.bcx.ins().sshr(a_lane, saturated_count)
             });
         }
+        "llvm.x86.avx2.psllv.d"
+        | "llvm.x86.avx2.psllv.d.256"
+        | "llvm.x86.avx2.psllv.q"
+        | "llvm.x86.avx2.psllv.q.256"
+        | "llvm.x86.avx2.psrlv.d"
+        | "llvm.x86.avx2.psrlv.d.256"
+        | "llvm.x86.avx2.psrlv.q"
+        | "llvm.x86.avx2.psrlv.q.256" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_sllv_epi32
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_srlv_epi64
+            intrinsic_args!(fx, args => (a, count); intrinsic);
+
+            let is_left = intrinsic.starts_with("llvm.x86.avx2.psllv");
+            simd_pair_for_each_lane(fx, a, count, ret, &|fx, lane_ty, _res_lane_ty, a, count| {
+                // Unlike Cranelift shifts, counts larger than the lane size zero the lane.
+                let lane_ty = fx.clif_type(lane_ty).unwrap();
+                let shifted =
+                    if is_left { fx.bcx.ins().ishl(a, count) } else { fx.bcx.ins().ushr(a, count) };
+                let zero = fx.bcx.ins().iconst(lane_ty, 0);
+                let out_of_range = fx.bcx.ins().icmp_imm(
+                    IntCC::UnsignedGreaterThanOrEqual,
+                    count,
+                    i64::from(lane_ty.bits()),
+                );
+                fx.bcx.ins().select(out_of_range, zero, shifted)
+            });
+        }
+        "llvm.x86.avx2.psrav.d" | "llvm.x86.avx2.psrav.d.256" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_srav_epi32
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_srav_epi32
+            intrinsic_args!(fx, args => (a, count); intrinsic);
+
+            simd_pair_for_each_lane(fx, a, count, ret, &|fx, _lane_ty, _res_lane_ty, a, count| {
+                // Counts larger than the lane size fill the lane with the sign bit.
+                let max_count = fx.bcx.ins().iconst(types::I32, 31);
+                let saturated_count = fx.bcx.ins().umin(count, max_count);
+                fx.bcx.ins().sshr(a, saturated_count)
+            });
+        }
         "llvm.x86.sse2.psad.bw" | "llvm.x86.avx2.psad.bw" => {
             // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_sad_epu8&ig_expand=5770
             // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_sad_epu8&ig_expand=5771
@@ -656,6 +720,44 @@ fn select4(
             }
         }
 
+        "llvm.x86.ssse3.phadd.w.128"
+        | "llvm.x86.ssse3.phadd.d.128"
+        | "llvm.x86.avx2.phadd.w"
+        | "llvm.x86.avx2.phadd.d"
+        | "llvm.x86.ssse3.phsub.w.128"
+        | "llvm.x86.ssse3.phsub.d.128"
+        | "llvm.x86.avx2.phsub.w"
+        | "llvm.x86.avx2.phsub.d" => {
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_hadd_epi16
+            // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm256_hsub_epi32
+            intrinsic_args!(fx, args => (a, b); intrinsic);
+
+            assert_eq!(a.layout(), b.layout());
+            let (lane_count, lane_ty) = a.layout().ty.simd_size_and_type(fx.tcx);
+            let lanes_per_128bit = 16 / fx.layout_of(lane_ty).size.bytes();
+            let is_sub = intrinsic.contains(".phsub.");
+
+            // Each 128bit part of the result contains the sums of adjacent lanes of the
+            // corresponding 128bit part of `a` followed by those of `b`.
+            let mut out_lane_idx = 0;
+            for part_start in (0..lane_count).step_by(lanes_per_128bit as usize) {
+                for src in [a, b] {
+                    for lane_idx in (part_start..part_start + lanes_per_128bit).step_by(2) {
+                        let lane0 = src.value_lane(fx, lane_idx).load_scalar(fx);
+                        let lane1 = src.value_lane(fx, lane_idx + 1).load_scalar(fx);
+                        let res_lane = if is_sub {
+                            fx.bcx.ins().isub(lane0, lane1)
+                        } else {
+                            fx.bcx.ins().iadd(lane0, lane1)
+                        };
+                        let res_lane = CValue::by_val(res_lane, fx.layout_of(lane_ty));
+                        ret.place_lane(fx, out_lane_idx).write_cvalue(fx, res_lane);
+                        out_lane_idx += 1;
+                    }
+                }
+            }
+        }
+
         "llvm.x86.ssse3.pmul.hr.sw.128" => {
             // https://www.intel.com/content/www/us/en/docs/intrinsics-guide/index.html#text=_mm_mulhrs_epi16&ig_expand=4782
             intrinsic_args!(fx, args => (a, b); intrinsic);
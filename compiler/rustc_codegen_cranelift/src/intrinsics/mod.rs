@@ -18,15 +18,18 @@ macro_rules! intrinsic_args {
 mod simd;
 
 use cranelift_codegen::ir::AtomicRmwOp;
+use rustc_ast::ast::{InlineAsmOptions, InlineAsmTemplatePiece};
 use rustc_middle::ty;
 use rustc_middle::ty::layout::{HasParamEnv, ValidityRequirement};
 use rustc_middle::ty::print::{with_no_trimmed_paths, with_no_visible_paths};
 use rustc_middle::ty::GenericArgsRef;
 use rustc_span::source_map::Spanned;
 use rustc_span::symbol::{sym, Symbol};
+use rustc_target::asm::*;
 
 pub(crate) use self::llvm::codegen_llvm_intrinsic_call;
 use crate::cast::clif_intcast;
+use crate::inline_asm::{codegen_inline_asm_inner, CInlineAsmOperand};
 use crate::prelude::*;
 
 fn bug_on_incorrect_arg_count(intrinsic: impl std::fmt::Display) -> ! {
@@ -50,13 +53,501 @@ fn report_atomic_type_validation_error<'tcx>(
     fx.bcx.ins().trap(TrapCode::UnreachableCodeReached);
 }
 
-pub(crate) fn clif_vector_type<'tcx>(tcx: TyCtxt<'tcx>, layout: TyAndLayout<'tcx>) -> Type {
-    let (element, count) = match layout.abi {
-        Abi::Vector { element, count } => (element, count),
-        _ => unreachable!(),
+/// Codegen an atomic read-modify-write operation.
+///
+/// Cranelift doesn't support 128bit `atomic_rmw`, so for 128bit integers this emits a
+/// compare-and-swap loop instead, using either `cmpxchg16b` or `__atomic_compare_exchange_16`.
+fn codegen_atomic_rmw(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    ty: Type,
+    op: AtomicRmwOp,
+    ptr: Value,
+    src: Value,
+) -> Value {
+    if ty != types::I128 {
+        return fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), op, ptr, src);
+    }
+
+    let loop_block = fx.bcx.create_block();
+    let done_block = fx.bcx.create_block();
+    let old = fx.bcx.append_block_param(loop_block, types::I128);
+    let res = fx.bcx.append_block_param(done_block, types::I128);
+
+    // A torn read only causes an extra iteration as the compare-and-swap will fail.
+    let initial = fx.bcx.ins().load(types::I128, MemFlags::trusted(), ptr, 0);
+    fx.bcx.ins().jump(loop_block, &[initial]);
+
+    fx.bcx.switch_to_block(loop_block);
+    let new = match op {
+        AtomicRmwOp::Add => fx.bcx.ins().iadd(old, src),
+        AtomicRmwOp::Sub => fx.bcx.ins().isub(old, src),
+        AtomicRmwOp::And => fx.bcx.ins().band(old, src),
+        AtomicRmwOp::Nand => {
+            let and = fx.bcx.ins().band(old, src);
+            fx.bcx.ins().bnot(and)
+        }
+        AtomicRmwOp::Or => fx.bcx.ins().bor(old, src),
+        AtomicRmwOp::Xor => fx.bcx.ins().bxor(old, src),
+        AtomicRmwOp::Xchg => src,
+        AtomicRmwOp::Umin | AtomicRmwOp::Umax | AtomicRmwOp::Smin | AtomicRmwOp::Smax => {
+            let cc = match op {
+                AtomicRmwOp::Umin => IntCC::UnsignedLessThan,
+                AtomicRmwOp::Umax => IntCC::UnsignedGreaterThan,
+                AtomicRmwOp::Smin => IntCC::SignedLessThan,
+                AtomicRmwOp::Smax => IntCC::SignedGreaterThan,
+                _ => unreachable!(),
+            };
+            let keep_old = fx.bcx.ins().icmp(cc, old, src);
+            fx.bcx.ins().select(keep_old, old, src)
+        }
     };
+    let prev = codegen_atomic_cas_128(fx, ptr, old, new);
+    let is_eq = fx.bcx.ins().icmp(IntCC::Equal, prev, old);
+    fx.bcx.ins().brif(is_eq, done_block, &[prev], loop_block, &[prev]);
+
+    fx.bcx.switch_to_block(done_block);
+    res
+}
+
+/// Whether `cmpxchg16b` can be used for 128bit atomics in the current function.
+fn has_cmpxchg16b(fx: &FunctionCx<'_, '_, '_>) -> bool {
+    if fx.tcx.sess.target.arch != "x86_64" {
+        return false;
+    }
+
+    fx.tcx.asm_target_features(fx.instance.def_id()).contains(&Symbol::intern("cmpxchg16b"))
+        || fx
+            .tcx
+            .sess
+            .target
+            .features
+            .split(',')
+            .chain(fx.tcx.sess.opts.cg.target_feature.split(','))
+            .any(|feature| feature == "+cmpxchg16b")
+}
+
+/// Codegen a sequentially consistent 128bit compare-and-swap, returning the previous value.
+fn codegen_atomic_cas_128(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    ptr: Value,
+    test_old: Value,
+    new: Value,
+) -> Value {
+    if has_cmpxchg16b(fx) {
+        let (test_old_lo, test_old_hi) = fx.bcx.ins().isplit(test_old);
+        let (new_lo, new_hi) = fx.bcx.ins().isplit(new);
+        let u64_layout = fx.layout_of(fx.tcx.types.u64);
+        let old_lo = CPlace::new_stack_slot(fx, u64_layout);
+        let old_hi = CPlace::new_stack_slot(fx, u64_layout);
+
+        // rbx is reserved, so pass the low half of the new value in r8 instead.
+        codegen_inline_asm_inner(
+            fx,
+            &[InlineAsmTemplatePiece::String(
+                "
+                xchg r8, rbx
+                lock cmpxchg16b xmmword ptr [rdi]
+                mov rbx, r8
+                "
+                .to_string(),
+            )],
+            &[
+                CInlineAsmOperand::In {
+                    reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::di)),
+                    value: ptr,
+                },
+                CInlineAsmOperand::InOut {
+                    reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::ax)),
+                    _late: true,
+                    in_value: test_old_lo,
+                    out_place: Some(old_lo),
+                },
+                CInlineAsmOperand::InOut {
+                    reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::dx)),
+                    _late: true,
+                    in_value: test_old_hi,
+                    out_place: Some(old_hi),
+                },
+                CInlineAsmOperand::In {
+                    reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::cx)),
+                    value: new_hi,
+                },
+                CInlineAsmOperand::InOut {
+                    reg: InlineAsmRegOrRegClass::Reg(InlineAsmReg::X86(X86InlineAsmReg::r8)),
+                    _late: true,
+                    in_value: new_lo,
+                    out_place: None,
+                },
+            ],
+            InlineAsmOptions::NOSTACK,
+        );
+
+        let old_lo = old_lo.to_cvalue(fx).load_scalar(fx);
+        let old_hi = old_hi.to_cvalue(fx).load_scalar(fx);
+        return fx.bcx.ins().iconcat(old_lo, old_hi);
+    }
+
+    let expected = fx.create_stack_slot(16, 16);
+    expected.store(fx, test_old, MemFlags::trusted());
+    let expected_ptr = expected.get_addr(fx);
+    let seq_cst = fx.bcx.ins().iconst(types::I32, 5); // __ATOMIC_SEQ_CST
+    fx.lib_call(
+        "__atomic_compare_exchange_16",
+        vec![
+            AbiParam::new(fx.pointer_type),
+            AbiParam::new(fx.pointer_type),
+            AbiParam::new(types::I128),
+            AbiParam::new(types::I32),
+            AbiParam::new(types::I32),
+        ],
+        vec![AbiParam::new(types::I8)],
+        &[ptr, expected_ptr, new, seq_cst, seq_cst],
+    );
+    // On failure the current value is written to `expected`, on success it is left unchanged.
+    expected.load(fx, types::I128, MemFlags::trusted())
+}
+
+/// Codegen a sequentially consistent 128bit atomic load.
+fn codegen_atomic_load_128(fx: &mut FunctionCx<'_, '_, '_>, ptr: Value) -> Value {
+    if has_cmpxchg16b(fx) {
+        // Replacing zero with zero leaves the memory unchanged, while always returning the
+        // current value.
+        let zero = fx.bcx.ins().iconst(types::I64, 0);
+        let zero = fx.bcx.ins().iconcat(zero, zero);
+        return codegen_atomic_cas_128(fx, ptr, zero, zero);
+    }
+
+    let seq_cst = fx.bcx.ins().iconst(types::I32, 5); // __ATOMIC_SEQ_CST
+    fx.lib_call(
+        "__atomic_load_16",
+        vec![AbiParam::new(fx.pointer_type), AbiParam::new(types::I32)],
+        vec![AbiParam::new(types::I128)],
+        &[ptr, seq_cst],
+    )[0]
+}
+
+/// Codegen a sequentially consistent 128bit atomic store.
+fn codegen_atomic_store_128(fx: &mut FunctionCx<'_, '_, '_>, ptr: Value, val: Value) {
+    if has_cmpxchg16b(fx) {
+        codegen_atomic_rmw(fx, types::I128, AtomicRmwOp::Xchg, ptr, val);
+        return;
+    }
 
-    scalar_to_clif_type(tcx, element).by(u32::try_from(count).unwrap()).unwrap()
+    let seq_cst = fx.bcx.ins().iconst(types::I32, 5); // __ATOMIC_SEQ_CST
+    fx.lib_call(
+        "__atomic_store_16",
+        vec![AbiParam::new(fx.pointer_type), AbiParam::new(types::I128), AbiParam::new(types::I32)],
+        vec![],
+        &[ptr, val, seq_cst],
+    );
+}
+
+/// Whether plain loads and stores work for unaligned addresses on the current target.
+fn has_unaligned_memory_access(fx: &FunctionCx<'_, '_, '_>) -> bool {
+    matches!(&*fx.tcx.sess.target.arch, "x86_64" | "aarch64" | "s390x")
+}
+
+/// Copy `size` bytes one byte at a time, such that neither `dst` nor `src` need to be aligned.
+fn codegen_bytewise_copy(fx: &mut FunctionCx<'_, '_, '_>, dst: Value, src: Value, size: u64) {
+    let mut flags = MemFlags::new();
+    flags.set_notrap();
+    for offset in 0..i32::try_from(size).unwrap() {
+        let byte = fx.bcx.ins().load(types::I8, flags, src, offset);
+        fx.bcx.ins().store(flags, byte, dst, offset);
+    }
+}
+
+/// Codegen a prefetch of `ptr` for the `prefetch_*` intrinsics and `llvm.prefetch`.
+///
+/// `locality` ranges from 0 (no temporal locality) to 3 (keep in all cache levels). Cranelift
+/// doesn't have a prefetch instruction, so this uses inline asm. Prefetches are merely a hint, so
+/// nothing is emitted on targets or for kinds of prefetches without a matching instruction.
+fn codegen_prefetch(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    ptr: Value,
+    is_write: bool,
+    locality: u8,
+    is_data: bool,
+) {
+    let (template, reg) = match &*fx.tcx.sess.target.arch {
+        // Like LLVM without the `prfchw` target feature, use the read prefetches for writes too.
+        // There are no instruction prefetches on x86.
+        "x86_64" if is_data => {
+            let hint = ["prefetchnta", "prefetcht2", "prefetcht1", "prefetcht0"][locality as usize];
+            (format!("{hint} [rax]"), InlineAsmReg::X86(X86InlineAsmReg::ax))
+        }
+        "aarch64" if is_data || !is_write => {
+            let kind = match (is_data, is_write) {
+                (true, false) => "pld",
+                (true, true) => "pst",
+                (false, _) => "pli",
+            };
+            // Locality 0 is a streaming prefetch into the L1 cache. Higher localities prefetch
+            // into caches closer to the cpu.
+            let (level, policy) = if locality == 0 { (1, "strm") } else { (4 - locality, "keep") };
+            (
+                format!("prfm {kind}l{level}{policy}, [x0]"),
+                InlineAsmReg::AArch64(AArch64InlineAsmReg::x0),
+            )
+        }
+        _ => return,
+    };
+
+    codegen_inline_asm_inner(
+        fx,
+        &[InlineAsmTemplatePiece::String(template)],
+        &[CInlineAsmOperand::In { reg: InlineAsmRegOrRegClass::Reg(reg), value: ptr }],
+        InlineAsmOptions::NOSTACK | InlineAsmOptions::PRESERVES_FLAGS | InlineAsmOptions::READONLY,
+    );
+}
+
+/// Panic with `msg` unless `cond` is true.
+///
+/// This is used to check the preconditions of intrinsics when `-Zub-checks` is enabled, which is
+/// the default with debug assertions, like the library does for the functions wrapping them.
+pub(crate) fn codegen_ub_check(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    cond: Value,
+    msg: &str,
+    span: Option<Span>,
+) {
+    let ok_block = fx.bcx.create_block();
+    let fail_block = fx.bcx.create_block();
+    fx.bcx.set_cold_block(fail_block);
+    fx.bcx.ins().brif(cond, ok_block, &[], fail_block, &[]);
+
+    fx.bcx.switch_to_block(fail_block);
+    crate::base::codegen_panic_nounwind(fx, msg, span);
+
+    fx.bcx.switch_to_block(ok_block);
+}
+
+/// Copy `count` elements of `elem_layout` from `src` to `dst`.
+///
+/// When `count` is statically known the copy is inlined as load/store pairs if it is small enough.
+/// Larger or dynamically sized copies call `memcpy` or `memmove`.
+pub(crate) fn codegen_copy_elems<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    dst: Value,
+    src: Value,
+    count: Value,
+    elem_layout: TyAndLayout<'tcx>,
+    non_overlapping: bool,
+) {
+    let elem_size: u64 = elem_layout.size.bytes();
+    if let Some(count) = crate::optimize::peephole::maybe_known_iconst(&fx.bcx, count) {
+        if let Some(byte_amount) = elem_size.checked_mul(count as u64) {
+            // Both pointers are required to be aligned for the element type. The aligned flag is
+            // set by emit_small_memory_copy itself when the accesses it emits are aligned.
+            let align = elem_layout.align.abi.bytes().min(128) as u8;
+            let mut flags = MemFlags::new();
+            flags.set_notrap();
+            fx.bcx.emit_small_memory_copy(
+                fx.target_config,
+                dst,
+                src,
+                byte_amount,
+                align,
+                align,
+                non_overlapping,
+                flags,
+            );
+            return;
+        }
+    }
+
+    let byte_amount =
+        if elem_size != 1 { fx.bcx.ins().imul_imm(count, elem_size as i64) } else { count };
+    if non_overlapping {
+        fx.bcx.call_memcpy(fx.target_config, dst, src, byte_amount);
+    } else {
+        fx.bcx.call_memmove(fx.target_config, dst, src, byte_amount);
+    }
+}
+
+/// Fill `count` elements of `elem_layout` at `dst` with the byte `val`.
+///
+/// When both `val` and `count` are statically known small fills are inlined as stores. Fills of
+/// up to 64 bytes that are a multiple of 16 bytes use vector stores when the target has them.
+/// Larger or dynamically sized fills call `memset`.
+pub(crate) fn codegen_write_bytes<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    dst: Value,
+    val: Value,
+    count: Value,
+    elem_layout: TyAndLayout<'tcx>,
+) {
+    let elem_size: u64 = elem_layout.size.bytes();
+    let known_byte = crate::optimize::peephole::maybe_known_iconst(&fx.bcx, val);
+    let known_count = crate::optimize::peephole::maybe_known_iconst(&fx.bcx, count);
+    if let (Some(byte), Some(count)) = (known_byte, known_count) {
+        if let Some(byte_amount) = elem_size.checked_mul(count as u64) {
+            let align = elem_layout.align.abi.bytes();
+            let mut flags = MemFlags::new();
+            flags.set_notrap();
+
+            let has_simd = matches!(&*fx.tcx.sess.target.arch, "x86_64" | "aarch64");
+            if has_simd && byte_amount > 32 && byte_amount <= 64 && byte_amount % 16 == 0 {
+                if align >= 16 {
+                    flags.set_aligned();
+                }
+                let byte = fx.bcx.ins().iconst(types::I8, i64::from(byte as u8));
+                let vector = fx.bcx.ins().splat(types::I8X16, byte);
+                for offset in (0..byte_amount as i32).step_by(16) {
+                    fx.bcx.ins().store(flags, vector, dst, offset);
+                }
+                return;
+            }
+
+            // The aligned flag is set by emit_small_memset itself when the stores it emits are
+            // aligned.
+            fx.bcx.emit_small_memset(
+                fx.target_config,
+                dst,
+                byte as u8,
+                byte_amount,
+                align.min(128) as u8,
+                flags,
+            );
+            return;
+        }
+    }
+
+    let byte_amount =
+        if elem_size != 1 { fx.bcx.ins().imul_imm(count, elem_size as i64) } else { count };
+    fx.bcx.call_memset(fx.target_config, dst, val, byte_amount);
+}
+
+/// Check whether `size` bytes at `lhs` and `rhs` are equal without calling `memcmp`.
+///
+/// 16 byte chunks are compared using vector instructions when the target has them, the rest in
+/// word sized chunks. Returns `None` if `size` is too large to be compared inline.
+fn codegen_small_bytes_eq(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    lhs: Value,
+    rhs: Value,
+    size: u64,
+) -> Option<Value> {
+    if size > 64 {
+        return None;
+    }
+
+    // Can't use `trusted` for these loads; they could be unaligned.
+    let mut flags = MemFlags::new();
+    flags.set_notrap();
+    let has_simd = matches!(&*fx.tcx.sess.target.arch, "x86_64" | "aarch64");
+
+    let mut vector_diff = None;
+    let mut int_diff = None;
+    let mut offset = 0;
+    while offset < size {
+        let remaining = size - offset;
+        if has_simd && remaining >= 16 {
+            let lhs_val = fx.bcx.ins().load(types::I8X16, flags, lhs, offset as i32);
+            let rhs_val = fx.bcx.ins().load(types::I8X16, flags, rhs, offset as i32);
+            let diff = fx.bcx.ins().bxor(lhs_val, rhs_val);
+            vector_diff = Some(match vector_diff {
+                Some(acc) => fx.bcx.ins().bor(acc, diff),
+                None => diff,
+            });
+            offset += 16;
+            continue;
+        }
+
+        let chunk_size = [8, 4, 2, 1].into_iter().find(|&chunk| chunk <= remaining).unwrap();
+        let ty = Type::int(chunk_size as u16 * 8).unwrap();
+        let lhs_val = fx.bcx.ins().load(ty, flags, lhs, offset as i32);
+        let rhs_val = fx.bcx.ins().load(ty, flags, rhs, offset as i32);
+        let diff = fx.bcx.ins().bxor(lhs_val, rhs_val);
+        let diff = if ty != types::I64 { fx.bcx.ins().uextend(types::I64, diff) } else { diff };
+        int_diff = Some(match int_diff {
+            Some(acc) => fx.bcx.ins().bor(acc, diff),
+            None => diff,
+        });
+        offset += chunk_size;
+    }
+
+    let vector_ne = vector_diff.map(|diff| fx.bcx.ins().vany_true(diff));
+    let int_ne = int_diff.map(|diff| fx.bcx.ins().icmp_imm(IntCC::NotEqual, diff, 0));
+    let is_ne = match (vector_ne, int_ne) {
+        (Some(vector_ne), Some(int_ne)) => fx.bcx.ins().bor(vector_ne, int_ne),
+        (Some(is_ne), None) | (None, Some(is_ne)) => is_ne,
+        (None, None) => fx.bcx.ins().iconst(types::I8, 0),
+    };
+    Some(fx.bcx.ins().bxor_imm(is_ne, 1))
+}
+
+/// Compare `size` bytes at `lhs` and `rhs` like `memcmp` without calling it.
+///
+/// The bytes are compared in word sized chunks which are byte swapped on little endian targets
+/// such that an unsigned comparison orders them like `memcmp` would. Returns `None` if `size` is
+/// too large to be compared inline.
+fn codegen_small_compare_bytes(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    lhs: Value,
+    rhs: Value,
+    size: u64,
+) -> Option<Value> {
+    if size > 32 {
+        return None;
+    }
+
+    let mut flags = MemFlags::new();
+    flags.set_notrap();
+    let is_little_endian = fx.tcx.data_layout.endian == rustc_target::abi::Endian::Little;
+
+    // The results of all chunks are computed unconditionally and then the first non-zero one is
+    // selected, which avoids branches. All bytes are dereferenceable, so this can't fault.
+    let mut chunk_results = vec![];
+    let mut offset = 0;
+    while offset < size {
+        let remaining = size - offset;
+        let chunk_size = [8, 4, 2, 1].into_iter().find(|&chunk| chunk <= remaining).unwrap();
+        let ty = Type::int(chunk_size as u16 * 8).unwrap();
+        let mut lhs_val = fx.bcx.ins().load(ty, flags, lhs, offset as i32);
+        let mut rhs_val = fx.bcx.ins().load(ty, flags, rhs, offset as i32);
+        if is_little_endian && chunk_size != 1 {
+            lhs_val = fx.bcx.ins().bswap(lhs_val);
+            rhs_val = fx.bcx.ins().bswap(rhs_val);
+        }
+        let is_gt = fx.bcx.ins().icmp(IntCC::UnsignedGreaterThan, lhs_val, rhs_val);
+        let is_lt = fx.bcx.ins().icmp(IntCC::UnsignedLessThan, lhs_val, rhs_val);
+        let is_gt = fx.bcx.ins().uextend(types::I32, is_gt);
+        let is_lt = fx.bcx.ins().uextend(types::I32, is_lt);
+        chunk_results.push(fx.bcx.ins().isub(is_gt, is_lt));
+        offset += chunk_size;
+    }
+
+    let mut result = fx.bcx.ins().iconst(types::I32, 0);
+    for chunk_result in chunk_results.into_iter().rev() {
+        result = fx.bcx.ins().select(chunk_result, chunk_result, result);
+    }
+    Some(result)
+}
+
+pub(crate) fn clif_vector_type<'tcx>(tcx: TyCtxt<'tcx>, layout: TyAndLayout<'tcx>) -> Type {
+    try_clif_vector_type(tcx, layout).unwrap()
+}
+
+/// Load a scalar of at most 64 bits using `atomic_load` if possible. The pointer must be aligned.
+fn codegen_atomic_scalar_load<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    ptr: Value,
+    layout: TyAndLayout<'tcx>,
+) -> Option<CValue<'tcx>> {
+    let Abi::Scalar(scalar) = layout.abi else {
+        return None;
+    };
+    let ty = scalar_to_clif_type(fx.tcx, scalar);
+    let int_ty = match ty {
+        types::I8 | types::I16 | types::I32 | types::I64 => ty,
+        types::F32 => types::I32,
+        types::F64 => types::I64,
+        _ => return None,
+    };
+    let val = fx.bcx.ins().atomic_load(int_ty, MemFlags::trusted(), ptr);
+    let val = if int_ty == ty { val } else { fx.bcx.ins().bitcast(ty, MemFlags::new(), val) };
+    Some(CValue::by_val(val, layout))
 }
 
 fn simd_for_each_lane<'tcx>(
@@ -204,12 +695,35 @@ fn simd_reduce<'tcx>(
     let lane_layout = fx.layout_of(lane_ty);
     assert_eq!(lane_layout, ret.layout());
 
-    let (mut res_val, start_lane) =
-        if let Some(acc) = acc { (acc, 0) } else { (val.value_lane(fx, 0).load_scalar(fx), 1) };
-    for lane_idx in start_lane..lane_count {
-        let lane = val.value_lane(fx, lane_idx).load_scalar(fx);
-        res_val = f(fx, lane_layout.ty, res_val, lane);
-    }
+    let res_val = if let Some(acc) = acc {
+        // Ordered reductions have to combine the lanes strictly from first to last.
+        let mut res_val = acc;
+        for lane_idx in 0..lane_count {
+            let lane = val.value_lane(fx, lane_idx).load_scalar(fx);
+            res_val = f(fx, lane_layout.ty, res_val, lane);
+        }
+        res_val
+    } else {
+        let mut lanes = (0..lane_count)
+            .map(|lane_idx| val.value_lane(fx, lane_idx).load_scalar(fx))
+            .collect::<Vec<Value>>();
+
+        // Unordered reductions may be reassociated. For power of two lane counts repeatedly
+        // combine the lower and upper half of the lanes to shorten the dependency chain.
+        while lanes.len() > 1 && lanes.len().is_power_of_two() {
+            let half = lanes.len() / 2;
+            let combined = (0..half)
+                .map(|lane_idx| f(fx, lane_layout.ty, lanes[lane_idx], lanes[half + lane_idx]))
+                .collect::<Vec<Value>>();
+            lanes = combined;
+        }
+
+        let mut res_val = lanes[0];
+        for &lane in &lanes[1..] {
+            res_val = f(fx, lane_layout.ty, res_val, lane);
+        }
+        res_val
+    };
     let res = CValue::by_val(res_val, lane_layout);
     ret.write_cvalue(fx, res);
 }
@@ -273,6 +787,10 @@ pub(crate) fn codegen_intrinsic_call<'tcx>(
     let intrinsic = fx.tcx.item_name(instance.def_id());
     let instance_args = instance.args;
 
+    if fx.cx.unsupported_intrinsics.contains(&intrinsic) {
+        return codegen_unsupported_intrinsic(fx, instance, source_info.span);
+    }
+
     if intrinsic.as_str().starts_with("simd_") {
         self::simd::codegen_simd_intrinsic_call(
             fx,
@@ -397,6 +915,10 @@ fn codegen_float_intrinsic_call<'tcx>(
         | sym::truncf64
         | sym::nearbyintf32
         | sym::nearbyintf64
+        | sym::rintf32
+        | sym::rintf64
+        | sym::roundevenf32
+        | sym::roundevenf64
         | sym::sqrtf32
         | sym::sqrtf64 => {
             let val = match intrinsic {
@@ -404,7 +926,13 @@ fn codegen_float_intrinsic_call<'tcx>(
                 sym::floorf32 | sym::floorf64 => fx.bcx.ins().floor(args[0]),
                 sym::ceilf32 | sym::ceilf64 => fx.bcx.ins().ceil(args[0]),
                 sym::truncf32 | sym::truncf64 => fx.bcx.ins().trunc(args[0]),
-                sym::nearbyintf32 | sym::nearbyintf64 => fx.bcx.ins().nearest(args[0]),
+                // Rust code always runs with the default round-to-nearest-even rounding mode.
+                sym::nearbyintf32
+                | sym::nearbyintf64
+                | sym::rintf32
+                | sym::rintf64
+                | sym::roundevenf32
+                | sym::roundevenf64 => fx.bcx.ins().nearest(args[0]),
                 sym::sqrtf32 | sym::sqrtf64 => fx.bcx.ins().sqrt(args[0]),
                 _ => unreachable!(),
             };
@@ -413,7 +941,8 @@ fn codegen_float_intrinsic_call<'tcx>(
         }
 
         // These intrinsics aren't supported natively by Cranelift.
-        // Lower them to a libcall.
+        // Lower them to a libcall. This includes `round` as Cranelift only supports rounding
+        // half-way cases to even and not away from zero.
         sym::powif32 | sym::powif64 => {
             let input_tys: Vec<_> = vec![AbiParam::new(clif_ty), AbiParam::new(types::I32)];
             let ret_val = fx.lib_call(name, input_tys, vec![AbiParam::new(clif_ty)], &args)[0];
@@ -457,22 +986,40 @@ fn codegen_regular_intrinsic_call<'tcx>(
         sym::breakpoint => {
             intrinsic_args!(fx, args => (); intrinsic);
 
+            // This is `int3` on x86_64, `brk #0xf000` on AArch64 and `ebreak` on riscv64, which
+            // matches what LLVM emits for `llvm.debugtrap`.
             fx.bcx.ins().debugtrap();
         }
+        sym::prefetch_read_data
+        | sym::prefetch_write_data
+        | sym::prefetch_read_instruction
+        | sym::prefetch_write_instruction => {
+            intrinsic_args!(fx, args => (ptr, _locality); intrinsic);
+            let ptr = ptr.load_scalar(fx);
+
+            let locality = crate::constant::mir_operand_get_const_val(fx, &args[1].node)
+                .map(|locality| locality.to_i32())
+                .filter(|locality| (0..=3).contains(locality))
+                .unwrap_or_else(|| {
+                    fx.tcx.dcx().span_fatal(
+                        source_info.span,
+                        format!("locality argument for `{intrinsic}` is not a constant in 0..=3"),
+                    )
+                });
+
+            let is_write =
+                matches!(intrinsic, sym::prefetch_write_data | sym::prefetch_write_instruction);
+            let is_data = matches!(intrinsic, sym::prefetch_read_data | sym::prefetch_write_data);
+            codegen_prefetch(fx, ptr, is_write, locality as u8, is_data);
+        }
         sym::copy => {
             intrinsic_args!(fx, args => (src, dst, count); intrinsic);
             let src = src.load_scalar(fx);
             let dst = dst.load_scalar(fx);
             let count = count.load_scalar(fx);
 
-            let elem_ty = generic_args.type_at(0);
-            let elem_size: u64 = fx.layout_of(elem_ty).size.bytes();
-            assert_eq!(args.len(), 3);
-            let byte_amount =
-                if elem_size != 1 { fx.bcx.ins().imul_imm(count, elem_size as i64) } else { count };
-
-            // FIXME emit_small_memmove
-            fx.bcx.call_memmove(fx.target_config, dst, src, byte_amount);
+            let elem_layout = fx.layout_of(generic_args.type_at(0));
+            codegen_copy_elems(fx, dst, src, count, elem_layout, false);
         }
         sym::volatile_copy_memory | sym::volatile_copy_nonoverlapping_memory => {
             // NOTE: the volatile variants have src and dst swapped
@@ -544,7 +1091,18 @@ fn codegen_regular_intrinsic_call<'tcx>(
         sym::exact_div => {
             intrinsic_args!(fx, args => (x, y); intrinsic);
 
-            // FIXME trap on inexact
+            if fx.tcx.sess.ub_checks() {
+                let rem = crate::num::codegen_int_binop(fx, BinOp::Rem, x, y).load_scalar(fx);
+                let is_exact = fx.bcx.ins().icmp_imm(IntCC::Equal, rem, 0);
+                codegen_ub_check(
+                    fx,
+                    is_exact,
+                    "unsafe precondition(s) violated: exact_div requires the division to have no \
+                     remainder",
+                    Some(source_info.span),
+                );
+            }
+
             let res = crate::num::codegen_int_binop(fx, BinOp::Div, x, y);
             ret.write_cvalue(fx, res);
         }
@@ -567,7 +1125,12 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let layout = x.layout();
             let x = x.load_scalar(fx);
-            let res = fx.bcx.ins().rotl(x, y);
+            // Cranelift takes the rotation amount modulo the bit width like Rust does.
+            let res = if fx.bcx.func.dfg.value_type(x) == types::I128 {
+                crate::codegen_i128::codegen_i128_rotate(fx, x, y, true)
+            } else {
+                fx.bcx.ins().rotl(x, y)
+            };
             ret.write_cvalue(fx, CValue::by_val(res, layout));
         }
         sym::rotate_right => {
@@ -576,12 +1139,17 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let layout = x.layout();
             let x = x.load_scalar(fx);
-            let res = fx.bcx.ins().rotr(x, y);
+            // Cranelift takes the rotation amount modulo the bit width like Rust does.
+            let res = if fx.bcx.func.dfg.value_type(x) == types::I128 {
+                crate::codegen_i128::codegen_i128_rotate(fx, x, y, false)
+            } else {
+                fx.bcx.ins().rotr(x, y)
+            };
             ret.write_cvalue(fx, CValue::by_val(res, layout));
         }
 
-        // The only difference between offset and arith_offset is regarding UB. Because Cranelift
-        // doesn't have UB both are codegen'ed the same way
+        // Unlike `offset`, which MIR lowers to `BinOp::Offset`, `arith_offset` is allowed to wrap
+        // around, so it never needs a UB check.
         sym::arith_offset => {
             intrinsic_args!(fx, args => (base, offset); intrinsic);
             let offset = offset.load_scalar(fx);
@@ -605,7 +1173,16 @@ fn codegen_regular_intrinsic_call<'tcx>(
             fx.bcx.ins().band(ptr, mask);
         }
 
-        sym::write_bytes | sym::volatile_set_memory => {
+        sym::write_bytes => {
+            intrinsic_args!(fx, args => (dst, val, count); intrinsic);
+            let val = val.load_scalar(fx);
+            let count = count.load_scalar(fx);
+
+            let pointee_layout = fx.layout_of(dst.layout().ty.builtin_deref(true).unwrap());
+            let dst_ptr = dst.load_scalar(fx);
+            codegen_write_bytes(fx, dst_ptr, val, count, pointee_layout);
+        }
+        sym::volatile_set_memory => {
             intrinsic_args!(fx, args => (dst, val, count); intrinsic);
             let val = val.load_scalar(fx);
             let count = count.load_scalar(fx);
@@ -619,7 +1196,6 @@ fn codegen_regular_intrinsic_call<'tcx>(
             };
             let dst_ptr = dst.load_scalar(fx);
             // FIXME make the memset actually volatile when switching to emit_small_memset
-            // FIXME use emit_small_memset
             fx.bcx.call_memset(fx.target_config, dst_ptr, val, count);
         }
         sym::ctlz | sym::ctlz_nonzero => {
@@ -627,7 +1203,11 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let val = arg.load_scalar(fx);
 
             // FIXME trap on `ctlz_nonzero` with zero arg.
-            let res = fx.bcx.ins().clz(val);
+            let res = if fx.bcx.func.dfg.value_type(val) == types::I128 {
+                crate::codegen_i128::codegen_i128_clz(fx, val)
+            } else {
+                fx.bcx.ins().clz(val)
+            };
             let res = clif_intcast(fx, res, types::I32, false);
             let res = CValue::by_val(res, ret.layout());
             ret.write_cvalue(fx, res);
@@ -637,7 +1217,11 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let val = arg.load_scalar(fx);
 
             // FIXME trap on `cttz_nonzero` with zero arg.
-            let res = fx.bcx.ins().ctz(val);
+            let res = if fx.bcx.func.dfg.value_type(val) == types::I128 {
+                crate::codegen_i128::codegen_i128_ctz(fx, val)
+            } else {
+                fx.bcx.ins().ctz(val)
+            };
             let res = clif_intcast(fx, res, types::I32, false);
             let res = CValue::by_val(res, ret.layout());
             ret.write_cvalue(fx, res);
@@ -646,7 +1230,11 @@ fn codegen_regular_intrinsic_call<'tcx>(
             intrinsic_args!(fx, args => (arg); intrinsic);
             let val = arg.load_scalar(fx);
 
-            let res = fx.bcx.ins().popcnt(val);
+            let res = if fx.bcx.func.dfg.value_type(val) == types::I128 {
+                crate::codegen_i128::codegen_i128_popcnt(fx, val)
+            } else {
+                fx.bcx.ins().popcnt(val)
+            };
             let res = clif_intcast(fx, res, types::I32, false);
             let res = CValue::by_val(res, ret.layout());
             ret.write_cvalue(fx, res);
@@ -655,7 +1243,11 @@ fn codegen_regular_intrinsic_call<'tcx>(
             intrinsic_args!(fx, args => (arg); intrinsic);
             let val = arg.load_scalar(fx);
 
-            let res = fx.bcx.ins().bitrev(val);
+            let res = if fx.bcx.func.dfg.value_type(val) == types::I128 {
+                crate::codegen_i128::codegen_i128_bitrev(fx, val)
+            } else {
+                fx.bcx.ins().bitrev(val)
+            };
             let res = CValue::by_val(res, arg.layout());
             ret.write_cvalue(fx, res);
         }
@@ -663,10 +1255,10 @@ fn codegen_regular_intrinsic_call<'tcx>(
             intrinsic_args!(fx, args => (arg); intrinsic);
             let val = arg.load_scalar(fx);
 
-            let res = if fx.bcx.func.dfg.value_type(val) == types::I8 {
-                val
-            } else {
-                fx.bcx.ins().bswap(val)
+            let res = match fx.bcx.func.dfg.value_type(val) {
+                types::I8 => val,
+                types::I128 => crate::codegen_i128::codegen_i128_bswap(fx, val),
+                _ => fx.bcx.ins().bswap(val),
             };
             let res = CValue::by_val(res, arg.layout());
             ret.write_cvalue(fx, res);
@@ -713,21 +1305,45 @@ fn codegen_regular_intrinsic_call<'tcx>(
         sym::volatile_load | sym::unaligned_volatile_load => {
             intrinsic_args!(fx, args => (ptr); intrinsic);
 
-            // Cranelift treats loads as volatile by default
-            // FIXME correctly handle unaligned_volatile_load
             let inner_layout = fx.layout_of(ptr.layout().ty.builtin_deref(true).unwrap());
-            let val = CValue::by_ref(Pointer::new(ptr.load_scalar(fx)), inner_layout);
-            ret.write_cvalue(fx, val);
+            let ptr = ptr.load_scalar(fx);
+            if intrinsic == sym::unaligned_volatile_load && !has_unaligned_memory_access(fx) {
+                let tmp = CPlace::new_stack_slot(fx, inner_layout);
+                let tmp_addr = tmp.to_ptr().get_addr(fx);
+                codegen_bytewise_copy(fx, tmp_addr, ptr, inner_layout.size.bytes());
+                ret.write_cvalue(fx, tmp.to_cvalue(fx));
+            } else {
+                // Cranelift never removes or reorders loads and stores relative to each other, but
+                // when optimizations are enabled its alias analysis may reuse the result of an
+                // earlier load from the same address. It doesn't do this for atomic loads. On x86
+                // an `atomic_load` is a plain `mov`, so this doesn't cost anything there.
+                // FIXME On other architectures `atomic_load` adds barriers a volatile load doesn't
+                // need, like `ldar` on aarch64 and the fences around the load on riscv64.
+                // FIXME Volatile loads of types larger than 64 bits or which aren't scalars and
+                // unaligned volatile loads use a plain load and can still be merged.
+                let val = match intrinsic {
+                    sym::volatile_load => codegen_atomic_scalar_load(fx, ptr, inner_layout),
+                    _ => None,
+                };
+                let val = val.unwrap_or_else(|| CValue::by_ref(Pointer::new(ptr), inner_layout));
+                ret.write_cvalue(fx, val);
+            }
         }
         sym::volatile_store | sym::unaligned_volatile_store | sym::nontemporal_store => {
             intrinsic_args!(fx, args => (ptr, val); intrinsic);
             let ptr = ptr.load_scalar(fx);
 
-            // Cranelift treats stores as volatile by default
-            // FIXME correctly handle unaligned_volatile_store
+            // Cranelift never removes stores, so no fence is necessary here.
             // FIXME actually do nontemporal stores if requested
-            let dest = CPlace::for_ptr(Pointer::new(ptr), val.layout());
-            dest.write_cvalue(fx, val);
+            if intrinsic == sym::unaligned_volatile_store && !has_unaligned_memory_access(fx) {
+                let tmp = CPlace::new_stack_slot(fx, val.layout());
+                tmp.write_cvalue(fx, val);
+                let tmp_addr = tmp.to_ptr().get_addr(fx);
+                codegen_bytewise_copy(fx, ptr, tmp_addr, val.layout().size.bytes());
+            } else {
+                let dest = CPlace::for_ptr(Pointer::new(ptr), val.layout());
+                dest.write_cvalue(fx, val);
+            }
         }
 
         sym::pref_align_of
@@ -753,6 +1369,22 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let pointee_size: u64 = fx.layout_of(ty).size.bytes();
             let diff_bytes = fx.bcx.ins().isub(ptr, base);
+
+            // Whether both pointers are derived from the same allocation can't be checked here.
+            if fx.tcx.sess.ub_checks() && pointee_size > 1 {
+                let rem = fx.bcx.ins().srem_imm(diff_bytes, pointee_size as i64);
+                let is_multiple = fx.bcx.ins().icmp_imm(IntCC::Equal, rem, 0);
+                let msg = with_no_visible_paths!({
+                    with_no_trimmed_paths!({
+                        format!(
+                            "unsafe precondition(s) violated: ptr::offset_from requires the \
+                             distance between the pointers to be a multiple of the size of `{ty}`"
+                        )
+                    })
+                });
+                codegen_ub_check(fx, is_multiple, &msg, Some(source_info.span));
+            }
+
             // FIXME this can be an exact division.
             let val = if intrinsic == sym::ptr_offset_from_unsigned {
                 let usize_layout = fx.layout_of(fx.tcx.types.usize);
@@ -790,18 +1422,6 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let ty = generic_args.type_at(0);
             match ty.kind() {
-                ty::Uint(UintTy::U128) | ty::Int(IntTy::I128) => {
-                    // FIXME implement 128bit atomics
-                    if fx.tcx.is_compiler_builtins(LOCAL_CRATE) {
-                        // special case for compiler-builtins to avoid having to patch it
-                        crate::trap::trap_unimplemented(fx, "128bit atomics not yet supported");
-                        return Ok(());
-                    } else {
-                        fx.tcx
-                            .dcx()
-                            .span_fatal(source_info.span, "128bit atomics not yet supported");
-                    }
-                }
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, ty);
@@ -810,7 +1430,11 @@ fn codegen_regular_intrinsic_call<'tcx>(
             }
             let clif_ty = fx.clif_type(ty).unwrap();
 
-            let val = fx.bcx.ins().atomic_load(clif_ty, MemFlags::trusted(), ptr);
+            let val = if clif_ty == types::I128 {
+                codegen_atomic_load_128(fx, ptr)
+            } else {
+                fx.bcx.ins().atomic_load(clif_ty, MemFlags::trusted(), ptr)
+            };
 
             let val = CValue::by_val(val, fx.layout_of(ty));
             ret.write_cvalue(fx, val);
@@ -821,18 +1445,6 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let ty = generic_args.type_at(0);
             match ty.kind() {
-                ty::Uint(UintTy::U128) | ty::Int(IntTy::I128) => {
-                    // FIXME implement 128bit atomics
-                    if fx.tcx.is_compiler_builtins(LOCAL_CRATE) {
-                        // special case for compiler-builtins to avoid having to patch it
-                        crate::trap::trap_unimplemented(fx, "128bit atomics not yet supported");
-                        return Ok(());
-                    } else {
-                        fx.tcx
-                            .dcx()
-                            .span_fatal(source_info.span, "128bit atomics not yet supported");
-                    }
-                }
                 ty::Uint(_) | ty::Int(_) | ty::RawPtr(..) => {}
                 _ => {
                     report_atomic_type_validation_error(fx, intrinsic, source_info.span, ty);
@@ -842,7 +1454,11 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let val = val.load_scalar(fx);
 
-            fx.bcx.ins().atomic_store(MemFlags::trusted(), val, ptr);
+            if fx.clif_type(ty).unwrap() == types::I128 {
+                codegen_atomic_store_128(fx, ptr, val);
+            } else {
+                fx.bcx.ins().atomic_store(MemFlags::trusted(), val, ptr);
+            }
         }
         _ if intrinsic.as_str().starts_with("atomic_xchg") => {
             intrinsic_args!(fx, args => (ptr, new); intrinsic);
@@ -860,13 +1476,17 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let new = new.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Xchg, ptr, new);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Xchg, ptr, new);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
         }
         _ if intrinsic.as_str().starts_with("atomic_cxchg") => {
             // both atomic_cxchg_* and atomic_cxchgweak_*
+            // FIXME Cranelift doesn't have a weak compare-and-swap, so atomic_cxchgweak_* uses a
+            // strong compare-and-swap which never fails spuriously. This is correct, but misses
+            // out on a single LL/SC attempt without retry loop on architectures like AArch64 and
+            // RISC-V.
             intrinsic_args!(fx, args => (ptr, test_old, new); intrinsic);
             let ptr = ptr.load_scalar(fx);
 
@@ -882,7 +1502,11 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let test_old = test_old.load_scalar(fx);
             let new = new.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_cas(MemFlags::trusted(), ptr, test_old, new);
+            let old = if fx.clif_type(layout.ty).unwrap() == types::I128 {
+                codegen_atomic_cas_128(fx, ptr, test_old, new)
+            } else {
+                fx.bcx.ins().atomic_cas(MemFlags::trusted(), ptr, test_old, new)
+            };
             let is_eq = fx.bcx.ins().icmp(IntCC::Equal, old, test_old);
 
             let ret_val = CValue::by_val_pair(old, is_eq, ret.layout());
@@ -905,8 +1529,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let amount = amount.load_scalar(fx);
 
-            let old =
-                fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Add, ptr, amount);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Add, ptr, amount);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -927,8 +1550,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let amount = amount.load_scalar(fx);
 
-            let old =
-                fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Sub, ptr, amount);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Sub, ptr, amount);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -949,7 +1571,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::And, ptr, src);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::And, ptr, src);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -970,7 +1592,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Or, ptr, src);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Or, ptr, src);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -991,7 +1613,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Xor, ptr, src);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Xor, ptr, src);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1012,7 +1634,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Nand, ptr, src);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Nand, ptr, src);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1033,7 +1655,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Smax, ptr, src);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Smax, ptr, src);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1054,7 +1676,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Umax, ptr, src);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Umax, ptr, src);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1075,7 +1697,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Smin, ptr, src);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Smin, ptr, src);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1096,7 +1718,7 @@ fn codegen_regular_intrinsic_call<'tcx>(
 
             let src = src.load_scalar(fx);
 
-            let old = fx.bcx.ins().atomic_rmw(ty, MemFlags::trusted(), AtomicRmwOp::Umin, ptr, src);
+            let old = codegen_atomic_rmw(fx, ty, AtomicRmwOp::Umin, ptr, src);
 
             let old = CValue::by_val(old, layout);
             ret.write_cvalue(fx, old);
@@ -1145,7 +1767,10 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let data = data.load_scalar(fx);
             let _catch_fn = catch_fn.load_scalar(fx);
 
-            // FIXME once unwinding is supported, change this to actually catch panics
+            // FIXME once unwinding is supported, change this to actually catch panics. This needs
+            // Cranelift to support calls with an exceptional successor and landing pads, see
+            // https://github.com/bytecodealliance/wasmtime/issues/1677. Until then panics abort
+            // before reaching `catch_fn`.
             let f_sig = fx.bcx.func.import_signature(Signature {
                 call_conv: fx.target_config.default_call_conv,
                 params: vec![AbiParam::new(pointer_ty(fx.tcx))],
@@ -1206,7 +1831,6 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let rhs_ref = rhs_ref.load_scalar(fx);
 
             let size = fx.layout_of(generic_args.type_at(0)).layout.size();
-            // FIXME add and use emit_small_memcmp
             let is_eq_value = if size == Size::ZERO {
                 // No bytes means they're trivially equal
                 fx.bcx.ins().iconst(types::I8, 1)
@@ -1217,6 +1841,8 @@ fn codegen_regular_intrinsic_call<'tcx>(
                 let lhs_val = fx.bcx.ins().load(clty, flags, lhs_ref, 0);
                 let rhs_val = fx.bcx.ins().load(clty, flags, rhs_ref, 0);
                 fx.bcx.ins().icmp(IntCC::Equal, lhs_val, rhs_val)
+            } else if let Some(is_eq) = codegen_small_bytes_eq(fx, lhs_ref, rhs_ref, size.bytes()) {
+                is_eq
             } else {
                 // Just call `memcmp` (like slices do in core) when the
                 // size is too large or it's not a power-of-two.
@@ -1237,45 +1863,90 @@ fn codegen_regular_intrinsic_call<'tcx>(
             let rhs_ptr = rhs_ptr.load_scalar(fx);
             let bytes_val = bytes_val.load_scalar(fx);
 
-            let params = vec![AbiParam::new(fx.pointer_type); 3];
-            let returns = vec![AbiParam::new(types::I32)];
-            let args = &[lhs_ptr, rhs_ptr, bytes_val];
-            // Here we assume that the `memcmp` provided by the target is a NOP for size 0.
-            let cmp = fx.lib_call("memcmp", params, returns, args)[0];
+            let small_cmp = crate::optimize::peephole::maybe_known_iconst(&fx.bcx, bytes_val)
+                .and_then(|bytes| codegen_small_compare_bytes(fx, lhs_ptr, rhs_ptr, bytes as u64));
+            let cmp = if let Some(cmp) = small_cmp {
+                cmp
+            } else {
+                let params = vec![AbiParam::new(fx.pointer_type); 3];
+                let returns = vec![AbiParam::new(types::I32)];
+                let args = &[lhs_ptr, rhs_ptr, bytes_val];
+                // Here we assume that the `memcmp` provided by the target is a NOP for size 0.
+                fx.lib_call("memcmp", params, returns, args)[0]
+            };
             ret.write_cvalue(fx, CValue::by_val(cmp, ret.layout()));
         }
 
         sym::black_box => {
             intrinsic_args!(fx, args => (a); intrinsic);
 
-            // FIXME implement black_box semantics
-            ret.write_cvalue(fx, a);
+            // Cranelift doesn't have a compiler-only barrier, so roundtrip the value through a
+            // stack slot and read it back through a pointer returned by an out-of-line identity
+            // function. The optimizer can't see that the pointer still points to the stack slot,
+            // so it can neither look through the result nor remove the computation of the input.
+            let place = CPlace::new_stack_slot(fx, a.layout());
+            place.write_cvalue(fx, a);
+            let identity_func = *fx.cx.black_box_identity_func.get_or_insert_with(|| {
+                crate::common::create_identity_function(fx.module, fx.pointer_type)
+            });
+            let identity_func = fx.module.declare_func_in_func(identity_func, fx.bcx.func);
+            let addr = place.to_ptr().get_addr(fx);
+            let call = fx.bcx.ins().call(identity_func, &[addr]);
+            let opaque_addr = fx.bcx.inst_results(call)[0];
+            let val = CPlace::for_ptr(Pointer::new(opaque_addr), a.layout()).to_cvalue(fx);
+            ret.write_cvalue(fx, val);
         }
 
         // FIXME implement variadics in cranelift
-        sym::va_copy | sym::va_arg | sym::va_end => {
-            fx.tcx.dcx().span_fatal(
-                source_info.span,
-                "Defining variadic functions is not yet supported by Cranelift",
-            );
+        sym::va_arg => {
+            intrinsic_args!(fx, args => (ap); intrinsic);
+
+            let ap = ap.load_scalar(fx);
+            crate::abi::codegen_va_arg(fx, ap, ret, source_info.span);
+        }
+        sym::va_copy => {
+            intrinsic_args!(fx, args => (dest, src); intrinsic);
+
+            // The `VaListImpl` only contains offsets and pointers into the register save area and
+            // the stack arguments, so a plain copy is enough.
+            let layout = fx.layout_of(src.layout().ty.builtin_deref(true).unwrap());
+            let dest = CPlace::for_ptr(Pointer::new(dest.load_scalar(fx)), layout);
+            dest.write_cvalue(fx, CValue::by_ref(Pointer::new(src.load_scalar(fx)), layout));
         }
+        sym::va_end => {
+            intrinsic_args!(fx, args => (_ap); intrinsic);
 
-        // Unimplemented intrinsics must have a fallback body. The fallback body is obtained
-        // by converting the `InstanceKind::Intrinsic` to an `InstanceKind::Item`.
-        _ => {
-            let intrinsic = fx.tcx.intrinsic(instance.def_id()).unwrap();
-            if intrinsic.must_be_overridden {
-                span_bug!(
-                    source_info.span,
-                    "intrinsic {} must be overridden by codegen_cranelift, but isn't",
-                    intrinsic.name,
-                );
-            }
-            return Err(Instance::new(instance.def_id(), instance.args));
+            // Nothing to clean up.
         }
+
+        _ => return codegen_unsupported_intrinsic(fx, instance, source_info.span),
     }
 
     let ret_block = fx.get_block(destination.unwrap());
     fx.bcx.ins().jump(ret_block, &[]);
     Ok(())
 }
+
+/// Unimplemented intrinsics must have a fallback body. The fallback body is obtained by converting
+/// the `InstanceKind::Intrinsic` to an `InstanceKind::Item`. Error out if there is none.
+fn codegen_unsupported_intrinsic<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    instance: Instance<'tcx>,
+    span: Span,
+) -> Result<(), Instance<'tcx>> {
+    let intrinsic = fx.tcx.intrinsic(instance.def_id()).unwrap();
+    // Intrinsics declared in an `extern "rust-intrinsic"` block don't have a body at all.
+    if intrinsic.must_be_overridden || fx.tcx.is_foreign_item(instance.def_id()) {
+        fx.tcx
+            .dcx()
+            .struct_span_fatal(
+                span,
+                format!("intrinsic `{}` is not yet supported by Cranelift", intrinsic.name),
+            )
+            .with_note(
+                "please report this at https://github.com/rust-lang/rustc_codegen_cranelift/issues",
+            )
+            .emit();
+    }
+    Err(Instance::new(instance.def_id(), instance.args))
+}
@@ -6,6 +6,19 @@
 use super::*;
 use crate::prelude::*;
 
+/// Whether Cranelift can lower the saturating add and sub instructions for the given vector type.
+fn has_vector_saturating_op(fx: &FunctionCx<'_, '_, '_>, vector_ty: Type) -> bool {
+    if !vector_ty.lane_type().is_int() {
+        return false;
+    }
+    match &*fx.tcx.sess.target.arch {
+        "aarch64" => true,
+        // SSE2 only has saturating arithmetic for 8 and 16 bit lanes.
+        "x86_64" => vector_ty.lane_bits() <= 16,
+        _ => false,
+    }
+}
+
 fn report_simd_type_validation_error(
     fx: &mut FunctionCx<'_, '_, '_>,
     intrinsic: Symbol,
@@ -35,6 +48,29 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
                 return;
             }
 
+            if let (Abi::Vector { .. }, Abi::Vector { .. }) = (a.layout().abi, ret.layout().abi) {
+                let from_ty = clif_vector_type(fx.tcx, a.layout());
+                let to_ty = clif_vector_type(fx.tcx, ret.layout());
+                if supports_vector_cast(fx, from_ty, to_ty) {
+                    let (_, lane_ty) = a.layout().ty.simd_size_and_type(fx.tcx);
+                    let (_, ret_lane_ty) = ret.layout().ty.simd_size_and_type(fx.tcx);
+                    let a = a.load_scalar(fx);
+                    let res = clif_int_or_float_cast(
+                        fx,
+                        a,
+                        type_sign(lane_ty),
+                        to_ty,
+                        type_sign(ret_lane_ty),
+                    );
+                    ret.write_cvalue(fx, CValue::by_val(res, ret.layout()));
+                    return;
+                }
+            }
+
+            if codegen_simd_int_resize(fx, a, ret) {
+                return;
+            }
+
             simd_for_each_lane(fx, a, ret, &|fx, lane_ty, ret_lane_ty, lane| {
                 let ret_lane_clif_ty = fx.clif_type(ret_lane_ty).unwrap();
 
@@ -139,29 +175,15 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             assert_eq!(x.layout(), y.layout());
             let layout = x.layout();
 
-            let (lane_count, lane_ty) = layout.ty.simd_size_and_type(fx.tcx);
+            let (_lane_count, lane_ty) = layout.ty.simd_size_and_type(fx.tcx);
             let (ret_lane_count, ret_lane_ty) = ret.layout().ty.simd_size_and_type(fx.tcx);
 
             assert_eq!(lane_ty, ret_lane_ty);
             assert_eq!(idx.len() as u64, ret_lane_count);
 
-            let total_len = lane_count * 2;
-
             let indexes = idx.iter().map(|idx| idx.unwrap_leaf().to_u32()).collect::<Vec<u32>>();
 
-            for &idx in &indexes {
-                assert!(u64::from(idx) < total_len, "idx {} out of range 0..{}", idx, total_len);
-            }
-
-            for (out_idx, in_idx) in indexes.into_iter().enumerate() {
-                let in_lane = if u64::from(in_idx) < lane_count {
-                    x.value_lane(fx, in_idx.into())
-                } else {
-                    y.value_lane(fx, u64::from(in_idx) - lane_count)
-                };
-                let out_lane = ret.place_lane(fx, u64::try_from(out_idx).unwrap());
-                out_lane.write_cvalue(fx, in_lane);
-            }
+            codegen_simd_shuffle(fx, intrinsic, span, x, y, ret, &indexes);
         }
 
         // simd_shuffle<T, I, U>(x: T, y: T, idx: I) -> U
@@ -205,14 +227,12 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             assert_eq!(x.layout(), y.layout());
             let layout = x.layout();
 
-            let (lane_count, lane_ty) = layout.ty.simd_size_and_type(fx.tcx);
+            let (_lane_count, lane_ty) = layout.ty.simd_size_and_type(fx.tcx);
             let (ret_lane_count, ret_lane_ty) = ret.layout().ty.simd_size_and_type(fx.tcx);
 
             assert_eq!(lane_ty, ret_lane_ty);
             assert_eq!(u64::from(n), ret_lane_count);
 
-            let total_len = lane_count * 2;
-
             let indexes = {
                 use rustc_middle::mir::interpret::*;
                 let idx_const = match &idx.node {
@@ -242,24 +262,12 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
                             &idx_bytes[4 * i..4 * i + 4],
                         )
                         .expect("read_target_uint");
-                        u16::try_from(idx).expect("try_from u32")
+                        u32::try_from(idx).unwrap()
                     })
-                    .collect::<Vec<u16>>()
+                    .collect::<Vec<u32>>()
             };
 
-            for &idx in &indexes {
-                assert!(u64::from(idx) < total_len, "idx {} out of range 0..{}", idx, total_len);
-            }
-
-            for (out_idx, in_idx) in indexes.into_iter().enumerate() {
-                let in_lane = if u64::from(in_idx) < lane_count {
-                    x.value_lane(fx, in_idx.into())
-                } else {
-                    y.value_lane(fx, u64::from(in_idx) - lane_count)
-                };
-                let out_lane = ret.place_lane(fx, u64::try_from(out_idx).unwrap());
-                out_lane.write_cvalue(fx, in_lane);
-            }
+            codegen_simd_shuffle(fx, intrinsic, span, x, y, ret, &indexes);
         }
 
         sym::simd_insert => {
@@ -596,6 +604,17 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
                 return;
             }
 
+            // fabs only clears the sign bit, which all these backends can do on whole vectors.
+            if intrinsic == sym::simd_fabs
+                && register_vector_type(fx.tcx, a.layout()).is_some()
+                && matches!(&*fx.tcx.sess.target.arch, "x86_64" | "aarch64" | "s390x")
+            {
+                let a = a.load_scalar(fx);
+                let res = fx.bcx.ins().fabs(a);
+                ret.write_cvalue(fx, CValue::by_val(res, ret.layout()));
+                return;
+            }
+
             simd_for_each_lane(fx, a, ret, &|fx, lane_ty, _ret_lane_ty, lane| {
                 match lane_ty.kind() {
                     ty::Float(_) => {}
@@ -842,7 +861,7 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
                         format!(
                             "invalid monomorphization of `simd_select_bitmask` intrinsic: \
                             cannot accept `{}` as mask, expected `u{}` or `[u8; {}]`",
-                            ret.layout().ty,
+                            m.layout().ty,
                             expected_int_bits,
                             expected_bytes
                         ),
@@ -957,7 +976,22 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
                 _ => unreachable!(),
             };
 
-            // FIXME use vector instructions when possible
+            let vector_ty = register_vector_type(fx.tcx, x.layout());
+            if vector_ty.is_some_and(|ty| has_vector_saturating_op(fx, ty)) {
+                let (_, lane_ty) = x.layout().ty.simd_size_and_type(fx.tcx);
+                let x = x.load_scalar(fx);
+                let y = y.load_scalar(fx);
+                let res = match (bin_op, type_sign(lane_ty)) {
+                    (BinOp::Add, false) => fx.bcx.ins().uadd_sat(x, y),
+                    (BinOp::Add, true) => fx.bcx.ins().sadd_sat(x, y),
+                    (BinOp::Sub, false) => fx.bcx.ins().usub_sat(x, y),
+                    (BinOp::Sub, true) => fx.bcx.ins().ssub_sat(x, y),
+                    _ => unreachable!(),
+                };
+                ret.write_cvalue(fx, CValue::by_val(res, ret.layout()));
+                return;
+            }
+
             simd_pair_for_each_lane_typed(fx, x, y, ret, &|fx, x_lane, y_lane| {
                 crate::num::codegen_saturating_int_binop(fx, bin_op, x_lane, y_lane)
             });
@@ -1039,6 +1073,8 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
             let lane_clif_ty = fx.clif_type(val_lane_ty).unwrap();
             let ret_lane_layout = fx.layout_of(ret_lane_ty);
 
+            // Cranelift doesn't have gather or scatter instructions, so load every enabled lane
+            // separately. Disabled lanes are taken from `val`.
             for lane_idx in 0..ptr_lane_count {
                 let val_lane = val.value_lane(fx, lane_idx).load_scalar(fx);
                 let ptr_lane = ptr.value_lane(fx, lane_idx).load_scalar(fx);
@@ -1158,3 +1194,122 @@ pub(super) fn codegen_simd_intrinsic_call<'tcx>(
     let ret_block = fx.get_block(target);
     fx.bcx.ins().jump(ret_block, &[]);
 }
+
+/// Sign extend, zero extend or truncate the integer lanes of `a` into `ret` using vector
+/// instructions when one side fits in a single vector register and the other side has lanes twice
+/// as wide. Returns `false` if the cast has to be done lane by lane instead.
+fn codegen_simd_int_resize<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    a: CValue<'tcx>,
+    ret: CPlace<'tcx>,
+) -> bool {
+    let (lane_count, lane_ty) = a.layout().ty.simd_size_and_type(fx.tcx);
+    let (_, ret_lane_ty) = ret.layout().ty.simd_size_and_type(fx.tcx);
+    if !lane_ty.is_integral()
+        || !ret_lane_ty.is_integral()
+        || fx.tcx.sess.target.endian != Endian::Little
+    {
+        return false;
+    }
+    let lane_size = fx.layout_of(lane_ty).size.bytes();
+    let ret_lane_size = fx.layout_of(ret_lane_ty).size.bytes();
+
+    match (register_vector_type(fx.tcx, a.layout()), register_vector_type(fx.tcx, ret.layout())) {
+        (Some(_), None) if ret_lane_size == lane_size * 2 && ret.layout().size.bytes() == 32 => {
+            // Widen the lower and the upper half of the lanes into separate vectors.
+            let a = a.load_scalar(fx);
+            let (lo, hi) = if type_sign(lane_ty) {
+                (fx.bcx.ins().swiden_low(a), fx.bcx.ins().swiden_high(a))
+            } else {
+                (fx.bcx.ins().uwiden_low(a), fx.bcx.ins().uwiden_high(a))
+            };
+            let res = CPlace::new_stack_slot(fx, ret.layout());
+            let ptr = res.to_ptr();
+            ptr.store(fx, lo, MemFlags::trusted());
+            ptr.offset_i64(fx, 16).store(fx, hi, MemFlags::trusted());
+            ret.write_cvalue(fx, res.to_cvalue(fx));
+            true
+        }
+        (None, Some(ret_vector_ty))
+            if lane_size == ret_lane_size * 2 && a.layout().size.bytes() == 32 =>
+        {
+            // Truncate by picking the low bytes of every lane out of both halves of `a`.
+            let (ptr, _) = a.force_stack(fx);
+            let lo = ptr.load(fx, types::I8X16, MemFlags::trusted());
+            let hi = ptr.offset_i64(fx, 16).load(fx, types::I8X16, MemFlags::trusted());
+            let mask = (0..lane_count)
+                .flat_map(|lane| {
+                    (0..ret_lane_size)
+                        .map(move |byte| u8::try_from(lane * lane_size + byte).unwrap())
+                })
+                .collect::<Vec<u8>>();
+            let mask = fx.bcx.func.dfg.immediates.push(mask.into());
+            let res = fx.bcx.ins().shuffle(lo, hi, mask);
+            let res = codegen_bitcast(fx, ret_vector_ty, res);
+            ret.write_cvalue(fx, CValue::by_val(res, ret.layout()));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Shuffle the lanes of `x` and `y` into `ret`. Indexes `0..lane_count` refer to the lanes of `x`
+/// and indexes `lane_count..2 * lane_count` to the lanes of `y`.
+fn codegen_simd_shuffle<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    intrinsic: Symbol,
+    span: Span,
+    x: CValue<'tcx>,
+    y: CValue<'tcx>,
+    ret: CPlace<'tcx>,
+    indexes: &[u32],
+) {
+    let (lane_count, lane_ty) = x.layout().ty.simd_size_and_type(fx.tcx);
+    let total_len = lane_count * 2;
+
+    for (i, &idx) in indexes.iter().enumerate() {
+        if u64::from(idx) >= total_len {
+            fx.tcx.dcx().span_fatal(
+                span,
+                format!(
+                    "invalid monomorphization of `{}` intrinsic: shuffle index #{} is out of \
+                    bounds (limit {})",
+                    intrinsic, i, total_len
+                ),
+            );
+        }
+    }
+
+    let vector_ty = register_vector_type(fx.tcx, x.layout());
+    if vector_ty.is_some() && vector_ty == register_vector_type(fx.tcx, ret.layout()) {
+        // Shuffle the bytes of both vectors using a single shuffle instruction.
+        let lane_size = fx.layout_of(lane_ty).size.bytes();
+        let mask = indexes
+            .iter()
+            .flat_map(|&idx| {
+                (0..lane_size)
+                    .map(move |byte| u8::try_from(u64::from(idx) * lane_size + byte).unwrap())
+            })
+            .collect::<Vec<u8>>();
+        let mask = fx.bcx.func.dfg.immediates.push(mask.into());
+
+        let x = x.load_scalar(fx);
+        let x = codegen_bitcast(fx, types::I8X16, x);
+        let y = y.load_scalar(fx);
+        let y = codegen_bitcast(fx, types::I8X16, y);
+        let res = fx.bcx.ins().shuffle(x, y, mask);
+        let res = codegen_bitcast(fx, vector_ty.unwrap(), res);
+        ret.write_cvalue(fx, CValue::by_val(res, ret.layout()));
+        return;
+    }
+
+    for (out_idx, &in_idx) in indexes.iter().enumerate() {
+        let in_lane = if u64::from(in_idx) < lane_count {
+            x.value_lane(fx, in_idx.into())
+        } else {
+            y.value_lane(fx, u64::from(in_idx) - lane_count)
+        };
+        let out_lane = ret.place_lane(fx, u64::try_from(out_idx).unwrap());
+        out_lane.write_cvalue(fx, in_lane);
+    }
+}
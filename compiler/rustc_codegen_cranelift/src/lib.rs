@@ -16,6 +16,7 @@
 #[macro_use]
 extern crate rustc_middle;
 extern crate rustc_ast;
+extern crate rustc_attr;
 extern crate rustc_codegen_ssa;
 extern crate rustc_data_structures;
 extern crate rustc_errors;
@@ -34,21 +35,28 @@
 
 use std::any::Any;
 use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::settings::{self, Configurable};
+use cranelift_module::DataId;
 use rustc_codegen_ssa::traits::CodegenBackend;
 use rustc_codegen_ssa::CodegenResults;
 use rustc_data_structures::profiling::SelfProfilerRef;
 use rustc_errors::ErrorGuaranteed;
 use rustc_metadata::EncodedMetadata;
 use rustc_middle::dep_graph::{WorkProduct, WorkProductId};
-use rustc_session::config::OutputFilenames;
+use rustc_session::config::{OutputFilenames, OutputType};
 use rustc_session::Session;
 use rustc_span::{sym, Symbol};
+use rustc_target::spec::{CodeModel, RelocModel};
 
 pub use crate::config::*;
+pub use crate::driver::aot::compile_codegen_unit;
+#[cfg(feature = "jit")]
+pub use crate::driver::jit::{with_jit_state, JitState};
 use crate::prelude::*;
 
 mod abi;
@@ -127,10 +135,26 @@ struct CodegenCx {
     profiler: SelfProfilerRef,
     output_filenames: Arc<OutputFilenames>,
     should_write_ir: bool,
+    should_write_asm: bool,
+    /// Directory to write the clif ir of every function to for `-Cllvm-args=emit_clif=<dir>`.
+    clif_dump_dir: Option<PathBuf>,
+    /// Compile time of every function for `-Cllvm-args=timings`.
+    function_timings: Option<Vec<(String, Duration)>>,
+    /// Disassembly of all compiled functions for `--emit=asm`.
+    assembly: String,
     global_asm: String,
     inline_asm_index: Cell<usize>,
     debug_context: Option<DebugContext>,
     cgu_name: Symbol,
+    /// Statics marked `#[used(linker)]` which must not be removed by the linker.
+    used_statics: Vec<DataId>,
+    /// `TargetIsa`s for functions with `#[target_feature]` or `#[optimize]`, keyed by their enabled
+    /// features and `opt_level` override.
+    fn_isas: FxHashMap<(Vec<Symbol>, Option<settings::OptLevel>), Arc<dyn TargetIsa + 'static>>,
+    /// Intrinsics to treat as unimplemented for `CG_CLIF_UNSUPPORTED_INTRINSICS`.
+    unsupported_intrinsics: Vec<Symbol>,
+    /// Function used by `black_box` to hide a pointer from the optimizer, created on first use.
+    black_box_identity_func: Option<FuncId>,
 }
 
 impl CodegenCx {
@@ -146,14 +170,28 @@ fn new(tcx: TyCtxt<'_>, isa: &dyn TargetIsa, debug_info: bool, cgu_name: Symbol)
             profiler: tcx.prof.clone(),
             output_filenames: tcx.output_filenames(()).clone(),
             should_write_ir: crate::pretty_clif::should_write_ir(tcx),
+            should_write_asm: tcx.sess.opts.output_types.contains_key(&OutputType::Assembly),
+            clif_dump_dir: None,
+            function_timings: None,
+            assembly: String::new(),
             global_asm: String::new(),
             inline_asm_index: Cell::new(0),
             debug_context,
             cgu_name,
+            used_statics: Vec::new(),
+            fn_isas: FxHashMap::default(),
+            unsupported_intrinsics: Vec::new(),
+            black_box_identity_func: None,
         }
     }
 }
 
+/// The Cranelift codegen backend.
+///
+/// Tools embedding the backend can construct it using `CraneliftCodegenBackend::default()` and
+/// pass it to rustc through `rustc_interface::Config::make_codegen_backend`. When `config` is
+/// `None` it is initialized from `-Cllvm-args` in [`CodegenBackend::init`].
+#[derive(Default)]
 pub struct CraneliftCodegenBackend {
     pub config: RefCell<Option<BackendConfig>>,
 }
@@ -165,7 +203,8 @@ fn locale_resource(&self) -> &'static str {
     }
 
     fn init(&self, sess: &Session) {
-        use rustc_session::config::{InstrumentCoverage, Lto};
+        use rustc_session::config::{DebugInfo, InstrumentCoverage, Lto};
+        use rustc_target::spec::{SanitizerSet, SplitDebuginfo};
         match sess.lto() {
             Lto::No | Lto::ThinLocal => {}
             Lto::Thin | Lto::Fat => {
@@ -173,11 +212,44 @@ fn init(&self, sess: &Session) {
             }
         }
 
+        // FIXME support split DWARF. This requires gimli to support writing the address indices
+        // (DW_FORM_addrx) necessary to reference the skeleton unit's .debug_addr from the
+        // unrelocated .dwo file.
+        if sess.opts.debuginfo != DebugInfo::None
+            && sess.split_debuginfo() != SplitDebuginfo::Off
+            && sess.target_can_use_split_dwarf()
+        {
+            sess.dcx().warn(
+                "split DWARF is not supported. Debuginfo will be kept in the object files instead.",
+            );
+        }
+
         if sess.opts.cg.instrument_coverage() != InstrumentCoverage::No {
             sess.dcx()
                 .fatal("`-Cinstrument-coverage` is LLVM specific and not supported by Cranelift");
         }
 
+        // FIXME implement CFI. Both the LLVM CFI scheme and KCFI need type ids attached to
+        // functions (through type metadata and LTO or through data in front of the function entry
+        // respectively), which Cranelift can't emit. Silently producing unprotected code would be
+        // worse than refusing to compile.
+        for sanitizer in [SanitizerSet::CFI, SanitizerSet::KCFI] {
+            if sess.opts.unstable_opts.sanitizer.contains(sanitizer) {
+                sess.dcx()
+                    .fatal(format!("`-Zsanitizer={sanitizer}` is not yet supported by Cranelift"));
+            }
+        }
+
+        // FIXME use profile data for block layout. The profile data is keyed by the instrumentation
+        // LLVM inserted for `-Cprofile-generate`, which can't be mapped back to MIR basic blocks.
+        // In addition Cranelift only supports marking blocks as cold, not block frequencies.
+        if let Some(profile_use) = &sess.opts.cg.profile_use {
+            sess.dcx().warn(format!(
+                "`-Cprofile-use` is not supported by Cranelift. Ignoring profile data from `{}`.",
+                profile_use.display(),
+            ));
+        }
+
         let mut config = self.config.borrow_mut();
         if config.is_none() {
             let new_config = BackendConfig::from_opts(&sess.opts.cg.llvm_args)
@@ -255,13 +327,51 @@ fn target_triple(sess: &Session) -> target_lexicon::Triple {
     }
 }
 
+/// Map `-Copt-level` to the value of Cranelift's `opt_level` setting.
+fn cranelift_opt_level(opt_level: rustc_session::config::OptLevel) -> &'static str {
+    use rustc_session::config::OptLevel;
+    match opt_level {
+        OptLevel::No | OptLevel::Less => "none",
+        OptLevel::Default | OptLevel::Aggressive => "speed",
+        OptLevel::Size | OptLevel::SizeMin => "speed_and_size",
+    }
+}
+
 fn build_isa(sess: &Session, backend_config: &BackendConfig) -> Arc<dyn TargetIsa + 'static> {
     use target_lexicon::BinaryFormat;
 
     let target_triple = crate::target_triple(sess);
 
+    // Cranelift can compile WebAssembly to native code, but has no backend producing WebAssembly.
+    // FIXME support wasm targets once Cranelift can emit WebAssembly modules
+    if let target_lexicon::Architecture::Wasm32 | target_lexicon::Architecture::Wasm64 =
+        target_triple.architecture
+    {
+        sess.dcx().fatal(format!(
+            "can't compile for {target_triple}: Cranelift doesn't support emitting WebAssembly"
+        ));
+    }
+
     let mut flags_builder = settings::builder();
-    flags_builder.enable("is_pic").unwrap();
+    // Only avoid position independent code when explicitly requested. The ARM specific ropi and
+    // rwpi models need code that can be moved too.
+    // FIXME rwpi and ropi-rwpi also need data to be addressed relative to a static base register
+    let is_pic = match sess.relocation_model() {
+        RelocModel::Static | RelocModel::DynamicNoPic => false,
+        RelocModel::Pic
+        | RelocModel::Pie
+        | RelocModel::Ropi
+        | RelocModel::Rwpi
+        | RelocModel::RopiRwpi => true,
+    };
+    if is_pic && sess.code_model() == Some(CodeModel::Large) {
+        // Cranelift can't use 64bit GOT offsets, so GOT entries beyond 2GB would be unreachable.
+        sess.dcx().fatal(
+            "`-Ccode-model=large` is only supported by Cranelift together with \
+             `-Crelocation-model=static`",
+        );
+    }
+    flags_builder.set("is_pic", if is_pic { "true" } else { "false" }).unwrap();
     let enable_verifier = if backend_config.enable_verifier { "true" } else { "false" };
     flags_builder.set("enable_verifier", enable_verifier).unwrap();
     flags_builder.set("regalloc_checker", enable_verifier).unwrap();
@@ -273,6 +383,9 @@ fn build_isa(sess: &Session, backend_config: &BackendConfig) -> Arc<dyn TargetIs
         .set("preserve_frame_pointers", if preserve_frame_pointer { "true" } else { "false" })
         .unwrap();
 
+    // Cranelift only implements the general dynamic TLS model for ELF. It is valid for any
+    // relocation model, so it is also used when local-exec or initial-exec would suffice.
+    // FIXME support emulated TLS for targets without native TLS support
     let tls_model = match target_triple.binary_format {
         BinaryFormat::Elf => "elf_gd",
         BinaryFormat::Macho => "macho",
@@ -283,16 +396,7 @@ fn build_isa(sess: &Session, backend_config: &BackendConfig) -> Arc<dyn TargetIs
 
     flags_builder.set("enable_llvm_abi_extensions", "true").unwrap();
 
-    use rustc_session::config::OptLevel;
-    match sess.opts.optimize {
-        OptLevel::No => {
-            flags_builder.set("opt_level", "none").unwrap();
-        }
-        OptLevel::Less | OptLevel::Default => {}
-        OptLevel::Size | OptLevel::SizeMin | OptLevel::Aggressive => {
-            flags_builder.set("opt_level", "speed_and_size").unwrap();
-        }
-    }
+    flags_builder.set("opt_level", cranelift_opt_level(sess.opts.optimize)).unwrap();
 
     if let target_lexicon::Architecture::Aarch64(_)
     | target_lexicon::Architecture::Riscv64(_)
@@ -308,9 +412,22 @@ fn build_isa(sess: &Session, backend_config: &BackendConfig) -> Arc<dyn TargetIs
         flags_builder.set("enable_probestack", "false").unwrap();
     }
 
+    // Shared settings have to be set before creating the flags. Any other setting is assumed to
+    // be target specific and set on the isa builder below.
+    let mut isa_flags = vec![];
+    for (name, value) in &backend_config.cranelift_flags {
+        match flags_builder.set(name, value) {
+            Ok(()) => {}
+            Err(settings::SetError::BadName(_)) => isa_flags.push((name, value)),
+            Err(err) => {
+                sess.dcx().fatal(format!("failed to set Cranelift flag `{name}={value}`: {err}"))
+            }
+        }
+    }
+
     let flags = settings::Flags::new(flags_builder);
 
-    let isa_builder = match sess.opts.cg.target_cpu.as_deref() {
+    let mut isa_builder = match sess.opts.cg.target_cpu.as_deref() {
         Some("native") => cranelift_native::builder_with_options(true).unwrap(),
         Some(value) => {
             let mut builder =
@@ -337,6 +454,139 @@ fn build_isa(sess: &Session, backend_config: &BackendConfig) -> Arc<dyn TargetIs
         }
     };
 
+    for (name, value) in isa_flags {
+        match isa_builder.set(name, value) {
+            Ok(()) => {}
+            Err(settings::SetError::BadName(_)) => {
+                sess.dcx().fatal(format!("unknown Cranelift flag `{name}`"))
+            }
+            Err(err) => {
+                sess.dcx().fatal(format!("failed to set Cranelift flag `{name}={value}`: {err}"))
+            }
+        }
+    }
+
+    match isa_builder.finish(flags) {
+        Ok(target_isa) => target_isa,
+        Err(err) => sess.dcx().fatal(format!("failed to build TargetIsa: {}", err)),
+    }
+}
+
+/// The target features directly implied by each x86 target feature, matching LLVM.
+const X86_IMPLIED_TARGET_FEATURES: &[(&str, &[&str])] = &[
+    ("aes", &["sse2"]),
+    ("avx", &["sse4.2"]),
+    ("avx2", &["avx"]),
+    ("avx512bf16", &["avx512bw"]),
+    ("avx512bitalg", &["avx512bw"]),
+    ("avx512bw", &["avx512f"]),
+    ("avx512cd", &["avx512f"]),
+    ("avx512dq", &["avx512f"]),
+    ("avx512f", &["avx2", "f16c", "fma"]),
+    ("avx512fp16", &["avx512bw", "avx512dq", "avx512vl"]),
+    ("avx512ifma", &["avx512f"]),
+    ("avx512vbmi", &["avx512bw"]),
+    ("avx512vbmi2", &["avx512bw"]),
+    ("avx512vl", &["avx512f"]),
+    ("avx512vnni", &["avx512f"]),
+    ("avx512vp2intersect", &["avx512f"]),
+    ("avx512vpopcntdq", &["avx512f"]),
+    ("avxifma", &["avx2"]),
+    ("avxneconvert", &["avx2"]),
+    ("avxvnni", &["avx2"]),
+    ("avxvnniint16", &["avx2"]),
+    ("avxvnniint8", &["avx2"]),
+    ("f16c", &["avx"]),
+    ("fma", &["avx"]),
+    ("gfni", &["sse2"]),
+    ("pclmulqdq", &["sse2"]),
+    ("sha", &["sse2"]),
+    ("sse2", &["sse"]),
+    ("sse3", &["sse2"]),
+    ("sse4.1", &["ssse3"]),
+    ("sse4.2", &["sse4.1"]),
+    ("sse4a", &["sse3"]),
+    ("ssse3", &["sse3"]),
+    ("vaes", &["aes", "avx2"]),
+    ("vpclmulqdq", &["avx", "pclmulqdq"]),
+    ("xsavec", &["xsave"]),
+    ("xsaveopt", &["xsave"]),
+    ("xsaves", &["xsave"]),
+];
+
+/// Returns the target features directly implied by `feature`.
+///
+/// FIXME add the implications for other architectures once Cranelift supports any of their
+/// optional target features
+fn implied_target_features(sess: &Session, feature: &str) -> &'static [&'static str] {
+    let implied_features = match &*sess.target.arch {
+        "x86" | "x86_64" => X86_IMPLIED_TARGET_FEATURES,
+        _ => &[],
+    };
+    implied_features
+        .iter()
+        .find(|&&(name, _)| name == feature)
+        .map_or(&[], |&(_, implied_features)| implied_features)
+}
+
+/// Build a `TargetIsa` for a function with `#[target_feature(enable = "...")]` or `#[optimize]` by
+/// enabling the given features on top of the isa flags of the module wide `TargetIsa` and
+/// overriding its `opt_level` if requested.
+///
+/// Target features that Cranelift doesn't know about are ignored with a warning, in which case
+/// the function is codegened as if the feature wasn't enabled.
+fn build_isa_for_fn(
+    sess: &Session,
+    isa: &dyn TargetIsa,
+    target_features: &[Symbol],
+    opt_level: Option<settings::OptLevel>,
+) -> Arc<dyn TargetIsa + 'static> {
+    let mut isa_builder = cranelift_codegen::isa::lookup(isa.triple().clone()).unwrap();
+    for isa_flag in isa.isa_flags() {
+        isa_builder.set(isa_flag.name, &isa_flag.value_string()).unwrap();
+    }
+
+    // Cranelift doesn't know about implied target features, so enable them explicitly.
+    let mut features = target_features.iter().map(|feature| feature.as_str()).collect::<Vec<_>>();
+    let mut i = 0;
+    while let Some(&feature) = features.get(i) {
+        for &implied_feature in implied_target_features(sess, feature) {
+            if !features.contains(&implied_feature) {
+                features.push(implied_feature);
+            }
+        }
+        i += 1;
+    }
+
+    for (i, feature) in features.into_iter().enumerate() {
+        // Cranelift names its isa flags `has_<feature>` with any dots removed.
+        let flag_name = format!("has_{}", feature.replace('.', ""));
+        let explicitly_enabled = i < target_features.len();
+        // Features which are enabled for the whole crate anyway, like sse2 on x86_64, and implied
+        // features without a Cranelift counterpart are skipped silently.
+        if isa_builder.enable(&flag_name).is_err()
+            && explicitly_enabled
+            && !sess.unstable_target_features.contains(&Symbol::intern(feature))
+        {
+            sess.dcx().warn(format!(
+                "the target feature `{feature}` isn't currently supported by Cranelift, \
+                compiling as if it wasn't enabled"
+            ));
+        }
+    }
+
+    let flags = match opt_level {
+        Some(opt_level) => {
+            let mut flags_builder = settings::builder();
+            for flag in isa.flags().iter() {
+                flags_builder.set(flag.name, &flag.value_string()).unwrap();
+            }
+            flags_builder.set("opt_level", &opt_level.to_string()).unwrap();
+            settings::Flags::new(flags_builder)
+        }
+        None => isa.flags().clone(),
+    };
+
     match isa_builder.finish(flags) {
         Ok(target_isa) => target_isa,
         Err(err) => sess.dcx().fatal(format!("failed to build TargetIsa: {}", err)),
@@ -346,5 +596,5 @@ fn build_isa(sess: &Session, backend_config: &BackendConfig) -> Arc<dyn TargetIs
 /// This is the entrypoint for a hot plugged rustc_codegen_cranelift
 #[no_mangle]
 pub fn __rustc_codegen_backend() -> Box<dyn CodegenBackend> {
-    Box::new(CraneliftCodegenBackend { config: RefCell::new(None) })
+    Box::new(CraneliftCodegenBackend::default())
 }
@@ -3,6 +3,7 @@
 use crate::prelude::*;
 
 pub(crate) fn get_clif_linkage(
+    tcx: TyCtxt<'_>,
     mono_item: MonoItem<'_>,
     linkage: RLinkage,
     visibility: Visibility,
@@ -11,10 +12,14 @@ pub(crate) fn get_clif_linkage(
     match (linkage, visibility) {
         (RLinkage::External, Visibility::Default) if is_compiler_builtins => Linkage::Hidden,
         (RLinkage::External, Visibility::Default) => Linkage::Export,
-        (RLinkage::Internal, Visibility::Default) => Linkage::Local,
+        // Cranelift doesn't support protected visibility. Default visibility is the closest match
+        // that keeps the symbol exported.
+        (RLinkage::External, Visibility::Protected) => Linkage::Export,
         (RLinkage::External, Visibility::Hidden) => Linkage::Hidden,
-        (RLinkage::WeakAny, Visibility::Default) => Linkage::Preemptible,
-        _ => panic!("{:?} = {:?} {:?}", mono_item, linkage, visibility),
+        // Local symbols aren't visible outside of the object file anyway and Cranelift can't
+        // combine weak binding with hidden visibility. Keeping the symbol weak is more important
+        // to avoid duplicate definitions at link time.
+        (linkage, _) => get_non_external_linkage(tcx, mono_item.def_id(), linkage),
     }
 }
 
@@ -24,9 +29,7 @@ pub(crate) fn get_static_linkage(tcx: TyCtxt<'_>, def_id: DefId) -> Linkage {
     if let Some(linkage) = fn_attrs.linkage {
         match linkage {
             RLinkage::External => Linkage::Export,
-            RLinkage::Internal => Linkage::Local,
-            RLinkage::ExternalWeak | RLinkage::WeakAny => Linkage::Preemptible,
-            _ => panic!("{:?}", linkage),
+            linkage => get_non_external_linkage(tcx, def_id, linkage),
         }
     } else if tcx.is_reachable_non_generic(def_id) {
         Linkage::Export
@@ -34,3 +37,27 @@ pub(crate) fn get_static_linkage(tcx: TyCtxt<'_>, def_id: DefId) -> Linkage {
         Linkage::Hidden
     }
 }
+
+/// Translate the linkage of a definition other than [`RLinkage::External`] to the closest linkage
+/// Cranelift supports.
+fn get_non_external_linkage(tcx: TyCtxt<'_>, def_id: DefId, linkage: RLinkage) -> Linkage {
+    match linkage {
+        RLinkage::External => unreachable!(),
+        RLinkage::Internal | RLinkage::Private => Linkage::Local,
+        // The definition is only provided for inlining and is never emitted. The symbol is defined
+        // by another object file instead.
+        RLinkage::AvailableExternally => Linkage::Import,
+        // Object files can't express that a definition may be replaced by any identical
+        // definition. Weak definitions are deduplicated by the linker and give way to a strong
+        // definition, which covers all of these.
+        RLinkage::WeakAny
+        | RLinkage::WeakODR
+        | RLinkage::LinkOnceAny
+        | RLinkage::LinkOnceODR
+        | RLinkage::ExternalWeak
+        | RLinkage::Common => Linkage::Preemptible,
+        RLinkage::Appending => tcx
+            .dcx()
+            .span_fatal(tcx.def_span(def_id), "`appending` linkage is not supported by cg_clif"),
+    }
+}
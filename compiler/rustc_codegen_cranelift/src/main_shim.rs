@@ -167,6 +167,7 @@ fn create_entry_fn(
             bcx.seal_all_blocks();
             bcx.finalize();
         }
+        crate::common::apply_code_model(tcx.sess, &mut ctx.func);
 
         if let Err(err) = m.define_function(cmain_func_id, &mut ctx) {
             tcx.dcx().fatal(format!("entry symbol `{entry_name}` defined multiple times: {err}"));
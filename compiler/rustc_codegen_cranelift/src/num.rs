@@ -297,6 +297,8 @@ pub(crate) fn codegen_checked_int_binop<'tcx>(
     CValue::by_val_pair(res, has_overflow, out_layout)
 }
 
+/// Cranelift's saturating arithmetic instructions only exist for vectors, so scalars are
+/// saturated using an overflow check and a select instead.
 pub(crate) fn codegen_saturating_int_binop<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     bin_op: BinOp,
@@ -385,6 +387,31 @@ pub(crate) fn codegen_float_binop<'tcx>(
     CValue::by_val(res, in_lhs.layout())
 }
 
+/// Multiply the `isize` or `usize` offset of a `BinOp::Offset` by the pointee size, panicking if
+/// the resulting byte offset doesn't fit in an `isize`.
+fn codegen_checked_byte_offset<'tcx>(
+    fx: &mut FunctionCx<'_, '_, 'tcx>,
+    offset: CValue<'tcx>,
+    pointee_size: u64,
+) -> Value {
+    let size = fx.bcx.ins().iconst(fx.pointer_type, pointee_size as i64);
+    let size = CValue::by_val(size, offset.layout());
+    let (ptr_diff, mut has_overflow) =
+        codegen_checked_int_binop(fx, BinOp::Mul, offset, size).load_scalar_pair(fx);
+    if !type_sign(offset.layout().ty) {
+        let exceeds_isize = fx.bcx.ins().icmp_imm(IntCC::SignedLessThan, ptr_diff, 0);
+        has_overflow = fx.bcx.ins().bor(has_overflow, exceeds_isize);
+    }
+    let fits_isize = fx.bcx.ins().bxor_imm(has_overflow, 1);
+    crate::intrinsics::codegen_ub_check(
+        fx,
+        fits_isize,
+        "unsafe precondition(s) violated: ptr::offset requires the byte offset to fit in an isize",
+        None,
+    );
+    ptr_diff
+}
+
 pub(crate) fn codegen_ptr_binop<'tcx>(
     fx: &mut FunctionCx<'_, '_, 'tcx>,
     bin_op: BinOp,
@@ -406,7 +433,13 @@ pub(crate) fn codegen_ptr_binop<'tcx>(
                 let pointee_ty = in_lhs.layout().ty.builtin_deref(true).unwrap();
                 let (base, offset) = (in_lhs, in_rhs.load_scalar(fx));
                 let pointee_size = fx.layout_of(pointee_ty).size.bytes();
-                let ptr_diff = fx.bcx.ins().imul_imm(offset, pointee_size as i64);
+                // Multiplying by one can only exceed `isize` for an unsigned offset.
+                let needs_check = pointee_size > 1 || !type_sign(in_rhs.layout().ty);
+                let ptr_diff = if fx.tcx.sess.ub_checks() && needs_check {
+                    codegen_checked_byte_offset(fx, in_rhs, pointee_size)
+                } else {
+                    fx.bcx.ins().imul_imm(offset, pointee_size as i64)
+                };
                 let base_val = base.load_scalar(fx);
                 let res = fx.bcx.ins().iadd(base_val, ptr_diff);
                 CValue::by_val(res, base.layout())
@@ -452,10 +485,17 @@ pub(crate) fn codegen_ptr_binop<'tcx>(
 // For this reason it is necessary to use `a.is_nan() ? b : (a >= b ? b : a)` for `minnumf*`
 // and `a.is_nan() ? b : (a <= b ? b : a)` for `maxnumf*`. NaN checks are done by comparing
 // a float against itself. Only in case of NaN is it not equal to itself.
+//
+// -0.0 and +0.0 compare equal, so the above would return whichever zero is passed as `b`. To
+// consistently return -0.0 for min and +0.0 for max, equal inputs are combined bitwise. Equal
+// inputs other than zeros have identical bits, so this only affects the sign of zeros.
 pub(crate) fn codegen_float_min(fx: &mut FunctionCx<'_, '_, '_>, a: Value, b: Value) -> Value {
     let a_is_nan = fx.bcx.ins().fcmp(FloatCC::NotEqual, a, a);
     let a_ge_b = fx.bcx.ins().fcmp(FloatCC::GreaterThanOrEqual, a, b);
     let temp = fx.bcx.ins().select(a_ge_b, b, a);
+    let a_eq_b = fx.bcx.ins().fcmp(FloatCC::Equal, a, b);
+    let neg_zero_if_either = codegen_float_bitwise(fx, a, b, true);
+    let temp = fx.bcx.ins().select(a_eq_b, neg_zero_if_either, temp);
     fx.bcx.ins().select(a_is_nan, b, temp)
 }
 
@@ -463,5 +503,23 @@ pub(crate) fn codegen_float_max(fx: &mut FunctionCx<'_, '_, '_>, a: Value, b: Va
     let a_is_nan = fx.bcx.ins().fcmp(FloatCC::NotEqual, a, a);
     let a_le_b = fx.bcx.ins().fcmp(FloatCC::LessThanOrEqual, a, b);
     let temp = fx.bcx.ins().select(a_le_b, b, a);
+    let a_eq_b = fx.bcx.ins().fcmp(FloatCC::Equal, a, b);
+    let pos_zero_if_either = codegen_float_bitwise(fx, a, b, false);
+    let temp = fx.bcx.ins().select(a_eq_b, pos_zero_if_either, temp);
     fx.bcx.ins().select(a_is_nan, b, temp)
 }
+
+/// Computes the bitwise or (`is_or`) or and of two floats.
+fn codegen_float_bitwise(
+    fx: &mut FunctionCx<'_, '_, '_>,
+    a: Value,
+    b: Value,
+    is_or: bool,
+) -> Value {
+    let float_ty = fx.bcx.func.dfg.value_type(a);
+    let int_ty = Type::int(float_ty.bits() as u16).unwrap();
+    let a = fx.bcx.ins().bitcast(int_ty, MemFlags::new(), a);
+    let b = fx.bcx.ins().bitcast(int_ty, MemFlags::new(), b);
+    let res = if is_or { fx.bcx.ins().bor(a, b) } else { fx.bcx.ins().band(a, b) };
+    fx.bcx.ins().bitcast(float_ty, MemFlags::new(), res)
+}
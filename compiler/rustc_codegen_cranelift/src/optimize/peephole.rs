@@ -45,3 +45,17 @@ pub(crate) fn maybe_known_branch_taken(
         _ => None,
     }
 }
+
+/// Returns the value of the given integer if it is statically known or `None` otherwise.
+pub(crate) fn maybe_known_iconst(bcx: &FunctionBuilder<'_>, arg: Value) -> Option<i64> {
+    let arg_inst = if let ValueDef::Result(arg_inst, 0) = bcx.func.dfg.value_def(arg) {
+        arg_inst
+    } else {
+        return None;
+    };
+
+    match bcx.func.dfg.insts[arg_inst] {
+        InstructionData::UnaryImm { opcode: Opcode::Iconst, imm } => Some(imm.bits()),
+        _ => None,
+    }
+}
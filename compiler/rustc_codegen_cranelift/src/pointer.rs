@@ -11,6 +11,9 @@
 pub(crate) struct Pointer {
     base: PointerBase,
     offset: Offset32,
+    /// The pointee is known not to change for the duration of the function, so loads can be
+    /// marked as `readonly` for Cranelift's alias analysis.
+    readonly: bool,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -22,15 +25,19 @@ pub(crate) enum PointerBase {
 
 impl Pointer {
     pub(crate) fn new(addr: Value) -> Self {
-        Pointer { base: PointerBase::Addr(addr), offset: Offset32::new(0) }
+        Pointer { base: PointerBase::Addr(addr), offset: Offset32::new(0), readonly: false }
     }
 
     pub(crate) fn stack_slot(stack_slot: StackSlot) -> Self {
-        Pointer { base: PointerBase::Stack(stack_slot), offset: Offset32::new(0) }
+        Pointer { base: PointerBase::Stack(stack_slot), offset: Offset32::new(0), readonly: false }
     }
 
     pub(crate) fn dangling(align: Align) -> Self {
-        Pointer { base: PointerBase::Dangling(align), offset: Offset32::new(0) }
+        Pointer { base: PointerBase::Dangling(align), offset: Offset32::new(0), readonly: false }
+    }
+
+    pub(crate) fn readonly(self) -> Self {
+        Pointer { readonly: true, ..self }
     }
 
     pub(crate) fn debug_base_and_offset(self) -> (PointerBase, Offset32) {
@@ -58,7 +65,7 @@ pub(crate) fn offset(self, fx: &mut FunctionCx<'_, '_, '_>, extra_offset: Offset
 
     pub(crate) fn offset_i64(self, fx: &mut FunctionCx<'_, '_, '_>, extra_offset: i64) -> Self {
         if let Some(new_offset) = self.offset.try_add_i64(extra_offset) {
-            Pointer { base: self.base, offset: new_offset }
+            Pointer { base: self.base, offset: new_offset, readonly: self.readonly }
         } else {
             let base_offset: i64 = self.offset.into();
             if let Some(new_offset) = base_offset.checked_add(extra_offset) {
@@ -72,7 +79,11 @@ pub(crate) fn offset_i64(self, fx: &mut FunctionCx<'_, '_, '_>, extra_offset: i6
                     }
                 };
                 let addr = fx.bcx.ins().iadd_imm(base_addr, new_offset);
-                Pointer { base: PointerBase::Addr(addr), offset: Offset32::new(0) }
+                Pointer {
+                    base: PointerBase::Addr(addr),
+                    offset: Offset32::new(0),
+                    readonly: self.readonly,
+                }
             } else {
                 panic!(
                     "self.offset ({}) + extra_offset ({}) not representable in i64",
@@ -87,12 +98,14 @@ pub(crate) fn offset_value(self, fx: &mut FunctionCx<'_, '_, '_>, extra_offset:
             PointerBase::Addr(addr) => Pointer {
                 base: PointerBase::Addr(fx.bcx.ins().iadd(addr, extra_offset)),
                 offset: self.offset,
+                readonly: self.readonly,
             },
             PointerBase::Stack(stack_slot) => {
                 let base_addr = fx.bcx.ins().stack_addr(fx.pointer_type, stack_slot, self.offset);
                 Pointer {
                     base: PointerBase::Addr(fx.bcx.ins().iadd(base_addr, extra_offset)),
                     offset: Offset32::new(0),
+                    readonly: self.readonly,
                 }
             }
             PointerBase::Dangling(align) => {
@@ -101,14 +114,28 @@ pub(crate) fn offset_value(self, fx: &mut FunctionCx<'_, '_, '_>, extra_offset:
                 Pointer {
                     base: PointerBase::Addr(fx.bcx.ins().iadd(addr, extra_offset)),
                     offset: self.offset,
+                    readonly: self.readonly,
                 }
             }
         }
     }
 
-    pub(crate) fn load(self, fx: &mut FunctionCx<'_, '_, '_>, ty: Type, flags: MemFlags) -> Value {
+    pub(crate) fn load(
+        self,
+        fx: &mut FunctionCx<'_, '_, '_>,
+        ty: Type,
+        mut flags: MemFlags,
+    ) -> Value {
+        if self.readonly {
+            flags.set_readonly();
+        }
         match self.base {
-            PointerBase::Addr(base_addr) => fx.bcx.ins().load(ty, flags, base_addr, self.offset),
+            PointerBase::Addr(base_addr) => {
+                if fx.sanitize_address {
+                    self.asan_check(fx, base_addr, ty, "load");
+                }
+                fx.bcx.ins().load(ty, flags, base_addr, self.offset)
+            }
             PointerBase::Stack(stack_slot) => fx.bcx.ins().stack_load(ty, stack_slot, self.offset),
             PointerBase::Dangling(_align) => unreachable!(),
         }
@@ -117,6 +144,10 @@ pub(crate) fn load(self, fx: &mut FunctionCx<'_, '_, '_>, ty: Type, flags: MemFl
     pub(crate) fn store(self, fx: &mut FunctionCx<'_, '_, '_>, value: Value, flags: MemFlags) {
         match self.base {
             PointerBase::Addr(base_addr) => {
+                if fx.sanitize_address {
+                    let ty = fx.bcx.func.dfg.value_type(value);
+                    self.asan_check(fx, base_addr, ty, "store");
+                }
                 fx.bcx.ins().store(flags, value, base_addr, self.offset);
             }
             PointerBase::Stack(stack_slot) => {
@@ -125,4 +156,33 @@ pub(crate) fn store(self, fx: &mut FunctionCx<'_, '_, '_>, value: Value, flags:
             PointerBase::Dangling(_align) => unreachable!(),
         }
     }
+
+    /// Report an invalid access of a value of type `ty` to AddressSanitizer.
+    ///
+    /// Stack slots are not checked as they don't get redzones. Detecting overflows of locals and
+    /// statics is out of scope for now. Heap allocations get their redzones from the
+    /// AddressSanitizer runtime.
+    // FIXME poison stack slots of locals outside of their scope and add redzones around them and
+    // around globals.
+    fn asan_check(self, fx: &mut FunctionCx<'_, '_, '_>, base_addr: Value, ty: Type, kind: &str) {
+        let offset: i64 = self.offset.into();
+        let addr = if offset == 0 { base_addr } else { fx.bcx.ins().iadd_imm(base_addr, offset) };
+        let size = ty.bytes();
+        if let 1 | 2 | 4 | 8 | 16 = size {
+            fx.lib_call(
+                &format!("__asan_{kind}{size}"),
+                vec![AbiParam::new(fx.pointer_type)],
+                vec![],
+                &[addr],
+            );
+        } else {
+            let size = fx.bcx.ins().iconst(fx.pointer_type, i64::from(size));
+            fx.lib_call(
+                &format!("__asan_{kind}N"),
+                vec![AbiParam::new(fx.pointer_type), AbiParam::new(fx.pointer_type)],
+                vec![],
+                &[addr, size],
+            );
+        }
+    }
 }
@@ -57,6 +57,7 @@
 
 use std::fmt;
 use std::io::Write;
+use std::path::Path;
 
 use cranelift_codegen::entity::SecondaryMap;
 use cranelift_codegen::ir::entities::AnyEntity;
@@ -76,8 +77,7 @@ pub(crate) struct CommentWriter {
 }
 
 impl CommentWriter {
-    pub(crate) fn new<'tcx>(tcx: TyCtxt<'tcx>, instance: Instance<'tcx>) -> Self {
-        let enabled = should_write_ir(tcx);
+    pub(crate) fn new<'tcx>(tcx: TyCtxt<'tcx>, instance: Instance<'tcx>, enabled: bool) -> Self {
         let global_comments = if enabled {
             with_no_trimmed_paths!({
                 vec![
@@ -243,25 +243,55 @@ pub(crate) fn write_clif_file(
     postfix: &str,
     isa: &dyn cranelift_codegen::isa::TargetIsa,
     func: &cranelift_codegen::ir::Function,
-    mut clif_comments: &CommentWriter,
+    clif_comments: &CommentWriter,
 ) {
     // FIXME work around filename too long errors
     write_ir_file(output_filenames, &format!("{}.{}.clif", symbol_name, postfix), |file| {
-        let mut clif = String::new();
-        cranelift_codegen::write::decorate_function(&mut clif_comments, &mut clif, func).unwrap();
+        write_clif(file, isa, func, clif_comments)
+    });
+}
 
-        for flag in isa.flags().iter() {
-            writeln!(file, "set {}", flag)?;
-        }
-        write!(file, "target {}", isa.triple().architecture)?;
-        for isa_flag in isa.isa_flags().iter() {
-            write!(file, " {}", isa_flag)?;
-        }
-        writeln!(file, "\n")?;
-        writeln!(file)?;
-        file.write_all(clif.as_bytes())?;
-        Ok(())
+/// Write the clif ir of a function to `<dir>/<symbol_name>.clif` for `-Cllvm-args=emit_clif`.
+pub(crate) fn write_clif_file_to_dir(
+    dir: &Path,
+    symbol_name: &str,
+    isa: &dyn cranelift_codegen::isa::TargetIsa,
+    func: &cranelift_codegen::ir::Function,
+    clif_comments: &CommentWriter,
+) {
+    // FIXME work around filename too long errors
+    let res = std::fs::create_dir_all(dir).and_then(|()| {
+        let mut file = std::fs::File::create(dir.join(format!("{}.clif", symbol_name)))?;
+        write_clif(&mut file, isa, func, clif_comments)
     });
+    if let Err(err) = res {
+        // Using early_warn as no Session is available here
+        let handler =
+            rustc_session::EarlyDiagCtxt::new(rustc_session::config::ErrorOutputType::default());
+        handler.early_warn(format!("error writing clif file: {}", err));
+    }
+}
+
+fn write_clif(
+    file: &mut dyn Write,
+    isa: &dyn cranelift_codegen::isa::TargetIsa,
+    func: &cranelift_codegen::ir::Function,
+    mut clif_comments: &CommentWriter,
+) -> std::io::Result<()> {
+    let mut clif = String::new();
+    cranelift_codegen::write::decorate_function(&mut clif_comments, &mut clif, func).unwrap();
+
+    for flag in isa.flags().iter() {
+        writeln!(file, "set {}", flag)?;
+    }
+    write!(file, "target {}", isa.triple().architecture)?;
+    for isa_flag in isa.isa_flags().iter() {
+        write!(file, " {}", isa_flag)?;
+    }
+    writeln!(file, "\n")?;
+    writeln!(file)?;
+    file.write_all(clif.as_bytes())?;
+    Ok(())
 }
 
 impl fmt::Debug for FunctionCx<'_, '_, '_> {
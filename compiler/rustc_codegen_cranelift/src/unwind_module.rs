@@ -4,7 +4,7 @@
 use cranelift_codegen::{Context, FinalizedMachReloc};
 use cranelift_module::{
     DataDescription, DataId, FuncId, FuncOrDataId, Linkage, Module, ModuleDeclarations,
-    ModuleResult,
+    ModuleError, ModuleResult,
 };
 use cranelift_object::{ObjectModule, ObjectProduct};
 
@@ -21,6 +21,30 @@ pub(crate) fn new(module: T, pic_eh_frame: bool) -> Self {
         let unwind_context = UnwindContext::new(module.isa(), pic_eh_frame);
         UnwindModule { module, unwind_context }
     }
+
+    /// Like [`Module::define_function`], but compiles the function using the given `isa` rather
+    /// than the isa of the module. This is used for functions with `#[target_feature]`.
+    pub(crate) fn define_function_with_isa(
+        &mut self,
+        func: FuncId,
+        ctx: &mut Context,
+        isa: &dyn TargetIsa,
+    ) -> ModuleResult<()> {
+        let res = ctx
+            .compile(isa, &mut ControlPlane::default())
+            .map_err(|err| ModuleError::Compilation(err.inner))?;
+        let alignment = res.buffer.alignment as u64;
+        let compiled_code = ctx.compiled_code().unwrap();
+        self.module.define_function_bytes(
+            func,
+            &ctx.func,
+            alignment,
+            compiled_code.code_buffer(),
+            compiled_code.buffer.relocs(),
+        )?;
+        self.unwind_context.add_function(func, ctx, isa);
+        Ok(())
+    }
 }
 
 impl UnwindModule<ObjectModule> {
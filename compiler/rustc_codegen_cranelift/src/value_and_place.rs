@@ -59,6 +59,11 @@ fn scalar_pair_calculate_b_offset(tcx: TyCtxt<'_>, a_scalar: Scalar, b_scalar: S
     Offset32::new(b_offset.bytes().try_into().unwrap())
 }
 
+/// The type of the SSA variable backing a `CPlaceInner::Var` with the given layout.
+fn var_clif_type<'tcx>(fx: &FunctionCx<'_, '_, 'tcx>, layout: TyAndLayout<'tcx>) -> Type {
+    fx.clif_type(layout.ty).or_else(|| register_vector_type(fx.tcx, layout)).unwrap()
+}
+
 /// A read-only value
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct CValue<'tcx>(CValueInner, TyAndLayout<'tcx>);
@@ -220,6 +225,16 @@ pub(crate) fn value_field(
         field: FieldIdx,
     ) -> CValue<'tcx> {
         let layout = self.1;
+        if let CValueInner::ByVal(_) | CValueInner::ByValPair(_, _) = self.0 {
+            if transparent_field_ty(fx.tcx, layout.ty).is_some() {
+                // Fields of a transparent wrapper are either the wrapped value or a 1-ZST.
+                let field_layout = layout.field(&*fx, usize::from(field));
+                if field_layout.is_1zst() {
+                    return CValue::zst(field_layout);
+                }
+                return CValue(self.0, field_layout);
+            }
+        }
         match self.0 {
             CValueInner::ByVal(_) => unreachable!(),
             CValueInner::ByValPair(val1, val2) => match layout.abi {
@@ -256,7 +271,11 @@ pub(crate) fn value_lane(
         assert!(lane_idx < lane_count);
 
         match self.0 {
-            CValueInner::ByVal(_) | CValueInner::ByValPair(_, _) => unreachable!(),
+            CValueInner::ByVal(val) => {
+                let lane = fx.bcx.ins().extractlane(val, u8::try_from(lane_idx).unwrap());
+                CValue::by_val(lane, lane_layout)
+            }
+            CValueInner::ByValPair(_, _) => unreachable!(),
             CValueInner::ByRef(ptr, None) => {
                 let field_offset = lane_layout.size * lane_idx;
                 let field_ptr = ptr.offset_i64(fx, i64::try_from(field_offset.bytes()).unwrap());
@@ -283,7 +302,12 @@ pub(crate) fn value_typed_lane(
         );
 
         match self.0 {
-            CValueInner::ByVal(_) | CValueInner::ByValPair(_, _) => unreachable!(),
+            CValueInner::ByVal(_) => {
+                // Reinterpreting lanes of a vector held in a register goes through memory.
+                let (ptr, _) = self.force_stack(fx);
+                CValue::by_ref(ptr, layout).value_typed_lane(fx, lane_ty, lane_idx)
+            }
+            CValueInner::ByValPair(_, _) => unreachable!(),
             CValueInner::ByRef(ptr, None) => {
                 let field_offset = lane_layout.size * lane_idx;
                 let field_ptr = ptr.offset_i64(fx, i64::try_from(field_offset.bytes()).unwrap());
@@ -304,7 +328,11 @@ pub(crate) fn value_lane_dyn(
         let (_lane_count, lane_ty) = layout.ty.simd_size_and_type(fx.tcx);
         let lane_layout = fx.layout_of(lane_ty);
         match self.0 {
-            CValueInner::ByVal(_) | CValueInner::ByValPair(_, _) => unreachable!(),
+            CValueInner::ByVal(_) => {
+                let (ptr, _) = self.force_stack(fx);
+                CValue::by_ref(ptr, layout).value_lane_dyn(fx, lane_idx)
+            }
+            CValueInner::ByValPair(_, _) => unreachable!(),
             CValueInner::ByRef(ptr, None) => {
                 let field_offset = fx.bcx.ins().imul_imm(lane_idx, lane_layout.size.bytes() as i64);
                 let field_ptr = ptr.offset_value(fx, field_offset);
@@ -323,6 +351,11 @@ pub(crate) fn const_val(
         assert_eq!(const_val.size(), layout.size, "{:#?}: {:?}", const_val, layout);
         use cranelift_codegen::ir::immediates::{Ieee32, Ieee64};
 
+        if let Some(field_ty) = transparent_field_ty(fx.tcx, layout.ty) {
+            let field_val = CValue::const_val(fx, fx.layout_of(field_ty), const_val);
+            return CValue(field_val.0, layout);
+        }
+
         let clif_ty = fx.clif_type(layout.ty).unwrap();
 
         let val = match layout.ty.kind() {
@@ -342,12 +375,24 @@ pub(crate) fn const_val(
                 let raw_val = const_val.size().truncate(const_val.to_bits(layout.size));
                 fx.bcx.ins().iconst(clif_ty, raw_val as i64)
             }
+            ty::Float(FloatTy::F16) => {
+                let raw_val =
+                    fx.bcx.ins().iconst(types::I16, u16::try_from(const_val).unwrap() as i64);
+                fx.bcx.ins().bitcast(types::F16, MemFlags::new(), raw_val)
+            }
             ty::Float(FloatTy::F32) => {
                 fx.bcx.ins().f32const(Ieee32::with_bits(u32::try_from(const_val).unwrap()))
             }
             ty::Float(FloatTy::F64) => {
                 fx.bcx.ins().f64const(Ieee64::with_bits(u64::try_from(const_val).unwrap()))
             }
+            ty::Float(FloatTy::F128) => {
+                let const_val = u128::try_from(const_val).unwrap();
+                let lsb = fx.bcx.ins().iconst(types::I64, const_val as u64 as i64);
+                let msb = fx.bcx.ins().iconst(types::I64, (const_val >> 64) as u64 as i64);
+                let raw_val = fx.bcx.ins().iconcat(lsb, msb);
+                fx.bcx.ins().bitcast(types::F128, MemFlags::new(), raw_val)
+            }
             _ => panic!(
                 "CValue::const_val for non bool/char/float/integer/pointer type {:?} is not allowed",
                 layout.ty
@@ -416,7 +461,7 @@ pub(crate) fn new_var(
     ) -> CPlace<'tcx> {
         let var = Variable::from_u32(fx.next_ssa_var);
         fx.next_ssa_var += 1;
-        fx.bcx.declare_var(var, fx.clif_type(layout.ty).unwrap());
+        fx.bcx.declare_var(var, var_clif_type(fx, layout));
         CPlace { inner: CPlaceInner::Var(local, var), layout }
     }
 
@@ -440,6 +485,16 @@ pub(crate) fn for_ptr(ptr: Pointer, layout: TyAndLayout<'tcx>) -> CPlace<'tcx> {
         CPlace { inner: CPlaceInner::Addr(ptr, None), layout }
     }
 
+    /// Mark the memory this place points to as not changing for the rest of the function.
+    pub(crate) fn readonly(self) -> CPlace<'tcx> {
+        match self.inner {
+            CPlaceInner::Addr(ptr, extra) => {
+                CPlace { inner: CPlaceInner::Addr(ptr.readonly(), extra), layout: self.layout }
+            }
+            CPlaceInner::Var(..) | CPlaceInner::VarPair(..) => bug!("readonly on {:?}", self),
+        }
+    }
+
     pub(crate) fn for_ptr_with_extra(
         ptr: Pointer,
         extra: Value,
@@ -453,14 +508,14 @@ pub(crate) fn to_cvalue(self, fx: &mut FunctionCx<'_, '_, 'tcx>) -> CValue<'tcx>
         match self.inner {
             CPlaceInner::Var(_local, var) => {
                 let val = fx.bcx.use_var(var);
-                //fx.bcx.set_val_label(val, cranelift_codegen::ir::ValueLabel::new(var.index()));
+                fx.bcx.set_val_label(val, cranelift_codegen::ir::ValueLabel::new(var.index()));
                 CValue::by_val(val, layout)
             }
             CPlaceInner::VarPair(_local, var1, var2) => {
                 let val1 = fx.bcx.use_var(var1);
-                //fx.bcx.set_val_label(val1, cranelift_codegen::ir::ValueLabel::new(var1.index()));
+                fx.bcx.set_val_label(val1, cranelift_codegen::ir::ValueLabel::new(var1.index()));
                 let val2 = fx.bcx.use_var(var2);
-                //fx.bcx.set_val_label(val2, cranelift_codegen::ir::ValueLabel::new(var2.index()));
+                fx.bcx.set_val_label(val2, cranelift_codegen::ir::ValueLabel::new(var2.index()));
                 CValue::by_val_pair(val1, val2, layout)
             }
             CPlaceInner::Addr(ptr, extra) => {
@@ -518,6 +573,14 @@ pub(crate) fn to_ptr_unsized(self) -> (Pointer, Value) {
         }
     }
 
+    /// Returns the SSA variable backing this place if it is stored in a single variable.
+    pub(crate) fn try_to_var(self) -> Option<Variable> {
+        match self.inner {
+            CPlaceInner::Var(_, var) => Some(var),
+            CPlaceInner::VarPair(_, _, _) | CPlaceInner::Addr(_, _) => None,
+        }
+    }
+
     pub(crate) fn try_to_ptr(self) -> Option<Pointer> {
         match self.inner {
             CPlaceInner::Var(_, _) | CPlaceInner::VarPair(_, _, _) => None,
@@ -581,7 +644,7 @@ fn transmute_scalar<'tcx>(
                 // calling `write_cvalue` you need to add a `bint` instruction.
                 _ => unreachable!("write_cvalue_transmute: {:?} -> {:?}", src_ty, dst_ty),
             };
-            //fx.bcx.set_val_label(data, cranelift_codegen::ir::ValueLabel::new(var.index()));
+            fx.bcx.set_val_label(data, cranelift_codegen::ir::ValueLabel::new(var.index()));
             fx.bcx.def_var(var, data);
         }
 
@@ -610,14 +673,16 @@ fn transmute_scalar<'tcx>(
         match self.inner {
             CPlaceInner::Var(_local, var) => {
                 let data = match from.1.abi {
-                    Abi::Scalar(_) => CValue(from.0, dst_layout).load_scalar(fx),
+                    Abi::Scalar(_) | Abi::Vector { .. } => {
+                        CValue(from.0, dst_layout).load_scalar(fx)
+                    }
                     _ => {
                         let (ptr, meta) = from.force_stack(fx);
                         assert!(meta.is_none());
                         CValue(CValueInner::ByRef(ptr, None), dst_layout).load_scalar(fx)
                     }
                 };
-                let dst_ty = fx.clif_type(self.layout().ty).unwrap();
+                let dst_ty = var_clif_type(fx, self.layout());
                 transmute_scalar(fx, var, data, dst_ty);
             }
             CPlaceInner::VarPair(_local, var1, var2) => {
@@ -713,6 +778,20 @@ pub(crate) fn place_field(
     ) -> CPlace<'tcx> {
         let layout = self.layout();
 
+        if let CPlaceInner::Var(_, _) | CPlaceInner::VarPair(_, _, _) = self.inner {
+            if transparent_field_ty(fx.tcx, layout.ty).is_some() {
+                // Fields of a transparent wrapper are either the wrapped value or a 1-ZST.
+                let field_layout = layout.field(&*fx, field.index());
+                if field_layout.is_1zst() {
+                    return CPlace::for_ptr(
+                        Pointer::dangling(field_layout.align.pref),
+                        field_layout,
+                    );
+                }
+                return CPlace { inner: self.inner, layout: field_layout };
+            }
+        }
+
         match self.inner {
             CPlaceInner::VarPair(local, var1, var2) => {
                 let layout = layout.field(&*fx, field.index());